@@ -0,0 +1,66 @@
+//! BuildCS construction time with the linked list and the vector linear combinations
+//!
+//! ```sh
+//! cargo bench --bench build_cs
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use fawkes_crypto::{
+    circuit::{
+        cs::CS,
+        eddsaposeidon::c_eddsaposeidon_verify,
+        num::CNum,
+        poseidon::{c_poseidon_merkle_proof_root, CMerkleProof},
+        r1cs::{
+            cs::BuildCS,
+            lc::{AbstractLC, VecLC, LC},
+        },
+    },
+    core::signal::Signal,
+    engines::bn256::{Fr, JubJubBN256},
+    native::poseidon::PoseidonParams,
+};
+
+fn merkle_proof<L: AbstractLC<Fr>>(poseidon_params: &PoseidonParams<Fr>) -> usize {
+    let ref cs = BuildCS::<Fr, L>::rc_new();
+    let leaf = CNum::alloc(cs, None);
+    let proof = CMerkleProof::<_, 32>::alloc(cs, None);
+    c_poseidon_merkle_proof_root(&leaf, &proof, poseidon_params).inputize();
+    let res = cs.borrow().num_gates();
+    res
+}
+
+fn eddsa<L: AbstractLC<Fr>>(poseidon_params: &PoseidonParams<Fr>, jubjub_params: &JubJubBN256) -> usize {
+    let ref cs = BuildCS::<Fr, L>::rc_new();
+    let (s, r, a, m) = <(CNum<_>, CNum<_>, CNum<_>, CNum<_>)>::alloc(cs, None);
+    c_eddsaposeidon_verify(&s, &r, &a, &m, poseidon_params, jubjub_params).inputize();
+    let res = cs.borrow().num_gates();
+    res
+}
+
+fn bench_build_cs(c: &mut Criterion) {
+    let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+    let jubjub_params = JubJubBN256::new();
+
+    let mut group = c.benchmark_group("build_cs");
+    group.sample_size(10);
+
+    group.bench_function(BenchmarkId::new("poseidon_merkle_32", "linked_list"), |b| {
+        b.iter(|| merkle_proof::<LC<Fr>>(&poseidon_params))
+    });
+    group.bench_function(BenchmarkId::new("poseidon_merkle_32", "vec"), |b| {
+        b.iter(|| merkle_proof::<VecLC<Fr>>(&poseidon_params))
+    });
+
+    group.bench_function(BenchmarkId::new("eddsa_poseidon", "linked_list"), |b| {
+        b.iter(|| eddsa::<LC<Fr>>(&poseidon_params, &jubjub_params))
+    });
+    group.bench_function(BenchmarkId::new("eddsa_poseidon", "vec"), |b| {
+        b.iter(|| eddsa::<VecLC<Fr>>(&poseidon_params, &jubjub_params))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_build_cs);
+criterion_main!(benches);
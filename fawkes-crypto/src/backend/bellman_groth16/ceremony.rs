@@ -0,0 +1,487 @@
+//! Phase 2 of the Groth16 trusted setup ceremony
+//!
+//! Initial parameters are computed from phase 1 powers of tau in Lagrange form (the `phase1radix2m*`
+//! layout of the powersoftau ceremony) with `delta = 1`. Every participant multiplies delta by a secret
+//! and publishes a public key proving knowledge of it, so each contribution can be checked against the
+//! previous one. The result is ordinary `Parameters`, written and read in the same layout as before.
+
+use super::osrng::OsRng;
+use super::setup::build_parameters;
+use super::*;
+use crate::{circuit::r1cs::{cs::BuildCS, lc::AbstractLC}, error::Error};
+
+use bellman::{
+    pairing::{
+        ff::{Field, PrimeField as BellmanPrimeField},
+        CurveAffine, CurveProjective, EncodedPoint, Engine as PairingEngine,
+    },
+    rand::{ChaChaRng, Rng, SeedableRng},
+};
+use blake2_rfc::blake2b::Blake2b;
+use std::{
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+type BE<E> = <E as Engine>::BE;
+type G1<E> = <BE<E> as PairingEngine>::G1;
+type G2<E> = <BE<E> as PairingEngine>::G2;
+type G1Affine<E> = <BE<E> as PairingEngine>::G1Affine;
+type G2Affine<E> = <BE<E> as PairingEngine>::G2Affine;
+type BFr<E> = <BE<E> as bellman::pairing::ScalarEngine>::Fr;
+
+fn write_point<C: CurveAffine, W: Write>(writer: &mut W, p: &C) -> io::Result<()> {
+    writer.write_all(p.into_uncompressed().as_ref())
+}
+
+fn read_point<C: CurveAffine, R: Read>(reader: &mut R) -> io::Result<C> {
+    let mut repr = C::Uncompressed::empty();
+    reader.read_exact(repr.as_mut())?;
+    repr.into_affine().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_points<C: CurveAffine, R: Read>(reader: &mut R, n: usize) -> io::Result<Vec<C>> {
+    (0..n).map(|_| read_point(reader)).collect()
+}
+
+fn blake2b(data: &[u8]) -> [u8; 64] {
+    let mut res = [0u8; 64];
+    let mut h = Blake2b::new(64);
+    h.update(data);
+    res.copy_from_slice(h.finalize().as_bytes());
+    res
+}
+
+fn seeded_rng(digest: &[u8; 64]) -> ChaChaRng {
+    let seed = digest[..32]
+        .chunks(4)
+        .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+        .collect::<Vec<_>>();
+    ChaChaRng::from_seed(&seed[..])
+}
+
+fn hash_to_g2<E: Engine>(digest: &[u8; 64]) -> G2Affine<E> {
+    seeded_rng(digest).gen::<G2<E>>().into_affine()
+}
+
+// e(g1.0, g2.1) == e(g1.1, g2.0)
+fn same_ratio<E: Engine>(g1: (G1Affine<E>, G1Affine<E>), g2: (G2Affine<E>, G2Affine<E>)) -> bool {
+    <BE<E> as PairingEngine>::pairing(g1.0, g2.1) == <BE<E> as PairingEngine>::pairing(g1.1, g2.0)
+}
+
+// random linear combination of both vectors with the same coefficients
+fn merge_pairs<E: Engine>(v1: &[G1Affine<E>], v2: &[G1Affine<E>]) -> (G1Affine<E>, G1Affine<E>) {
+    let ref mut rng = OsRng::new();
+    let mut s = G1::<E>::zero();
+    let mut sx = G1::<E>::zero();
+    for (a, b) in v1.iter().zip(v2.iter()) {
+        let rho: BFr<E> = rng.gen();
+        s.add_assign(&a.mul(rho.into_repr()));
+        sx.add_assign(&b.mul(rho.into_repr()));
+    }
+    (s.into_affine(), sx.into_affine())
+}
+
+fn batch_mul<C: CurveAffine>(points: &[C], k: C::Scalar) -> Vec<C> {
+    points.iter().map(|p| p.mul(k.into_repr()).into_affine()).collect()
+}
+
+/// Phase 1 powers of tau evaluated at the Lagrange basis of a radix-2 domain of size `m`
+pub struct Phase1<E: Engine> {
+    pub alpha_g1: G1Affine<E>,
+    pub beta_g1: G1Affine<E>,
+    pub beta_g2: G2Affine<E>,
+    // L_i(tau)
+    pub coeffs_g1: Vec<G1Affine<E>>,
+    pub coeffs_g2: Vec<G2Affine<E>>,
+    // alpha * L_i(tau), beta * L_i(tau)
+    pub alpha_coeffs_g1: Vec<G1Affine<E>>,
+    pub beta_coeffs_g1: Vec<G1Affine<E>>,
+    // tau^i * (tau^m - 1), i < m - 1
+    pub h: Vec<G1Affine<E>>,
+}
+
+impl<E: Engine> Phase1<E> {
+    /// Single party phase 1 with locally generated toxic waste, for tests and development only
+    pub fn insecure_new<R: Rng>(m: usize, rng: &mut R) -> Result<Self, Error> {
+        if !m.is_power_of_two() || m.trailing_zeros() > BFr::<E>::S {
+            return Err(Error::Setup("domain size should be a supported power of two".to_string()));
+        }
+        let tau: BFr<E> = rng.gen();
+        let alpha: BFr<E> = rng.gen();
+        let beta: BFr<E> = rng.gen();
+
+        let mut omega = BFr::<E>::root_of_unity();
+        for _ in m.trailing_zeros()..BFr::<E>::S {
+            omega.square();
+        }
+
+        // Z(tau) / m
+        let mut z = tau.pow([m as u64]);
+        z.sub_assign(&BFr::<E>::one());
+        let mut z_m = z;
+        z_m.mul_assign(&BFr::<E>::from_repr(<BFr<E> as BellmanPrimeField>::Repr::from(m as u64)).unwrap().inverse().unwrap());
+
+        let g1 = G1Affine::<E>::one();
+        let g2 = G2Affine::<E>::one();
+        let (mut coeffs_g1, mut coeffs_g2) = (Vec::with_capacity(m), Vec::with_capacity(m));
+        let (mut alpha_coeffs_g1, mut beta_coeffs_g1) = (Vec::with_capacity(m), Vec::with_capacity(m));
+
+        // L_i(tau) = Z(tau) / m * omega^i / (tau - omega^i)
+        let mut omega_i = BFr::<E>::one();
+        for _ in 0..m {
+            let mut l = tau;
+            l.sub_assign(&omega_i);
+            let mut l = l.inverse().ok_or_else(|| Error::Setup("tau is in the domain".to_string()))?;
+            l.mul_assign(&omega_i);
+            l.mul_assign(&z_m);
+
+            coeffs_g1.push(g1.mul(l.into_repr()).into_affine());
+            coeffs_g2.push(g2.mul(l.into_repr()).into_affine());
+            let mut al = l;
+            al.mul_assign(&alpha);
+            alpha_coeffs_g1.push(g1.mul(al.into_repr()).into_affine());
+            let mut bl = l;
+            bl.mul_assign(&beta);
+            beta_coeffs_g1.push(g1.mul(bl.into_repr()).into_affine());
+
+            omega_i.mul_assign(&omega);
+        }
+
+        let mut h = Vec::with_capacity(m - 1);
+        let mut t = z;
+        for _ in 0..m - 1 {
+            h.push(g1.mul(t.into_repr()).into_affine());
+            t.mul_assign(&tau);
+        }
+
+        Ok(Self {
+            alpha_g1: g1.mul(alpha.into_repr()).into_affine(),
+            beta_g1: g1.mul(beta.into_repr()).into_affine(),
+            beta_g2: g2.mul(beta.into_repr()).into_affine(),
+            coeffs_g1,
+            coeffs_g2,
+            alpha_coeffs_g1,
+            beta_coeffs_g1,
+            h,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_point(writer, &self.alpha_g1)?;
+        write_point(writer, &self.beta_g1)?;
+        write_point(writer, &self.beta_g2)?;
+        self.coeffs_g1.iter().try_for_each(|p| write_point(writer, p))?;
+        self.coeffs_g2.iter().try_for_each(|p| write_point(writer, p))?;
+        self.alpha_coeffs_g1.iter().try_for_each(|p| write_point(writer, p))?;
+        self.beta_coeffs_g1.iter().try_for_each(|p| write_point(writer, p))?;
+        self.h.iter().try_for_each(|p| write_point(writer, p))
+    }
+
+    /// Read uncompressed `phase1radix2m{log2(m)}` transcript
+    pub fn read<R: Read>(reader: &mut R, m: usize) -> io::Result<Self> {
+        if !m.is_power_of_two() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "domain size should be a power of two"));
+        }
+        Ok(Self {
+            alpha_g1: read_point(reader)?,
+            beta_g1: read_point(reader)?,
+            beta_g2: read_point(reader)?,
+            coeffs_g1: read_points(reader, m)?,
+            coeffs_g2: read_points(reader, m)?,
+            alpha_coeffs_g1: read_points(reader, m)?,
+            beta_coeffs_g1: read_points(reader, m)?,
+            h: read_points(reader, m - 1)?,
+        })
+    }
+}
+
+/// Size of the phase 1 transcript required for `cs`
+pub fn domain_size<Fr: PrimeField, L: AbstractLC<Fr>>(cs: &BuildCS<Fr, L>) -> usize {
+    // bellman appends `x * 0 = 0` constraint for every input
+    (cs.gates.len() + cs.num_input).next_power_of_two()
+}
+
+/// Proof of knowledge of the delta multiplier of a single contribution
+pub struct PublicKey<E: Engine> {
+    pub delta_after: G1Affine<E>,
+    pub s: G1Affine<E>,
+    pub s_delta: G1Affine<E>,
+    pub r_delta: G2Affine<E>,
+    pub transcript: [u8; 64],
+}
+
+impl<E: Engine> PartialEq for PublicKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta_after == other.delta_after
+            && self.s == other.s
+            && self.s_delta == other.s_delta
+            && self.r_delta == other.r_delta
+            && self.transcript[..] == other.transcript[..]
+    }
+}
+
+impl<E: Engine> PublicKey<E> {
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_point(writer, &self.delta_after)?;
+        write_point(writer, &self.s)?;
+        write_point(writer, &self.s_delta)?;
+        write_point(writer, &self.r_delta)?;
+        writer.write_all(&self.transcript)
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let delta_after = read_point(reader)?;
+        let s = read_point(reader)?;
+        let s_delta = read_point(reader)?;
+        let r_delta = read_point(reader)?;
+        let mut transcript = [0u8; 64];
+        reader.read_exact(&mut transcript)?;
+        Ok(Self { delta_after, s, s_delta, r_delta, transcript })
+    }
+
+    /// Contribution hash, published by the participant
+    pub fn hash(&self) -> [u8; 64] {
+        let mut buf = vec![];
+        self.write(&mut buf).unwrap();
+        blake2b(&buf)
+    }
+}
+
+/// Groth16 parameters under construction together with the chain of contributions
+pub struct MPCParameters<E: Engine> {
+    pub params: Parameters<E>,
+    // hash of the initial parameters, identifies the circuit and phase 1 transcript
+    pub cs_hash: [u8; 64],
+    pub contributions: Vec<PublicKey<E>>,
+}
+
+impl<E: Engine> MPCParameters<E> {
+    pub fn new<Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec)>(
+        circuit: C,
+        phase1: &Phase1<E>,
+    ) -> Result<Self, Error> {
+        let ref rcs = BuildCS::<E::Fr>::rc_new();
+        let signal_pub = Pub::alloc(rcs, None);
+        signal_pub.inputize();
+        let signal_sec = Sec::alloc(rcs, None);
+
+        circuit(signal_pub, signal_sec);
+
+        Self::new_cs(rcs, phase1)
+    }
+
+    /// Initial parameters with `delta = 1` for prebuilt constraint system
+    pub fn new_cs(rcs: &RCS<BuildCS<E::Fr>>, phase1: &Phase1<E>) -> Result<Self, Error> {
+        let cs = rcs.borrow();
+        let num_input = cs.num_input;
+        let num_vars = cs.num_input + cs.num_aux;
+
+        let m = domain_size(&cs);
+        if phase1.coeffs_g1.len() != m || phase1.h.len() != m - 1 {
+            return Err(Error::Setup(format!("phase 1 transcript for domain size {} is required", m)));
+        }
+
+        let var = |i: Index| match i {
+            Index::Input(i) => i as usize,
+            Index::Aux(i) => num_input + i as usize,
+        };
+
+        let mut a = vec![G1::<E>::zero(); num_vars];
+        let mut b_g1 = vec![G1::<E>::zero(); num_vars];
+        let mut b_g2 = vec![G2::<E>::zero(); num_vars];
+        // beta * u_i(tau) + alpha * v_i(tau) + w_i(tau)
+        let mut ext = vec![G1::<E>::zero(); num_vars];
+
+        for (row, g) in cs.gates.iter().enumerate() {
+            for (k, i) in g.0.iter() {
+                let k: BFr<E> = num_to_bellman_fp(*k);
+                a[var(*i)].add_assign(&phase1.coeffs_g1[row].mul(k.into_repr()));
+                ext[var(*i)].add_assign(&phase1.beta_coeffs_g1[row].mul(k.into_repr()));
+            }
+            for (k, i) in g.1.iter() {
+                let k: BFr<E> = num_to_bellman_fp(*k);
+                b_g1[var(*i)].add_assign(&phase1.coeffs_g1[row].mul(k.into_repr()));
+                b_g2[var(*i)].add_assign(&phase1.coeffs_g2[row].mul(k.into_repr()));
+                ext[var(*i)].add_assign(&phase1.alpha_coeffs_g1[row].mul(k.into_repr()));
+            }
+            for (k, i) in g.2.iter() {
+                let k: BFr<E> = num_to_bellman_fp(*k);
+                ext[var(*i)].add_assign(&phase1.coeffs_g1[row].mul(k.into_repr()));
+            }
+        }
+        for i in 0..num_input {
+            let row = cs.gates.len() + i;
+            a[i].add_assign(&phase1.coeffs_g1[row].into_projective());
+            ext[i].add_assign(&phase1.beta_coeffs_g1[row].into_projective());
+        }
+
+        let to_affine = |v: Vec<G1<E>>| v.into_iter().map(|p| p.into_affine()).collect::<Vec<_>>();
+        // points at infinity are filtered away from A/B queries, the same way bellman does
+        let non_zero = |v: Vec<G1<E>>| to_affine(v.into_iter().filter(|p| !p.is_zero()).collect());
+        let l = to_affine(ext.split_off(num_input));
+
+        let vk = bellman::groth16::VerifyingKey {
+            alpha_g1: phase1.alpha_g1,
+            beta_g1: phase1.beta_g1,
+            beta_g2: phase1.beta_g2,
+            gamma_g2: G2Affine::<E>::one(),
+            delta_g1: G1Affine::<E>::one(),
+            delta_g2: G2Affine::<E>::one(),
+            ic: to_affine(ext),
+        };
+        let bp = bellman::groth16::Parameters {
+            vk,
+            h: Arc::new(phase1.h.clone()),
+            l: Arc::new(l),
+            a: Arc::new(non_zero(a)),
+            b_g1: Arc::new(non_zero(b_g1)),
+            b_g2: Arc::new(b_g2.into_iter().filter(|p| !p.is_zero()).map(|p| p.into_affine()).collect()),
+        };
+        let params = build_parameters(bp, &cs)?;
+
+        let mut buf = vec![];
+        params.write(&mut buf)?;
+        Ok(Self { params, cs_hash: blake2b(&buf), contributions: vec![] })
+    }
+
+    // transcript hash binds the contribution to the circuit and all previous contributions
+    fn transcript(&self, s: &G1Affine<E>, s_delta: &G1Affine<E>) -> [u8; 64] {
+        let mut buf = self.cs_hash.to_vec();
+        for c in self.contributions.iter() {
+            c.write(&mut buf).unwrap();
+        }
+        write_point(&mut buf, s).unwrap();
+        write_point(&mut buf, s_delta).unwrap();
+        blake2b(&buf)
+    }
+
+    fn keypair<R: Rng>(&self, rng: &mut R) -> (PublicKey<E>, BFr<E>) {
+        let delta: BFr<E> = rng.gen();
+        let s = rng.gen::<G1<E>>().into_affine();
+        let s_delta = s.mul(delta.into_repr()).into_affine();
+        let transcript = self.transcript(&s, &s_delta);
+        let r_delta = hash_to_g2::<E>(&transcript).mul(delta.into_repr()).into_affine();
+        let delta_after = self.params.params.vk.delta_g1.mul(delta.into_repr()).into_affine();
+        (PublicKey { delta_after, s, s_delta, r_delta, transcript }, delta)
+    }
+
+    /// Multiply delta by a fresh secret and return the contribution hash. The secret is dropped
+    /// after the call, so `rng` should be a secure one.
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> [u8; 64] {
+        let (pubkey, delta) = self.keypair(rng);
+        let delta_inv = delta.inverse().expect("delta should not be zero");
+
+        let p = &mut self.params.params;
+        p.h = Arc::new(batch_mul(&p.h, delta_inv));
+        p.l = Arc::new(batch_mul(&p.l, delta_inv));
+        p.vk.delta_g1 = p.vk.delta_g1.mul(delta.into_repr()).into_affine();
+        p.vk.delta_g2 = p.vk.delta_g2.mul(delta.into_repr()).into_affine();
+
+        let hash = pubkey.hash();
+        self.contributions.push(pubkey);
+        hash
+    }
+
+    /// Final contribution derived from a public random beacon value
+    pub fn beacon(&mut self, beacon: &[u8]) -> [u8; 64] {
+        self.contribute(&mut seeded_rng(&blake2b(beacon)))
+    }
+
+    pub fn into_params(self) -> Parameters<E> {
+        self.params
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.params.write(writer)?;
+        writer.write_all(&self.cs_hash)?;
+        BorshSerialize::serialize(&(self.contributions.len() as u32), writer)?;
+        self.contributions.iter().try_for_each(|c| c.write(writer))
+    }
+
+    pub fn read(reader: &mut &[u8], checked: bool) -> io::Result<Self> {
+        let params = Parameters::read(reader, false, checked)?;
+        let mut cs_hash = [0u8; 64];
+        reader.read_exact(&mut cs_hash)?;
+        let n = <u32 as BorshDeserialize>::deserialize(reader)?;
+        let contributions = (0..n).map(|_| PublicKey::read(reader)).collect::<io::Result<_>>()?;
+        Ok(Self { params, cs_hash, contributions })
+    }
+}
+
+/// Check that `after` is `before` with exactly one valid contribution and return its hash
+pub fn verify_contribution<E: Engine>(before: &MPCParameters<E>, after: &MPCParameters<E>) -> Result<[u8; 64], Error> {
+    let err = |msg: &str| Err(Error::Verification(msg.to_string()));
+
+    if before.cs_hash[..] != after.cs_hash[..] {
+        return err("circuit hash mismatch");
+    }
+    if after.contributions.len() != before.contributions.len() + 1
+        || before.contributions.iter().zip(after.contributions.iter()).any(|(a, b)| a != b)
+    {
+        return err("contribution chain mismatch");
+    }
+
+    let (b, a) = (&before.params, &after.params);
+    if b.num_gates != a.num_gates
+        || b.gates != a.gates
+        || b.const_tracker != a.const_tracker
+        || b.aux_mask != a.aux_mask
+        || b.fingerprint != a.fingerprint
+        || b.checkpoints != a.checkpoints
+    {
+        return err("circuit mismatch");
+    }
+    let (b, a) = (&b.params, &a.params);
+    if b.vk.alpha_g1 != a.vk.alpha_g1
+        || b.vk.beta_g1 != a.vk.beta_g1
+        || b.vk.beta_g2 != a.vk.beta_g2
+        || b.vk.gamma_g2 != a.vk.gamma_g2
+        || b.vk.ic != a.vk.ic
+        || b.a != a.a
+        || b.b_g1 != a.b_g1
+        || b.b_g2 != a.b_g2
+    {
+        return err("only delta dependent elements may change");
+    }
+    if b.h.len() != a.h.len() || b.l.len() != a.l.len() {
+        return err("query length mismatch");
+    }
+
+    let pubkey = after.contributions.last().unwrap();
+    if pubkey.delta_after != a.vk.delta_g1 || pubkey.s.is_zero() || pubkey.delta_after.is_zero() {
+        return err("invalid delta");
+    }
+    if before.transcript(&pubkey.s, &pubkey.s_delta)[..] != pubkey.transcript[..] {
+        return err("invalid transcript");
+    }
+
+    let r = hash_to_g2::<E>(&pubkey.transcript);
+    if !same_ratio::<E>((pubkey.s, pubkey.s_delta), (r, pubkey.r_delta)) {
+        return err("invalid proof of knowledge");
+    }
+    if !same_ratio::<E>((b.vk.delta_g1, a.vk.delta_g1), (r, pubkey.r_delta)) {
+        return err("delta is not derived from the proven secret");
+    }
+    if !same_ratio::<E>((G1Affine::<E>::one(), a.vk.delta_g1), (G2Affine::<E>::one(), a.vk.delta_g2)) {
+        return err("inconsistent delta in G1 and G2");
+    }
+    if !same_ratio::<E>(merge_pairs::<E>(&a.h, &b.h), (b.vk.delta_g2, a.vk.delta_g2)) {
+        return err("invalid H query");
+    }
+    if !same_ratio::<E>(merge_pairs::<E>(&a.l, &b.l), (b.vk.delta_g2, a.vk.delta_g2)) {
+        return err("invalid L query");
+    }
+
+    Ok(pubkey.hash())
+}
+
+/// Same as `verify_contribution`, and also check that the contribution was derived from `beacon`
+pub fn verify_beacon<E: Engine>(before: &MPCParameters<E>, after: &MPCParameters<E>, beacon: &[u8]) -> Result<[u8; 64], Error> {
+    let hash = verify_contribution(before, after)?;
+    let (pubkey, _) = before.keypair(&mut seeded_rng(&blake2b(beacon)));
+    if &pubkey != after.contributions.last().unwrap() {
+        return Err(Error::Verification("contribution is not derived from the beacon".to_string()));
+    }
+    Ok(hash)
+}
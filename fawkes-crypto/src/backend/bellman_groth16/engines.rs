@@ -7,16 +7,20 @@ pub trait Engine {
     type BE: bellman::pairing::Engine;
     type Fq: PrimeField;
     type Fr: PrimeField;
+    // curve name used by snarkjs
+    const CURVE_NAME: &'static str;
 }
 
 impl Engine for Bn256 {
     type BE = bellman::pairing::bn256::Bn256;
     type Fq = crate::engines::bn256::Fq;
     type Fr = crate::engines::bn256::Fr;
+    const CURVE_NAME: &'static str = "bn128";
 }
 
 impl Engine for Bls12_381 {
     type BE = bellman::pairing::bls12_381::Bls12;
     type Fq = crate::engines::bls12_381::Fq;
     type Fr = crate::engines::bls12_381::Fr;
+    const CURVE_NAME: &'static str = "bls12381";
 }
@@ -7,6 +7,7 @@ use serde::{Serialize, Deserialize};
 use bellman::pairing::{CurveAffine, RawEncodable};
 use std::io::Cursor;
 use super::engines::Engine;
+use crate::error::Error;
 use ff_uint::Num;
 
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
@@ -52,8 +53,12 @@ impl<E: Engine> BorshDeserialize for G2Point<E> {
 
 impl<E: Engine> G1Point<E> {
     pub fn to_bellman(&self) -> <E::BE as bellman::pairing::Engine>::G1Affine {
+        self.try_to_bellman().unwrap()
+    }
+
+    pub fn try_to_bellman(&self) -> Result<<E::BE as bellman::pairing::Engine>::G1Affine, Error> {
         if self.0 == Num::ZERO && self.1 == Num::ZERO {
-            <E::BE as bellman::pairing::Engine>::G1Affine::zero()
+            Ok(<E::BE as bellman::pairing::Engine>::G1Affine::zero())
         } else {
             let mut buf =
                 <E::BE as bellman::pairing::Engine>::G1Affine::zero().into_raw_uncompressed_le();
@@ -63,7 +68,7 @@ impl<E: Engine> G1Point<E> {
                 BorshSerialize::serialize(&self.1.to_mont_uint(), &mut cur).unwrap();
             }
             <E::BE as bellman::pairing::Engine>::G1Affine::from_raw_uncompressed_le(&buf, false)
-                .unwrap()
+                .map_err(|e| Error::invalid_data(format!("invalid G1 point: {:?}", e)))
         }
     }
 
@@ -86,12 +91,16 @@ pub struct G2Point<E: Engine>(pub (Num<E::Fq>, Num<E::Fq>), pub (Num<E::Fq>, Num
 
 impl<E: Engine> G2Point<E> {
     pub fn to_bellman(&self) -> <E::BE as bellman::pairing::Engine>::G2Affine {
+        self.try_to_bellman().unwrap()
+    }
+
+    pub fn try_to_bellman(&self) -> Result<<E::BE as bellman::pairing::Engine>::G2Affine, Error> {
         if self.0 .0 == Num::ZERO
             && self.0 .1 == Num::ZERO
             && self.1 .0 == Num::ZERO
             && self.1 .1 == Num::ZERO
         {
-            <E::BE as bellman::pairing::Engine>::G2Affine::zero()
+            Ok(<E::BE as bellman::pairing::Engine>::G2Affine::zero())
         } else {
             let mut buf =
                 <E::BE as bellman::pairing::Engine>::G2Affine::zero().into_raw_uncompressed_le();
@@ -103,7 +112,7 @@ impl<E: Engine> G2Point<E> {
                 BorshSerialize::serialize(&self.1.1.to_mont_uint(), &mut cur).unwrap();
             }
             <E::BE as bellman::pairing::Engine>::G2Affine::from_raw_uncompressed_le(&buf, false)
-                .unwrap()
+                .map_err(|e| Error::invalid_data(format!("invalid G2 point: {:?}", e)))
         }
     }
 
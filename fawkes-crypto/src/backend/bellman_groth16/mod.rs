@@ -1,13 +1,18 @@
 use crate::{
+    backend::ProofSystem,
     circuit::{
-        cs::{RCS, WitnessCS, CS},
-        lc::{Index}
+        cs::{CSHandle, HandleKind, RCS, CS},
+        r1cs::{
+            cs::{BuildCS, DebugCS, TraceCheckpoints, WitnessCS, R1CS},
+            lc::Index,
+        },
     },
     core::signal::Signal,
     ff_uint::{Num, PrimeField},
 };
 
 use bit_vec::BitVec;
+use blake2_rfc::blake2s::Blake2s;
 
 
 use bellman::{ConstraintSystem, SynthesisError};
@@ -22,19 +27,24 @@ use engines::Engine;
 
 pub mod engines;
 #[cfg(feature = "rand_support")]
+pub mod ceremony;
+#[cfg(feature = "rand_support")]
 pub mod osrng;
 pub mod prover;
 #[cfg(feature = "rand_support")]
 pub mod setup;
 pub mod verifier;
 pub mod group;
+#[cfg(feature = "serde_support")]
+pub mod snarkjs;
+pub mod solidity;
 
 
 
 #[repr(transparent)]
-pub struct BellmanCS<E: Engine, C:CS<Fr=E::Fr>>(RCS<C>, PhantomData<E>);
+pub struct BellmanCS<E: Engine, C:R1CS<Fr=E::Fr>>(RCS<C>, PhantomData<E>);
 
-impl<E: Engine, C:CS<Fr=E::Fr>> BellmanCS<E,C> {
+impl<E: Engine, C:R1CS<Fr=E::Fr>> BellmanCS<E,C> {
     pub fn new(inner:RCS<C>) -> Self {
         Self(inner, PhantomData)
     }
@@ -58,7 +68,7 @@ pub fn convert_lc<E: Engine>(
     bellman::LinearCombination::new(res)
 }
 
-impl<E: Engine, C:CS<Fr=E::Fr>> bellman::Circuit<E::BE> for BellmanCS<E, C> {
+impl<E: Engine, C:R1CS<Fr=E::Fr>> bellman::Circuit<E::BE> for BellmanCS<E, C> {
     fn synthesize<BCS: ConstraintSystem<E::BE>>(
         self,
         bellman_cs: &mut BCS,
@@ -76,7 +86,7 @@ impl<E: Engine, C:CS<Fr=E::Fr>> bellman::Circuit<E::BE> for BellmanCS<E, C> {
             let v = bellman_cs.alloc_input(
                 || format!("input_{}", i),
                 || cs.get_value(Index::Input(i)).map(num_to_bellman_fp).ok_or(SynthesisError::AssignmentMissing)
-            ).unwrap();
+            )?;
             variables_input.push(v);
         }
 
@@ -84,7 +94,7 @@ impl<E: Engine, C:CS<Fr=E::Fr>> bellman::Circuit<E::BE> for BellmanCS<E, C> {
             let v = bellman_cs.alloc(
                 || format!("aux_{}", i),
                 || cs.get_value(Index::Aux(i)).map(num_to_bellman_fp).ok_or(SynthesisError::AssignmentMissing)
-            ).unwrap();
+            )?;
             variables_aux.push(v);
         }
 
@@ -136,42 +146,228 @@ pub fn bellman_fp_to_num<Fx: PrimeField, Fy: bellman::pairing::ff::PrimeField>(
     to
 }
 
-pub struct Parameters<E: Engine>(pub bellman::groth16::Parameters<E::BE>, pub u32, pub Vec<u8>, pub BitVec);
+fn write_bitvec<W:std::io::Write>(v: &BitVec, writer: &mut W) -> std::io::Result<()> {
+    BorshSerialize::serialize(&(v.len() as u32), writer)?;
+    BorshSerialize::serialize(&v.to_bytes(), writer)
+}
+
+fn read_bitvec(reader: &mut &[u8]) -> std::io::Result<BitVec> {
+    let len = <u32 as BorshDeserialize>::deserialize(reader)? as usize;
+    let buf:Vec<u8> = BorshDeserialize::deserialize(reader)?;
+
+    if len > buf.len() * 8 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "inconsistent bitvec length"));
+    }
+
+    let mut res = BitVec::from_bytes(&buf);
+    res.truncate(len);
+    Ok(res)
+}
+
+// header of the serialized `Parameters`. Parameters written before the header was introduced start with the
+// number of gates, which would have to be about 1.9 billion to be taken for the magic, and are still read
+const PARAMETERS_MAGIC: &[u8; 4] = b"fkgp";
+const PARAMETERS_VERSION: u32 = 1;
+
+/// Blake2s hash of the cached gates, const tracker, aux mask and trace checkpoints of `Parameters`
+pub fn circuit_fingerprint(num_gates: u32, gates_data: &[u8], const_tracker: &BitVec, aux_mask: &BitVec, checkpoints: &TraceCheckpoints) -> [u8; 32] {
+    let mut h = Blake2s::new(32);
+    h.update(&num_gates.to_le_bytes());
+    h.update(&(gates_data.len() as u64).to_le_bytes());
+    h.update(gates_data);
+    for v in [const_tracker, aux_mask] {
+        h.update(&(v.len() as u64).to_le_bytes());
+        h.update(&v.to_bytes());
+    }
+    h.update(&checkpoints.interval.to_le_bytes());
+    h.update(&(checkpoints.states.len() as u64).to_le_bytes());
+    for state in checkpoints.states.iter() {
+        h.update(&state.to_le_bytes());
+    }
+    h.update(&checkpoints.num_gates.to_le_bytes());
+    h.update(&checkpoints.last.to_le_bytes());
+
+    let mut res = [0u8; 32];
+    res.copy_from_slice(h.finalize().as_bytes());
+    res
+}
+
+/// Bellman parameters with the cached gates, const tracker, mask of the aux variables kept by `optimize`, circuit
+/// fingerprint and checkpoints of the `CircuitTrace`, used to detect a circuit different from
+/// the one used at setup
+pub struct Parameters<E: Engine> {
+    pub params: bellman::groth16::Parameters<E::BE>,
+    pub num_gates: u32,
+    // brotli compressed gates replayed by `WitnessCS` at proving time
+    pub gates: Vec<u8>,
+    pub const_tracker: BitVec,
+    pub aux_mask: BitVec,
+    pub fingerprint: [u8; 32],
+    pub checkpoints: TraceCheckpoints,
+}
 
 impl<E: Engine> Parameters<E> {
     pub fn get_vk(&self) -> verifier::VK<E> {
-        verifier::VK::from_bellman(&self.0.vk)
+        verifier::VK::from_bellman(&self.params.vk)
+    }
+
+    pub fn get_witness_rcs<H: HandleKind>(&self)->RCS<WitnessCS<E::Fr, H>> {
+        WitnessCS::rc_new(self.num_gates as usize, &self.gates, &self.const_tracker, &self.aux_mask, &self.checkpoints)
     }
 
-    pub fn get_witness_rcs(&self)->RCS<WitnessCS<E::Fr>> {
-        WitnessCS::rc_new(self.1 as usize, &self.2, &self.3)
+    /// Hash of the cached circuit data and the trace of the circuit the parameters were generated for. It is
+    /// checked against the data when the parameters are read, and the prover compares it with the fingerprint of
+    /// the circuit being proven, see `WitnessCS::trace_checkpoints`.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint
     }
 
     pub fn write<W:std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        let bitvec_len = self.3.len() as u32;
-        BorshSerialize::serialize(&self.1, writer)?;
-        BorshSerialize::serialize(&self.2, writer)?;
-        BorshSerialize::serialize(&bitvec_len, writer)?;
-        BorshSerialize::serialize(&self.3.to_bytes(), writer)?;
-        self.0.write(writer)
+        writer.write_all(PARAMETERS_MAGIC)?;
+        BorshSerialize::serialize(&PARAMETERS_VERSION, writer)?;
+        BorshSerialize::serialize(&self.num_gates, writer)?;
+        BorshSerialize::serialize(&self.gates, writer)?;
+        write_bitvec(&self.const_tracker, writer)?;
+        write_bitvec(&self.aux_mask, writer)?;
+        BorshSerialize::serialize(&self.fingerprint, writer)?;
+        BorshSerialize::serialize(&self.checkpoints, writer)?;
+        self.params.write(writer)
     }
 
+    /// Reads both the current format and the format without header, which has no aux mask, fingerprint and
+    /// trace checkpoints, so it is treated as not optimized and the circuit is not checked at proving
     pub fn read(reader: &mut &[u8], disallow_points_at_infinity: bool, checked: bool) -> std::io::Result<Self> {
-        let e1 = BorshDeserialize::deserialize(reader)?;
-        let e2 = BorshDeserialize::deserialize(reader)?;
-        let e3_len = <u32 as BorshDeserialize>::deserialize(reader)? as usize;
-        let e3_buf:Vec<u8> = BorshDeserialize::deserialize(reader)?;
+        if !reader.starts_with(PARAMETERS_MAGIC) {
+            return Self::read_legacy(reader, disallow_points_at_infinity, checked);
+        }
+        *reader = &reader[PARAMETERS_MAGIC.len()..];
+        let version = <u32 as BorshDeserialize>::deserialize(reader)?;
+        if version != PARAMETERS_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported parameters version {}", version),
+            ));
+        }
 
-        if e3_len > e3_buf.len() * 8 {
-            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "inconsistent bitvec length"));
+        let num_gates = BorshDeserialize::deserialize(reader)?;
+        let gates: Vec<u8> = BorshDeserialize::deserialize(reader)?;
+        let const_tracker = read_bitvec(reader)?;
+        let aux_mask = read_bitvec(reader)?;
+        let fingerprint: [u8; 32] = BorshDeserialize::deserialize(reader)?;
+        let checkpoints: TraceCheckpoints = BorshDeserialize::deserialize(reader)?;
+        if checkpoints.interval != 0 && !checkpoints.interval.is_power_of_two() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid trace checkpoints"));
+        }
+        if circuit_fingerprint(num_gates, &gates, &const_tracker, &aux_mask, &checkpoints) != fingerprint {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "circuit fingerprint mismatch"));
         }
 
-        let mut e3 = BitVec::from_bytes(&e3_buf);
-        e3.truncate(e3_len);
+        let params = bellman::groth16::Parameters::read(reader, disallow_points_at_infinity, checked)?;
+        Ok(Self { params, num_gates, gates, const_tracker, aux_mask, fingerprint, checkpoints })
+    }
 
+    fn read_legacy(reader: &mut &[u8], disallow_points_at_infinity: bool, checked: bool) -> std::io::Result<Self> {
+        let num_gates = BorshDeserialize::deserialize(reader)?;
+        let gates: Vec<u8> = BorshDeserialize::deserialize(reader)?;
+        let const_tracker = read_bitvec(reader)?;
+        let aux_mask = BitVec::new();
+        let checkpoints = TraceCheckpoints::default();
+        let fingerprint = circuit_fingerprint(num_gates, &gates, &const_tracker, &aux_mask, &checkpoints);
 
-        let e0 = bellman::groth16::Parameters::read(reader, disallow_points_at_infinity, checked)?;
-        Ok(Self(e0, e1, e2, e3))
+        let params = bellman::groth16::Parameters::read(reader, disallow_points_at_infinity, checked)?;
+        Ok(Self { params, num_gates, gates, const_tracker, aux_mask, fingerprint, checkpoints })
     }
 
 }
+
+/// Groth16 as a `ProofSystem`. The setup is per circuit, so there are no universal parameters and the proving
+/// key is `Parameters`, which contain the verifying key as well.
+pub struct Groth16<E: Engine>(PhantomData<E>);
+
+#[cfg(feature = "borsh_support")]
+impl<E: Engine> ProofSystem for Groth16<E> {
+    type Fr = E::Fr;
+    type SetupCS = BuildCS<E::Fr>;
+    type WitnessCS<'a> = WitnessCS<'a, E::Fr>;
+    type DebugCS = DebugCS<E::Fr>;
+
+    type Params = ();
+    type ProvingKey = Parameters<E>;
+    type VerifyingKey = verifier::VK<E>;
+    type Proof = prover::Proof<E>;
+
+    fn try_setup<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        _: &(),
+        circuit: C,
+    ) -> Result<(Self::VerifyingKey, Self::ProvingKey), crate::error::Error> {
+        let params = setup::try_setup(circuit)?;
+        Ok((params.get_vk(), params))
+    }
+
+    fn try_prove<'a, Pub: Signal<Self::WitnessCS<'a>>, Sec: Signal<Self::WitnessCS<'a>>, C: Fn(Pub, Sec)>(
+        _: &(),
+        pk: &'a Self::ProvingKey,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+        circuit: C,
+    ) -> Result<(Vec<Num<E::Fr>>, Self::Proof), crate::error::Error> {
+        prover::try_prove(pk, input_pub, input_sec, circuit)
+    }
+
+    fn try_verify(
+        _: &(),
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        inputs: &[Num<E::Fr>],
+    ) -> Result<bool, crate::error::Error> {
+        verifier::try_verify(vk, proof, inputs)
+    }
+
+    fn compute_public_inputs<Pub: Signal<Self::DebugCS>, Sec: Signal<Self::DebugCS>, C: Fn(Pub, Sec)>(
+        circuit: C,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+    ) -> Result<Vec<Num<E::Fr>>, crate::error::Error> {
+        prover::compute_public_inputs::<E, _, _, _>(circuit, input_pub, input_sec)
+    }
+
+    fn write_params<W: std::io::Write>(_: &(), _: &mut W) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn read_params(_: &mut &[u8]) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn write_proving_key<W: std::io::Write>(pk: &Self::ProvingKey, writer: &mut W) -> std::io::Result<()> {
+        pk.write(writer)
+    }
+
+    fn write_verifying_key<W: std::io::Write>(vk: &Self::VerifyingKey, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(vk, writer)
+    }
+
+    fn read_proving_key<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        reader: &mut &[u8],
+        _: &(),
+        _: C,
+    ) -> std::io::Result<Self::ProvingKey> {
+        Parameters::read(reader, false, true)
+    }
+
+    fn read_verifying_key<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        reader: &mut &[u8],
+        _: &(),
+        _: C,
+    ) -> std::io::Result<Self::VerifyingKey> {
+        BorshDeserialize::deserialize(reader)
+    }
+
+    fn write_proof<W: std::io::Write>(proof: &Self::Proof, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(proof, writer)
+    }
+
+    fn read_proof(reader: &mut &[u8]) -> std::io::Result<Self::Proof> {
+        BorshDeserialize::deserialize(reader)
+    }
+}
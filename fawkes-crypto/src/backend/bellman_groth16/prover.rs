@@ -2,6 +2,8 @@
 use super::osrng::OsRng;
 use super::*;
 use super::group::{G1Point, G2Point};
+use crate::{circuit::{cs::RcHandle, r1cs::cs::DebugCS}, error::Error};
+use bellman::rand::Rng;
 
 #[cfg(feature = "serde_support")]
 use serde::{Serialize, Deserialize};
@@ -18,11 +20,15 @@ pub struct Proof<E: Engine> {
 
 impl<E: Engine> Proof<E> {
     pub fn to_bellman(&self) -> bellman::groth16::Proof<E::BE> {
-        bellman::groth16::Proof {
-            a: self.a.to_bellman(),
-            b: self.b.to_bellman(),
-            c: self.c.to_bellman(),
-        }
+        self.try_to_bellman().unwrap()
+    }
+
+    pub fn try_to_bellman(&self) -> Result<bellman::groth16::Proof<E::BE>, Error> {
+        Ok(bellman::groth16::Proof {
+            a: self.a.try_to_bellman()?,
+            b: self.b.try_to_bellman()?,
+            c: self.c.try_to_bellman()?,
+        })
     }
 
     pub fn from_bellman(proof: &bellman::groth16::Proof<E::BE>) -> Self {
@@ -66,25 +72,161 @@ pub fn prove<'a, E: Engine, Pub: Signal<WitnessCS<'a, E::Fr>>, Sec: Signal<Witne
     input_sec: &Sec::Value,
     circuit: C,
 ) -> (Vec<Num<E::Fr>>, Proof<E>) {
-    let ref rcs = params.get_witness_rcs();
+    try_prove(params, input_pub, input_sec, circuit).unwrap()
+}
+
+#[cfg(feature = "rand_support")]
+pub fn try_prove<'a, E: Engine, Pub: Signal<WitnessCS<'a, E::Fr>>, Sec: Signal<WitnessCS<'a, E::Fr>>, C: Fn(Pub, Sec)>(
+    params: &'a Parameters<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+) -> Result<(Vec<Num<E::Fr>>, Proof<E>), Error> {
+    try_prove_with_rng(params, input_pub, input_sec, circuit, &mut OsRng::new())
+}
+
+/// Same as `prove`, but with caller provided randomness. Seeded rng makes proofs reproducible, which is
+/// only useful for tests: the proof randomness must stay secret to keep the proof zero knowledge.
+pub fn prove_with_rng<'a, E: Engine, Pub: Signal<WitnessCS<'a, E::Fr>>, Sec: Signal<WitnessCS<'a, E::Fr>>, C: Fn(Pub, Sec), R: Rng>(
+    params: &'a Parameters<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+    rng: &mut R,
+) -> (Vec<Num<E::Fr>>, Proof<E>) {
+    try_prove_with_rng(params, input_pub, input_sec, circuit, rng).unwrap()
+}
+
+pub fn try_prove_with_rng<'a, E: Engine, Pub: Signal<WitnessCS<'a, E::Fr>>, Sec: Signal<WitnessCS<'a, E::Fr>>, C: Fn(Pub, Sec), R: Rng>(
+    params: &'a Parameters<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+    rng: &mut R,
+) -> Result<(Vec<Num<E::Fr>>, Proof<E>), Error> {
+    let ref rcs = params.get_witness_rcs::<RcHandle>();
+    let signal_pub = Pub::alloc(rcs, Some(input_pub));
+    signal_pub.inputize();
+    let signal_sec = Sec::alloc(rcs, Some(input_sec));
+
+    circuit(signal_pub, signal_sec);
+
+    try_prove_cs_with_rng(params, rcs, rng)
+}
+
+/// Prove with witness built by the caller: `params.get_witness_rcs()`, public signal allocated and inputized,
+/// secret signal allocated and the circuit applied to both, the same steps as `prove` does. With `ArcHandle`
+/// the witness can be built on a thread pool or across await points.
+#[cfg(feature = "rand_support")]
+pub fn prove_cs<'a, E: Engine, H: HandleKind>(params: &'a Parameters<E>, rcs: &RCS<WitnessCS<'a, E::Fr, H>>) -> (Vec<Num<E::Fr>>, Proof<E>) {
+    try_prove_cs(params, rcs).unwrap()
+}
+
+#[cfg(feature = "rand_support")]
+pub fn try_prove_cs<'a, E: Engine, H: HandleKind>(params: &'a Parameters<E>, rcs: &RCS<WitnessCS<'a, E::Fr, H>>) -> Result<(Vec<Num<E::Fr>>, Proof<E>), Error> {
+    try_prove_cs_with_rng(params, rcs, &mut OsRng::new())
+}
+
+pub fn try_prove_cs_with_rng<'a, E: Engine, H: HandleKind, R: Rng>(
+    params: &'a Parameters<E>,
+    rcs: &RCS<WitnessCS<'a, E::Fr, H>>,
+    rng: &mut R,
+) -> Result<(Vec<Num<E::Fr>>, Proof<E>), Error> {
+    {
+        let cs = rcs.borrow();
+        if let Some(index) = cs.unassigned {
+            return Err(Error::Synthesis(format!("value of variable {:?} is not assigned", index)));
+        }
+        // wiring of the gates is not compared, see `WitnessCS::first_divergence`
+        if let Some(gates) = cs.first_divergence() {
+            let gates = if gates.len() == 1 { gates.start.to_string() } else { format!("in {:?}", gates) };
+            return Err(Error::Synthesis(format!(
+                "circuit (allocations, inputs, gates, constants or coefficients) differs from the one used at setup, first differing gate {}",
+                gates
+            )));
+        }
+        if cs.const_tracker_index != cs.const_tracker.len() {
+            return Err(Error::Synthesis("not all cached data used".to_string()));
+        }
+        // the trace of the proven circuit together with the cached data it was replayed against
+        let fingerprint = circuit_fingerprint(params.num_gates, &params.gates, &params.const_tracker, &params.aux_mask, &cs.trace_checkpoints());
+        if !params.checkpoints.is_empty() && fingerprint != params.fingerprint {
+            return Err(Error::Synthesis("circuit fingerprint differs from the one stored in the parameters".to_string()));
+        }
+    }
+
+    create_proof(params, rcs, rng)
+}
+
+/// Prove with full witness ordered as `[1, inputs..., aux...]`, for example imported from circom. Aux variables
+/// removed by `optimize` are skipped.
+#[cfg(feature = "rand_support")]
+pub fn prove_witness<E: Engine>(params: &Parameters<E>, witness: &[Num<E::Fr>]) -> (Vec<Num<E::Fr>>, Proof<E>) {
+    try_prove_witness(params, witness).unwrap()
+}
+
+#[cfg(feature = "rand_support")]
+pub fn try_prove_witness<E: Engine>(params: &Parameters<E>, witness: &[Num<E::Fr>]) -> Result<(Vec<Num<E::Fr>>, Proof<E>), Error> {
+    let num_input = params.params.vk.ic.len();
+    let num_aux = if params.aux_mask.is_empty() { params.params.l.len() } else { params.aux_mask.len() };
+    if witness.len() != num_input + num_aux {
+        return Err(Error::Synthesis("wrong witness length".to_string()));
+    }
+
+    let ref rcs = params.get_witness_rcs::<RcHandle>();
+    {
+        let mut cs = rcs.borrow_mut();
+        cs.values_input = witness[..num_input].to_vec();
+        cs.values_aux = witness[num_input..].iter()
+            .enumerate()
+            .filter(|(i, _)| params.aux_mask.get(*i).unwrap_or(true))
+            .map(|(_, v)| *v)
+            .collect();
+    }
+    create_proof(params, rcs, &mut OsRng::new())
+}
+
+/// Run the circuit on `DebugCS` without parameters and proving, which checks the gates as they are added and does
+/// not keep them, and return the public inputs, the same as `prove` returns them.
+pub fn compute_public_inputs<E: Engine, Pub: Signal<DebugCS<E::Fr>>, Sec: Signal<DebugCS<E::Fr>>, C: Fn(Pub, Sec)>(
+    circuit: C,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+) -> Result<Vec<Num<E::Fr>>, Error> {
+    let ref rcs = DebugCS::<E::Fr>::rc_collecting();
     let signal_pub = Pub::alloc(rcs, Some(input_pub));
     signal_pub.inputize();
     let signal_sec = Sec::alloc(rcs, Some(input_sec));
 
     circuit(signal_pub, signal_sec);
 
-    let bcs = BellmanCS::<E, WitnessCS<E::Fr>>::new(rcs.clone());
+    let cs = rcs.borrow();
+    // gates with missing values are not checked
+    if let Some(index) = cs.unassigned {
+        return Err(Error::Synthesis(format!("value of variable {:?} is not assigned", index)));
+    }
+    if let Some(c) = cs.first_unsatisfied() {
+        return Err(Error::Synthesis(c.to_string()));
+    }
+    cs.inputs
+        .iter()
+        .enumerate()
+        .map(|(i, v)| v.ok_or_else(|| Error::Synthesis(format!("public input {} is not assigned", i))))
+        .collect()
+}
+
+fn create_proof<'a, E: Engine, H: HandleKind, R: Rng>(params: &'a Parameters<E>, rcs: &RCS<WitnessCS<'a, E::Fr, H>>, rng: &mut R) -> Result<(Vec<Num<E::Fr>>, Proof<E>), Error> {
+    let bcs = BellmanCS::<E, WitnessCS<E::Fr, H>>::new(rcs.clone());
 
-    let ref mut rng = OsRng::new();
-    let proof =
-        Proof::from_bellman(&bellman::groth16::create_random_proof(bcs, &params.0, rng).unwrap());
+    let proof = bellman::groth16::create_random_proof(bcs, &params.params, rng)
+        .map_err(|e| Error::Synthesis(format!("{:?}", e)))?;
+    let proof = Proof::from_bellman(&proof);
 
     let cs = rcs.borrow();
-    assert!(cs.const_tracker_index==cs.const_tracker.len(), "not all cached data used");
     let mut inputs = Vec::with_capacity(cs.num_input());
     for i in 1..cs.num_input() as u32{
         inputs.push(cs.get_value(Index::Input(i)).unwrap())
     }
     
-    (inputs, proof)
+    Ok((inputs, proof))
 }
@@ -2,34 +2,125 @@ use std::io::Write;
 
 use super::osrng::OsRng;
 use super::*;
-use crate::circuit::cs::BuildCS;
+use bellman::rand::Rng;
+use crate::circuit::r1cs::{cs::BuildCS, lc::AbstractLC, optimize::optimize};
+use crate::error::Error;
 
 pub fn setup<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec)>(
     circuit: C,
 ) -> Parameters<E> {
-    let ref rcs = BuildCS::rc_new();
-    let signal_pub = Pub::alloc(rcs, None);
+    try_setup(circuit).unwrap()
+}
+
+pub fn try_setup<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec)>(
+    circuit: C,
+) -> Result<Parameters<E>, Error> {
+    try_setup_with_rng(circuit, &mut OsRng::new())
+}
+
+/// Same as `setup`, but with caller provided randomness. Use a seeded rng for reproducible parameters in tests,
+/// never in production.
+pub fn setup_with_rng<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec), R: Rng>(
+    circuit: C,
+    rng: &mut R,
+) -> Parameters<E> {
+    try_setup_with_rng(circuit, rng).unwrap()
+}
+
+pub fn try_setup_with_rng<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec), R: Rng>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<Parameters<E>, Error> {
+    try_setup_cs_with_rng(&build_cs(circuit), rng)
+}
+
+/// Same as `setup`, but the constraint system is reduced by `optimize` first. Aux variables removed by the
+/// optimization are not a part of the witness, so the parameters are not compatible with ones from `setup`.
+pub fn setup_optimized<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec)>(
+    circuit: C,
+) -> Parameters<E> {
+    try_setup_optimized(circuit).unwrap()
+}
+
+pub fn try_setup_optimized<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec)>(
+    circuit: C,
+) -> Result<Parameters<E>, Error> {
+    try_setup_optimized_with_rng(circuit, &mut OsRng::new())
+}
+
+/// Same as `setup_optimized`, but with caller provided randomness, see `setup_with_rng`
+pub fn setup_optimized_with_rng<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec), R: Rng>(
+    circuit: C,
+    rng: &mut R,
+) -> Parameters<E> {
+    try_setup_optimized_with_rng(circuit, rng).unwrap()
+}
+
+pub fn try_setup_optimized_with_rng<E: Engine, Pub: Signal<BuildCS<E::Fr>>, Sec: Signal<BuildCS<E::Fr>>, C: Fn(Pub, Sec), R: Rng>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<Parameters<E>, Error> {
+    let ref rcs = build_cs(circuit);
+    optimize(&mut rcs.borrow_mut());
+
+    try_setup_cs_with_rng(rcs, rng)
+}
+
+fn build_cs<Fr: PrimeField, Pub: Signal<BuildCS<Fr>>, Sec: Signal<BuildCS<Fr>>, C: Fn(Pub, Sec)>(
+    circuit: C,
+) -> RCS<BuildCS<Fr>> {
+    let rcs = BuildCS::<Fr>::rc_new();
+    let signal_pub = Pub::alloc(&rcs, None);
     signal_pub.inputize();
-    let signal_sec = Sec::alloc(rcs, None);
+    let signal_sec = Sec::alloc(&rcs, None);
 
     circuit(signal_pub, signal_sec);
+    rcs
+}
 
+/// Generate parameters for prebuilt constraint system, for example imported from circom
+pub fn setup_cs<E: Engine>(rcs: &RCS<BuildCS<E::Fr>>) -> Parameters<E> {
+    try_setup_cs(rcs).unwrap()
+}
+
+pub fn try_setup_cs<E: Engine>(rcs: &RCS<BuildCS<E::Fr>>) -> Result<Parameters<E>, Error> {
+    try_setup_cs_with_rng(rcs, &mut OsRng::new())
+}
+
+pub fn try_setup_cs_with_rng<E: Engine, R: Rng>(rcs: &RCS<BuildCS<E::Fr>>, rng: &mut R) -> Result<Parameters<E>, Error> {
     let bcs = BellmanCS::<E, BuildCS<E::Fr>>::new(rcs.clone());
 
-    let ref mut rng = OsRng::new();
-    let bp = bellman::groth16::generate_random_parameters(bcs, rng).unwrap();
+    let bp = bellman::groth16::generate_random_parameters(bcs, rng)
+        .map_err(|e| Error::Setup(format!("{:?}", e)))?;
     let cs=rcs.borrow();
 
+    build_parameters(bp, &cs)
+}
+
+// attach compressed gates, const tracker, aux mask and trace checkpoints of `cs` to bellman parameters
+pub(crate) fn build_parameters<E: Engine, L: AbstractLC<E::Fr>>(bp: bellman::groth16::Parameters<E::BE>, cs: &BuildCS<E::Fr, L>) -> Result<Parameters<E>, Error> {
     let num_gates = cs.gates.len();
 
     let mut buf = std::io::Cursor::new(vec![]);
     let mut c = brotli::CompressorWriter::new(&mut buf, 4096, 9, 22);
     for g in cs.gates.iter() {
-        c.write_all(&g.try_to_vec().unwrap()).unwrap();
+        c.write_all(&g.try_to_vec()?)?;
     }
 
-    c.flush().unwrap();
+    c.flush()?;
     drop(c);
 
-    Parameters(bp, num_gates as u32, buf.into_inner(), cs.const_tracker.clone())
+    let gates_data = buf.into_inner();
+    let checkpoints = cs.trace_checkpoints();
+    let fingerprint = circuit_fingerprint(num_gates as u32, &gates_data, &cs.const_tracker, &cs.aux_mask, &checkpoints);
+
+    Ok(Parameters {
+        params: bp,
+        num_gates: num_gates as u32,
+        gates: gates_data,
+        const_tracker: cs.const_tracker.clone(),
+        aux_mask: cs.aux_mask.clone(),
+        fingerprint,
+        checkpoints,
+    })
 }
@@ -0,0 +1,140 @@
+//! Groth16 proof and verification key in snarkjs `proof.json` and `verification_key.json` layout
+//!
+//! Points are stored as projective triples of decimal strings with `z = 1`, infinity is `[0, 1, 0]`.
+//! G2 coordinates are `[c0, c1]` pairs, which matches the `(re, im)` order of `G2Point`.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    engines::Engine,
+    group::{G1Point, G2Point},
+    prover::Proof,
+    verifier::VK,
+};
+use crate::{error::Error, ff_uint::Num};
+
+const PROTOCOL: &str = "groth16";
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "", deserialize = ""))]
+pub struct SnarkjsProof<E: Engine> {
+    pub pi_a: [Num<E::Fq>; 3],
+    pub pi_b: [[Num<E::Fq>; 2]; 3],
+    pub pi_c: [Num<E::Fq>; 3],
+    pub protocol: String,
+    pub curve: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "", deserialize = ""))]
+pub struct SnarkjsVK<E: Engine> {
+    pub protocol: String,
+    pub curve: String,
+    pub nPublic: usize,
+    pub vk_alpha_1: [Num<E::Fq>; 3],
+    pub vk_beta_2: [[Num<E::Fq>; 2]; 3],
+    pub vk_gamma_2: [[Num<E::Fq>; 2]; 3],
+    pub vk_delta_2: [[Num<E::Fq>; 2]; 3],
+    pub IC: Vec<[Num<E::Fq>; 3]>,
+}
+
+fn check_header<E: Engine>(protocol: &str, curve: &str) -> Result<(), Error> {
+    if protocol != PROTOCOL {
+        return Err(Error::invalid_data(format!("unsupported protocol {}", protocol)));
+    }
+    if curve != E::CURVE_NAME {
+        return Err(Error::invalid_data(format!("unsupported curve {}", curve)));
+    }
+    Ok(())
+}
+
+impl<E: Engine> G1Point<E> {
+    pub fn to_snarkjs(&self) -> [Num<E::Fq>; 3] {
+        if self.0 == Num::ZERO && self.1 == Num::ZERO {
+            [Num::ZERO, Num::ONE, Num::ZERO]
+        } else {
+            [self.0, self.1, Num::ONE]
+        }
+    }
+
+    pub fn from_snarkjs(p: &[Num<E::Fq>; 3]) -> Result<Self, Error> {
+        if p[2] == Num::ZERO {
+            Ok(Self(Num::ZERO, Num::ZERO))
+        } else if p[2] == Num::ONE {
+            Ok(Self(p[0], p[1]))
+        } else {
+            Err(Error::invalid_data("G1 point is not normalized"))
+        }
+    }
+}
+
+impl<E: Engine> G2Point<E> {
+    pub fn to_snarkjs(&self) -> [[Num<E::Fq>; 2]; 3] {
+        if self.0 .0 == Num::ZERO && self.0 .1 == Num::ZERO && self.1 .0 == Num::ZERO && self.1 .1 == Num::ZERO {
+            [[Num::ZERO, Num::ZERO], [Num::ONE, Num::ZERO], [Num::ZERO, Num::ZERO]]
+        } else {
+            [[self.0 .0, self.0 .1], [self.1 .0, self.1 .1], [Num::ONE, Num::ZERO]]
+        }
+    }
+
+    pub fn from_snarkjs(p: &[[Num<E::Fq>; 2]; 3]) -> Result<Self, Error> {
+        if p[2] == [Num::ZERO, Num::ZERO] {
+            Ok(Self((Num::ZERO, Num::ZERO), (Num::ZERO, Num::ZERO)))
+        } else if p[2] == [Num::ONE, Num::ZERO] {
+            Ok(Self((p[0][0], p[0][1]), (p[1][0], p[1][1])))
+        } else {
+            Err(Error::invalid_data("G2 point is not normalized"))
+        }
+    }
+}
+
+impl<E: Engine> Proof<E> {
+    pub fn to_snarkjs(&self) -> SnarkjsProof<E> {
+        SnarkjsProof {
+            pi_a: self.a.to_snarkjs(),
+            pi_b: self.b.to_snarkjs(),
+            pi_c: self.c.to_snarkjs(),
+            protocol: PROTOCOL.to_string(),
+            curve: E::CURVE_NAME.to_string(),
+        }
+    }
+
+    pub fn from_snarkjs(proof: &SnarkjsProof<E>) -> Result<Self, Error> {
+        check_header::<E>(&proof.protocol, &proof.curve)?;
+        Ok(Self {
+            a: G1Point::from_snarkjs(&proof.pi_a)?,
+            b: G2Point::from_snarkjs(&proof.pi_b)?,
+            c: G1Point::from_snarkjs(&proof.pi_c)?,
+        })
+    }
+}
+
+impl<E: Engine> VK<E> {
+    pub fn to_snarkjs(&self) -> SnarkjsVK<E> {
+        SnarkjsVK {
+            protocol: PROTOCOL.to_string(),
+            curve: E::CURVE_NAME.to_string(),
+            nPublic: self.ic.len() - 1,
+            vk_alpha_1: self.alpha.to_snarkjs(),
+            vk_beta_2: self.beta.to_snarkjs(),
+            vk_gamma_2: self.gamma.to_snarkjs(),
+            vk_delta_2: self.delta.to_snarkjs(),
+            IC: self.ic.iter().map(|p| p.to_snarkjs()).collect(),
+        }
+    }
+
+    pub fn from_snarkjs(vk: &SnarkjsVK<E>) -> Result<Self, Error> {
+        check_header::<E>(&vk.protocol, &vk.curve)?;
+        if vk.IC.len() != vk.nPublic + 1 {
+            return Err(Error::invalid_data("wrong number of IC points"));
+        }
+        Ok(Self {
+            alpha: G1Point::from_snarkjs(&vk.vk_alpha_1)?,
+            beta: G2Point::from_snarkjs(&vk.vk_beta_2)?,
+            gamma: G2Point::from_snarkjs(&vk.vk_gamma_2)?,
+            delta: G2Point::from_snarkjs(&vk.vk_delta_2)?,
+            ic: vk.IC.iter().map(G1Point::from_snarkjs).collect::<Result<_, _>>()?,
+        })
+    }
+}
@@ -0,0 +1,218 @@
+//! Solidity Groth16 verifier for bn256 verifying keys
+//!
+//! The contract checks `e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1` with
+//! the ecAdd (0x06), ecMul (0x07) and ecPairing (0x08) precompiles. G2 points are passed to the
+//! precompiles with the imaginary part first, as EIP-197 requires.
+
+use super::{
+    engines::{Bn256, Engine},
+    group::{G1Point, G2Point},
+    num_to_bellman_fp,
+    prover::Proof,
+    verifier::VK,
+};
+use crate::{
+    engines::bn256::{Fq, Fr},
+    error::Error,
+    ff_uint::{Num, NumRepr, PrimeField, Uint},
+};
+
+use bellman::pairing::{
+    ff::{Field, PrimeField as BellmanPrimeField},
+    CurveAffine, CurveProjective,
+};
+
+type BE = <Bn256 as Engine>::BE;
+type BFr = <BE as bellman::pairing::ScalarEngine>::Fr;
+type BFqk = <BE as bellman::pairing::Engine>::Fqk;
+
+// keccak256("verifyProof(uint256[2],uint256[2][2],uint256[2],uint256[])")[..4]
+pub const VERIFY_PROOF_SELECTOR: [u8; 4] = [0xc3, 0x2e, 0x37, 0x0e];
+
+const PAIRING_PAIR_SIZE: usize = 192;
+
+fn g1_words(p: &G1Point<Bn256>) -> [Num<Fq>; 2] {
+    [p.0, p.1]
+}
+
+// EIP-197 order: x.im, x.re, y.im, y.re
+fn g2_words(p: &G2Point<Bn256>) -> [Num<Fq>; 4] {
+    [p.0 .1, p.0 .0, p.1 .1, p.1 .0]
+}
+
+fn put_word<F: PrimeField>(out: &mut Vec<u8>, n: Num<F>) {
+    out.extend_from_slice(&n.to_uint().0.to_big_endian());
+}
+
+fn put_u64(out: &mut Vec<u8>, n: u64) {
+    out.extend_from_slice(&[0u8; 24]);
+    out.extend_from_slice(&n.to_be_bytes());
+}
+
+fn get_word(data: &[u8]) -> Result<Num<Fq>, Error> {
+    let n = NumRepr::new(<Fq as crate::ff_uint::PrimeFieldParams>::Inner::from_big_endian(data));
+    Num::from_uint(n).ok_or_else(|| Error::invalid_data("field element overflow"))
+}
+
+/// Render a complete Solidity verifier contract for `vk`
+pub fn generate_verifier(vk: &VK<Bn256>) -> String {
+    let n_inputs = vk.ic.len() - 1;
+    let mut constants = String::new();
+    let mut push_g1 = |name: &str, p: &G1Point<Bn256>| {
+        let [x, y] = g1_words(p);
+        constants += &format!("    uint256 constant {}_X = {};\n", name, x);
+        constants += &format!("    uint256 constant {}_Y = {};\n", name, y);
+    };
+    push_g1("ALPHA", &vk.alpha);
+    for (i, p) in vk.ic.iter().enumerate() {
+        push_g1(&format!("IC{}", i), p);
+    }
+    for (name, p) in [("BETA", &vk.beta), ("GAMMA", &vk.gamma), ("DELTA", &vk.delta)] {
+        let [x1, x0, y1, y0] = g2_words(p);
+        constants += &format!("    uint256 constant {}_X1 = {};\n", name, x1);
+        constants += &format!("    uint256 constant {}_X0 = {};\n", name, x0);
+        constants += &format!("    uint256 constant {}_Y1 = {};\n", name, y1);
+        constants += &format!("    uint256 constant {}_Y0 = {};\n", name, y0);
+    }
+
+    let mut vk_x = String::new();
+    for i in 0..n_inputs {
+        vk_x += &format!(
+            "        require(input[{0}] < SNARK_SCALAR_FIELD, \"verifier: input overflow\");\n        \
+             vkX = ecAdd(vkX, ecMul([IC{1}_X, IC{1}_Y], input[{0}]));\n",
+            i,
+            i + 1
+        );
+    }
+
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+contract Groth16Verifier {{
+    uint256 constant SNARK_SCALAR_FIELD = {r};
+    uint256 constant PRIME_Q = {q};
+    uint256 constant N_INPUTS = {n_inputs};
+
+{constants}
+    function ecAdd(uint256[2] memory p1, uint256[2] memory p2) internal view returns (uint256[2] memory r) {{
+        uint256[4] memory input = [p1[0], p1[1], p2[0], p2[1]];
+        bool success;
+        assembly {{
+            success := staticcall(sub(gas(), 2000), 6, input, 0x80, r, 0x40)
+        }}
+        require(success, "verifier: ecAdd failed");
+    }}
+
+    function ecMul(uint256[2] memory p, uint256 s) internal view returns (uint256[2] memory r) {{
+        uint256[3] memory input = [p[0], p[1], s];
+        bool success;
+        assembly {{
+            success := staticcall(sub(gas(), 2000), 7, input, 0x60, r, 0x40)
+        }}
+        require(success, "verifier: ecMul failed");
+    }}
+
+    function negateY(uint256 y) internal pure returns (uint256) {{
+        return y == 0 ? 0 : PRIME_Q - (y % PRIME_Q);
+    }}
+
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[] calldata input
+    ) public view returns (bool) {{
+        require(input.length == N_INPUTS, "verifier: wrong number of inputs");
+
+        uint256[2] memory vkX = [IC0_X, IC0_Y];
+{vk_x}
+        uint256[24] memory p = [
+            a[0], negateY(a[1]), b[0][0], b[0][1], b[1][0], b[1][1],
+            ALPHA_X, ALPHA_Y, BETA_X1, BETA_X0, BETA_Y1, BETA_Y0,
+            vkX[0], vkX[1], GAMMA_X1, GAMMA_X0, GAMMA_Y1, GAMMA_Y0,
+            c[0], c[1], DELTA_X1, DELTA_X0, DELTA_Y1, DELTA_Y0
+        ];
+        uint256[1] memory out;
+        bool success;
+        assembly {{
+            success := staticcall(sub(gas(), 2000), 8, p, 0x300, out, 0x20)
+        }}
+        require(success, "verifier: pairing failed");
+        return out[0] == 1;
+    }}
+}}
+"#,
+        r = Num::<Fr>::MODULUS,
+        q = Num::<Fq>::MODULUS,
+        n_inputs = n_inputs,
+        constants = constants,
+        vk_x = vk_x,
+    )
+}
+
+/// ABI-encoded call of `verifyProof(a, b, c, input)`, including the function selector
+pub fn encode_calldata(proof: &Proof<Bn256>, inputs: &[Num<Fr>]) -> Vec<u8> {
+    let mut res = VERIFY_PROOF_SELECTOR.to_vec();
+    g1_words(&proof.a).into_iter().for_each(|w| put_word(&mut res, w));
+    g2_words(&proof.b).into_iter().for_each(|w| put_word(&mut res, w));
+    g1_words(&proof.c).into_iter().for_each(|w| put_word(&mut res, w));
+    // offset of the dynamic input array after 9 static words
+    put_u64(&mut res, 9 * 32);
+    put_u64(&mut res, inputs.len() as u64);
+    inputs.iter().for_each(|w| put_word(&mut res, *w));
+    res
+}
+
+/// Input of the ecPairing precompile, computed natively the same way as the generated contract does
+pub fn pairing_input(vk: &VK<Bn256>, proof: &Proof<Bn256>, inputs: &[Num<Fr>]) -> Result<Vec<u8>, Error> {
+    if inputs.len() + 1 != vk.ic.len() {
+        return Err(Error::Verification("wrong number of public inputs".to_string()));
+    }
+
+    let mut neg_a = proof.a.try_to_bellman()?;
+    neg_a.negate();
+
+    let ic = vk.ic.iter().map(|p| p.try_to_bellman()).collect::<Result<Vec<_>, _>>()?;
+    let mut vk_x = ic[0].into_projective();
+    for (p, x) in ic[1..].iter().zip(inputs.iter()) {
+        vk_x.add_assign(&p.mul(num_to_bellman_fp::<Fr, BFr>(*x).into_repr()));
+    }
+
+    let pairs = [
+        (G1Point::from_bellman(&neg_a), &proof.b),
+        (G1Point(vk.alpha.0, vk.alpha.1), &vk.beta),
+        (G1Point::from_bellman(&vk_x.into_affine()), &vk.gamma),
+        (G1Point(proof.c.0, proof.c.1), &vk.delta),
+    ];
+
+    let mut res = Vec::with_capacity(pairs.len() * PAIRING_PAIR_SIZE);
+    for (g1, g2) in pairs.iter() {
+        g1_words(g1).into_iter().for_each(|w| put_word(&mut res, w));
+        g2_words(g2).into_iter().for_each(|w| put_word(&mut res, w));
+    }
+    Ok(res)
+}
+
+/// Native evaluation of the ecPairing precompile
+pub fn check_pairing_input(input: &[u8]) -> Result<bool, Error> {
+    if input.len() % PAIRING_PAIR_SIZE != 0 {
+        return Err(Error::invalid_data("wrong pairing input length"));
+    }
+
+    let mut pairs = Vec::with_capacity(input.len() / PAIRING_PAIR_SIZE);
+    for chunk in input.chunks(PAIRING_PAIR_SIZE) {
+        let w = (0..6)
+            .map(|i| get_word(&chunk[32 * i..32 * (i + 1)]))
+            .collect::<Result<Vec<_>, _>>()?;
+        let g1 = G1Point::<Bn256>(w[0], w[1]).try_to_bellman()?;
+        let g2 = G2Point::<Bn256>((w[3], w[2]), (w[5], w[4])).try_to_bellman()?;
+        pairs.push((g1.prepare(), g2.prepare()));
+    }
+
+    let terms: Vec<_> = pairs.iter().map(|(a, b)| (a, b)).collect();
+    let res = <BE as bellman::pairing::Engine>::final_exponentiation(
+        &<BE as bellman::pairing::Engine>::miller_loop(terms.iter())
+    );
+    Ok(res == Some(BFqk::one()))
+}
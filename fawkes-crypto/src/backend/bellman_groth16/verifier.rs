@@ -4,8 +4,14 @@ use borsh::{BorshSerialize, BorshDeserialize};
 use serde::{Serialize, Deserialize};
 
 use super::prover::Proof;
+#[cfg(feature = "rand_support")]
+use bellman::{
+    rand::Rng,
+    pairing::{CurveProjective, ff::{Field, PrimeField as BellmanPrimeField}},
+};
 use super::*;
 use super::group::{G1Point, G2Point};
+use crate::error::Error;
 
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde_support", serde(bound(serialize = "", deserialize = "")))]
@@ -20,15 +26,19 @@ pub struct VK<E: Engine> {
 impl<E: Engine> VK<E> {
     // fill missing fields with zeroes
     pub fn to_bellman(&self) -> bellman::groth16::VerifyingKey<E::BE> {
-        bellman::groth16::VerifyingKey {
-            alpha_g1: self.alpha.to_bellman(),
+        self.try_to_bellman().unwrap()
+    }
+
+    pub fn try_to_bellman(&self) -> Result<bellman::groth16::VerifyingKey<E::BE>, Error> {
+        Ok(bellman::groth16::VerifyingKey {
+            alpha_g1: self.alpha.try_to_bellman()?,
             beta_g1: <E::BE as bellman::pairing::Engine>::G1Affine::zero(),
-            beta_g2: self.beta.to_bellman(),
-            gamma_g2: self.gamma.to_bellman(),
+            beta_g2: self.beta.try_to_bellman()?,
+            gamma_g2: self.gamma.try_to_bellman()?,
             delta_g1: <E::BE as bellman::pairing::Engine>::G1Affine::zero(),
-            delta_g2: self.delta.to_bellman(),
-            ic: self.ic.iter().map(|e| e.to_bellman()).collect(),
-        }
+            delta_g2: self.delta.try_to_bellman()?,
+            ic: self.ic.iter().map(|e| e.try_to_bellman()).collect::<Result<_, _>>()?,
+        })
     }
 
     pub fn from_bellman(vk: &bellman::groth16::VerifyingKey<E::BE>) -> Self {
@@ -73,9 +83,143 @@ impl<E: Engine> BorshDeserialize for VK<E> {
 }
 
 pub fn verify<E: Engine>(vk: &VK<E>, proof: &Proof<E>, inputs: &[Num<E::Fr>]) -> bool {
+    try_verify(vk, proof, inputs).unwrap()
+}
+
+pub fn try_verify<E: Engine>(vk: &VK<E>, proof: &Proof<E>, inputs: &[Num<E::Fr>]) -> Result<bool, Error> {
+    if inputs.len() + 1 != vk.ic.len() {
+        return Ok(false);
+    }
     let inputs: Vec<_> = inputs.iter().map(|e| num_to_bellman_fp(*e)).collect();
-    let vk = vk.to_bellman();
-    let proof = proof.to_bellman();
+    let vk = vk.try_to_bellman()?;
+    let proof = proof.try_to_bellman()?;
     let pvk = bellman::groth16::prepare_verifying_key(&vk);
-    bellman::groth16::verify_proof(&pvk, &proof, &inputs).unwrap()
+    bellman::groth16::verify_proof(&pvk, &proof, &inputs)
+        .map_err(|e| Error::Verification(format!("{:?}", e)))
+}
+
+
+/// Verifying key with precomputed pairing data. Build it once and reuse it for many proofs.
+pub struct PreparedVK<E: Engine> {
+    pub pvk: bellman::groth16::PreparedVerifyingKey<E::BE>,
+    alpha_g1: <E::BE as bellman::pairing::Engine>::G1Affine,
+    neg_beta_g2: <<E::BE as bellman::pairing::Engine>::G2Affine as CurveAffine>::Prepared,
+    neg_gamma_g2: <<E::BE as bellman::pairing::Engine>::G2Affine as CurveAffine>::Prepared,
+    neg_delta_g2: <<E::BE as bellman::pairing::Engine>::G2Affine as CurveAffine>::Prepared,
+    ic: Vec<<E::BE as bellman::pairing::Engine>::G1Affine>,
+}
+
+impl<E: Engine> PreparedVK<E> {
+    pub fn new(vk: &VK<E>) -> Self {
+        Self::try_new(vk).unwrap()
+    }
+
+    pub fn try_new(vk: &VK<E>) -> Result<Self, Error> {
+        let vk = vk.try_to_bellman()?;
+        let neg = |p: &<E::BE as bellman::pairing::Engine>::G2Affine| {
+            let mut p = *p;
+            p.negate();
+            p.prepare()
+        };
+        Ok(Self {
+            pvk: bellman::groth16::prepare_verifying_key(&vk),
+            alpha_g1: vk.alpha_g1,
+            neg_beta_g2: neg(&vk.beta_g2),
+            neg_gamma_g2: neg(&vk.gamma_g2),
+            neg_delta_g2: neg(&vk.delta_g2),
+            ic: vk.ic,
+        })
+    }
+}
+
+impl<E: Engine> VK<E> {
+    pub fn prepare(&self) -> PreparedVK<E> {
+        PreparedVK::new(self)
+    }
+}
+
+// malformed proofs and inputs are not valid, the verifier does not panic on untrusted data
+pub fn verify_prepared<E: Engine>(pvk: &PreparedVK<E>, proof: &Proof<E>, inputs: &[Num<E::Fr>]) -> bool {
+    try_verify_prepared(pvk, proof, inputs).unwrap_or(false)
+}
+
+pub fn try_verify_prepared<E: Engine>(pvk: &PreparedVK<E>, proof: &Proof<E>, inputs: &[Num<E::Fr>]) -> Result<bool, Error> {
+    if inputs.len() + 1 != pvk.ic.len() {
+        return Ok(false);
+    }
+    let inputs: Vec<_> = inputs.iter().map(|e| num_to_bellman_fp(*e)).collect();
+    let proof = proof.try_to_bellman()?;
+    bellman::groth16::verify_proof(&pvk.pvk, &proof, &inputs)
+        .map_err(|e| Error::Verification(format!("{:?}", e)))
+}
+
+#[cfg(feature = "rand_support")]
+pub fn verify_batch<E: Engine>(vk: &VK<E>, proofs: &[(Proof<E>, Vec<Num<E::Fr>>)]) -> bool {
+    verify_batch_prepared(&vk.prepare(), proofs)
+}
+
+#[cfg(feature = "rand_support")]
+pub fn verify_batch_prepared<E: Engine>(pvk: &PreparedVK<E>, proofs: &[(Proof<E>, Vec<Num<E::Fr>>)]) -> bool {
+    try_verify_batch_prepared(pvk, proofs).unwrap_or(false)
+}
+
+// Checks random linear combination of the groth16 equations
+//
+// prod e(r_i*A_i, B_i) * e(sum r_i*alpha, -beta) * e(sum r_i*IC(x_i), -gamma) * e(sum r_i*C_i, -delta) == 1
+//
+// The combination holds with negligible probability if any of the proofs is invalid. A proof with the wrong
+// number of inputs makes the batch invalid, points which are not on the curve are reported as errors.
+#[cfg(feature = "rand_support")]
+pub fn try_verify_batch_prepared<E: Engine>(pvk: &PreparedVK<E>, proofs: &[(Proof<E>, Vec<Num<E::Fr>>)]) -> Result<bool, Error> {
+    type BFr<E> = <<E as Engine>::BE as bellman::pairing::ScalarEngine>::Fr;
+    type BG1<E> = <<E as Engine>::BE as bellman::pairing::Engine>::G1;
+
+    if proofs.is_empty() {
+        return Ok(true);
+    }
+
+    let ref mut rng = super::osrng::OsRng::new();
+
+    let mut r_sum = BFr::<E>::zero();
+    let mut ic_coeffs = vec![BFr::<E>::zero(); pvk.ic.len()];
+    let mut c_acc = BG1::<E>::zero();
+    let mut ab_terms = Vec::with_capacity(proofs.len());
+
+    for (proof, inputs) in proofs.iter() {
+        if inputs.len() + 1 != pvk.ic.len() {
+            return Ok(false);
+        }
+        let proof = proof.try_to_bellman()?;
+        let r: BFr<E> = rng.gen();
+
+        r_sum.add_assign(&r);
+        for (coeff, x) in ic_coeffs[1..].iter_mut().zip(inputs.iter()) {
+            let mut t: BFr<E> = num_to_bellman_fp(*x);
+            t.mul_assign(&r);
+            coeff.add_assign(&t);
+        }
+
+        c_acc.add_assign(&proof.c.mul(r.into_repr()));
+        ab_terms.push((proof.a.mul(r.into_repr()).into_affine().prepare(), proof.b.prepare()));
+    }
+    ic_coeffs[0] = r_sum;
+
+    let mut ic_acc = BG1::<E>::zero();
+    for (p, coeff) in pvk.ic.iter().zip(ic_coeffs.iter()) {
+        ic_acc.add_assign(&p.mul(coeff.into_repr()));
+    }
+
+    let alpha_acc = pvk.alpha_g1.mul(r_sum.into_repr()).into_affine().prepare();
+    let ic_acc = ic_acc.into_affine().prepare();
+    let c_acc = c_acc.into_affine().prepare();
+
+    let mut terms: Vec<_> = ab_terms.iter().map(|(a, b)| (a, b)).collect();
+    terms.push((&alpha_acc, &pvk.neg_beta_g2));
+    terms.push((&ic_acc, &pvk.neg_gamma_g2));
+    terms.push((&c_acc, &pvk.neg_delta_g2));
+
+    let res = <E::BE as bellman::pairing::Engine>::final_exponentiation(
+        &<E::BE as bellman::pairing::Engine>::miller_loop(terms.iter())
+    );
+    Ok(res == Some(<<E::BE as bellman::pairing::Engine>::Fqk as Field>::one()))
 }
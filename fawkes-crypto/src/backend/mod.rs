@@ -3,3 +3,101 @@ pub mod bellman_groth16;
 
 #[cfg(feature = "plonk")]
 pub mod plonk;
+
+use crate::{
+    circuit::cs::CS,
+    core::signal::Signal,
+    error::Error,
+    ff_uint::{Num, PrimeField},
+};
+
+use std::io::{self, Write};
+
+/// Common interface of the proving backends, implemented by `bellman_groth16::Groth16` and `plonk::Plonk`.
+/// A circuit written against `circuit::cs::CS` can be set up, proven and verified with any of them.
+///
+/// `Params` are the universal parameters of the proof system, `()` for Groth16, where the setup is per circuit.
+/// Public inputs returned by `prove` are in the order expected by `verify`.
+pub trait ProofSystem {
+    type Fr: PrimeField;
+    // constraint systems the circuit is built on at setup, at proving time and by `compute_public_inputs`
+    type SetupCS: CS<Fr = Self::Fr>;
+    type WitnessCS<'a>: CS<Fr = Self::Fr>;
+    type DebugCS: CS<Fr = Self::Fr>;
+
+    type Params;
+    type ProvingKey;
+    type VerifyingKey;
+    type Proof;
+
+    fn try_setup<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        params: &Self::Params,
+        circuit: C,
+    ) -> Result<(Self::VerifyingKey, Self::ProvingKey), Error>;
+
+    fn try_prove<'a, Pub: Signal<Self::WitnessCS<'a>>, Sec: Signal<Self::WitnessCS<'a>>, C: Fn(Pub, Sec)>(
+        params: &Self::Params,
+        pk: &'a Self::ProvingKey,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+        circuit: C,
+    ) -> Result<(Vec<Num<Self::Fr>>, Self::Proof), Error>;
+
+    fn try_verify(
+        params: &Self::Params,
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        inputs: &[Num<Self::Fr>],
+    ) -> Result<bool, Error>;
+
+    // Run the circuit without proving and check its constraints, returns the public inputs the same as `prove`
+    fn compute_public_inputs<Pub: Signal<Self::DebugCS>, Sec: Signal<Self::DebugCS>, C: Fn(Pub, Sec)>(
+        circuit: C,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+    ) -> Result<Vec<Num<Self::Fr>>, Error>;
+
+    fn setup<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        params: &Self::Params,
+        circuit: C,
+    ) -> (Self::VerifyingKey, Self::ProvingKey) {
+        Self::try_setup(params, circuit).unwrap()
+    }
+
+    fn prove<'a, Pub: Signal<Self::WitnessCS<'a>>, Sec: Signal<Self::WitnessCS<'a>>, C: Fn(Pub, Sec)>(
+        params: &Self::Params,
+        pk: &'a Self::ProvingKey,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+        circuit: C,
+    ) -> (Vec<Num<Self::Fr>>, Self::Proof) {
+        Self::try_prove(params, pk, input_pub, input_sec, circuit).unwrap()
+    }
+
+    // malformed proofs and inputs are not valid, use `try_verify` to tell them from a failed check
+    fn verify(params: &Self::Params, vk: &Self::VerifyingKey, proof: &Self::Proof, inputs: &[Num<Self::Fr>]) -> bool {
+        Self::try_verify(params, vk, proof, inputs).unwrap_or(false)
+    }
+
+    fn write_params<W: Write>(params: &Self::Params, writer: &mut W) -> io::Result<()>;
+    fn read_params(reader: &mut &[u8]) -> io::Result<Self::Params>;
+
+    fn write_proving_key<W: Write>(pk: &Self::ProvingKey, writer: &mut W) -> io::Result<()>;
+    fn write_verifying_key<W: Write>(vk: &Self::VerifyingKey, writer: &mut W) -> io::Result<()>;
+
+    // Keys are read for a given circuit, PLONK checks the circuit hash stored in the key
+    fn read_proving_key<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        reader: &mut &[u8],
+        params: &Self::Params,
+        circuit: C,
+    ) -> io::Result<Self::ProvingKey>;
+
+    fn read_verifying_key<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        reader: &mut &[u8],
+        params: &Self::Params,
+        circuit: C,
+    ) -> io::Result<Self::VerifyingKey>;
+
+    fn write_proof<W: Write>(proof: &Self::Proof, writer: &mut W) -> io::Result<()>;
+    fn read_proof(reader: &mut &[u8]) -> io::Result<Self::Proof>;
+}
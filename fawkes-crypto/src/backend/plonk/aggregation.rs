@@ -0,0 +1,412 @@
+//! Aggregation of bn256 PLONK proofs with KZG accumulation
+//!
+//! Proofs to be aggregated are created by `prove_snark` with a Poseidon transcript, which is
+//! cheap to replay inside a circuit. `AggregationCircuit` runs the succinct verifier of every
+//! proof and folds the resulting KZG accumulators into one, exposed as `4 * LIMBS` public
+//! inputs followed by the public inputs of every aggregated proof, so the verifier learns which
+//! statements were proven. The aggregate proof uses `EvmTranscript`, and `verify` checks it
+//! together with the accumulator with a single pairing.
+
+use std::{io::Cursor, rc::Rc};
+
+use super::{
+    engines::Bn256,
+    prover::{build_witness, Proof},
+    setup::{ProvingKey, VerifyingKey},
+    *,
+};
+use crate::{circuit::plonk::cs::WitnessCS, engines::bn256::Fr};
+
+use halo2_curves::bn256::{Bn256 as HaloBn256, Fq as HaloFq, Fr as HaloFr, G1Affine};
+use halo2_proofs::{
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, ConstraintSystem as HaloConstraintSystem, ProvingKey as HaloProvingKey,
+        VerifyingKey as HaloVerifyingKey,
+    },
+    poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverGWC},
+    transcript::{TranscriptReadBuffer, TranscriptWriterBuffer},
+};
+use halo2_wrong_ecc::{
+    integer::rns::Rns,
+    maingate::{MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions, RegionCtx},
+    EccConfig,
+};
+use itertools::Itertools;
+use plonk_verifier::{
+    loader::{self, native::NativeLoader},
+    pcs::{
+        kzg::{Gwc19, KzgAccumulator, KzgAs, KzgSuccinctVerifyingKey, LimbsEncoding, LimbsEncodingInstructions},
+        AccumulationScheme, AccumulationSchemeProver,
+    },
+    system::halo2::{
+        compile,
+        transcript::{evm::EvmTranscript, halo2::PoseidonTranscript as Halo2PoseidonTranscript},
+        Config,
+    },
+    util::arithmetic::fe_to_limbs,
+    verifier::{self, plonk::PlonkProtocol, SnarkVerifier},
+};
+
+pub const LIMBS: usize = 4;
+pub const BITS: usize = 68;
+
+// Poseidon parameters of the transcript
+const T: usize = 5;
+const RATE: usize = 4;
+const R_F: usize = 8;
+const R_P: usize = 60;
+
+type As = KzgAs<HaloBn256, Gwc19>;
+type PlonkSuccinctVerifier = verifier::plonk::PlonkSuccinctVerifier<As, LimbsEncoding<LIMBS, BITS>>;
+type PlonkVerifier = verifier::plonk::PlonkVerifier<As, LimbsEncoding<LIMBS, BITS>>;
+type Svk = KzgSuccinctVerifyingKey<G1Affine>;
+type BaseFieldEccChip = halo2_wrong_ecc::BaseFieldEccChip<G1Affine, LIMBS, BITS>;
+type Halo2Loader<'a> = loader::halo2::Halo2Loader<'a, G1Affine, BaseFieldEccChip>;
+type Halo2Scalar<'a> = loader::halo2::Scalar<'a, G1Affine, BaseFieldEccChip>;
+type PoseidonTranscript<L, S> = Halo2PoseidonTranscript<G1Affine, L, S, T, RATE, R_F, R_P>;
+
+fn synthesis_error<E: std::fmt::Debug>(e: E) -> crate::error::Error {
+    crate::error::Error::Synthesis(format!("{:?}", e))
+}
+
+/// Proof of a fawkes circuit together with what is needed to verify it inside the aggregation circuit
+#[derive(Clone, Debug)]
+pub struct Snark {
+    pub protocol: PlonkProtocol<G1Affine>,
+    pub instances: Vec<Vec<HaloFr>>,
+    pub proof: Vec<u8>,
+}
+
+impl Snark {
+    /// `proof` must be created by `prove_snark`
+    pub fn new(params: &Parameters<Bn256>, vk: &VerifyingKey<Bn256>, inputs: &[Num<Fr>], proof: &Proof) -> Self {
+        let protocol = compile(&params.0, &vk.0, Config::kzg().with_num_instance(vec![inputs.len()]));
+        Self {
+            protocol,
+            instances: vec![inputs.iter().cloned().map(num_to_halo_fp).collect()],
+            proof: proof.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SnarkWitness {
+    protocol: PlonkProtocol<G1Affine>,
+    instances: Vec<Vec<Value<HaloFr>>>,
+    proof: Value<Vec<u8>>,
+}
+
+impl From<&Snark> for SnarkWitness {
+    fn from(snark: &Snark) -> Self {
+        Self {
+            protocol: snark.protocol.clone(),
+            instances: snark.instances.iter().map(|i| i.iter().cloned().map(Value::known).collect()).collect(),
+            proof: Value::known(snark.proof.clone()),
+        }
+    }
+}
+
+impl SnarkWitness {
+    fn without_witnesses(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            instances: self.instances.iter().map(|i| vec![Value::unknown(); i.len()]).collect(),
+            proof: Value::unknown(),
+        }
+    }
+
+    fn proof(&self) -> Value<&[u8]> {
+        self.proof.as_ref().map(Vec::as_slice)
+    }
+
+    fn num_instance(&self) -> usize {
+        self.instances.iter().map(Vec::len).sum()
+    }
+}
+
+/// Same as `prover::prove`, but the proof is created with the Poseidon transcript and can be aggregated
+pub fn prove_snark<
+    'a,
+    Pub: Signal<WitnessCS<'a, Fr>>,
+    Sec: Signal<WitnessCS<'a, Fr>>,
+    C: Fn(Pub, Sec)
+>(
+    params: &Parameters<Bn256>,
+    pk: &'a ProvingKey<Bn256>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+) -> Result<(Vec<Num<Fr>>, Proof), crate::error::Error> {
+    let (rcs, inputs) = build_witness(pk, input_pub, input_sec, circuit)?;
+    let instances = inputs.iter().cloned().map(num_to_halo_fp).collect_vec();
+
+    let mut transcript = PoseidonTranscript::<NativeLoader, _>::init(Vec::new());
+    let res = if uses_lookup(pk.key.get_vk()) {
+        create_proof::<KZGCommitmentScheme<HaloBn256>, ProverGWC<_>, _, _, _, _>(
+            &params.0,
+            &pk.key,
+            &[HaloCS::<_, true>::new(rcs)],
+            &[&[instances.as_slice()]],
+            OsRng,
+            &mut transcript,
+        )
+    } else {
+        create_proof::<KZGCommitmentScheme<HaloBn256>, ProverGWC<_>, _, _, _, _>(
+            &params.0,
+            &pk.key,
+            &[HaloCS::<_, false>::new(rcs)],
+            &[&[instances.as_slice()]],
+            OsRng,
+            &mut transcript,
+        )
+    };
+    res.map_err(synthesis_error)?;
+
+    Ok((inputs, Proof(transcript.finalize())))
+}
+
+// in-circuit succinct verification of every snark, folded into one accumulator,
+// also returns the assigned instances of the snarks to be exposed as public inputs
+fn aggregate<'a>(
+    svk: &Svk,
+    loader: &Rc<Halo2Loader<'a>>,
+    snarks: &[SnarkWitness],
+    as_proof: Value<&'_ [u8]>,
+) -> Result<(KzgAccumulator<G1Affine, Rc<Halo2Loader<'a>>>, Vec<Halo2Scalar<'a>>), plonk_verifier::Error> {
+    let mut accumulators = vec![];
+    let mut snark_instances = vec![];
+    for snark in snarks.iter() {
+        let protocol = snark.protocol.loaded(loader);
+        let instances = snark
+            .instances
+            .iter()
+            .map(|i| i.iter().map(|v| loader.assign_scalar(*v)).collect_vec())
+            .collect_vec();
+        let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, snark.proof());
+        let proof = PlonkSuccinctVerifier::read_proof(svk, &protocol, &instances, &mut transcript)?;
+        accumulators.extend(PlonkSuccinctVerifier::verify(svk, &protocol, &instances, &proof)?);
+        snark_instances.extend(instances.into_iter().flatten());
+    }
+
+    let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, as_proof);
+    let proof = As::read_proof(&Default::default(), &accumulators, &mut transcript)?;
+    let accumulator = As::verify(&Default::default(), &accumulators, &proof)?;
+    Ok((accumulator, snark_instances))
+}
+
+#[derive(Clone)]
+pub struct AggregationConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+}
+
+impl AggregationConfig {
+    fn main_gate(&self) -> MainGate<HaloFr> {
+        MainGate::new(self.main_gate_config.clone())
+    }
+
+    fn range_chip(&self) -> RangeChip<HaloFr> {
+        RangeChip::new(self.range_config.clone())
+    }
+
+    fn ecc_chip(&self) -> BaseFieldEccChip {
+        BaseFieldEccChip::new(EccConfig::new(self.range_config.clone(), self.main_gate_config.clone()))
+    }
+}
+
+/// Circuit verifying a fixed number of snarks of fixed circuits
+#[derive(Clone)]
+pub struct AggregationCircuit {
+    svk: Svk,
+    snarks: Vec<SnarkWitness>,
+    instances: Vec<HaloFr>,
+    as_proof: Value<Vec<u8>>,
+}
+
+impl AggregationCircuit {
+    pub fn new(params: &Parameters<Bn256>, snarks: &[Snark]) -> Result<Self, crate::error::Error> {
+        let svk: Svk = params.0.get_g()[0].into();
+
+        let mut accumulators = vec![];
+        for snark in snarks.iter() {
+            let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(snark.proof.as_slice());
+            let proof = PlonkSuccinctVerifier::read_proof(&svk, &snark.protocol, &snark.instances, &mut transcript)
+                .map_err(synthesis_error)?;
+            accumulators.extend(
+                PlonkSuccinctVerifier::verify(&svk, &snark.protocol, &snark.instances, &proof).map_err(synthesis_error)?,
+            );
+        }
+
+        let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(Vec::new());
+        let accumulator = As::create_proof(&Default::default(), &accumulators, &mut transcript, OsRng)
+            .map_err(synthesis_error)?;
+        let as_proof = transcript.finalize();
+
+        let KzgAccumulator { lhs, rhs } = accumulator;
+        let instances = [lhs.x, lhs.y, rhs.x, rhs.y]
+            .map(fe_to_limbs::<_, _, LIMBS, BITS>)
+            .into_iter()
+            .flatten()
+            .chain(snarks.iter().flat_map(|snark| snark.instances.iter().flatten().cloned()))
+            .collect();
+
+        Ok(Self {
+            svk,
+            snarks: snarks.iter().map_into().collect(),
+            instances,
+            as_proof: Value::known(as_proof),
+        })
+    }
+
+    /// Positions of the accumulator limbs among the public inputs
+    pub fn accumulator_indices() -> Vec<(usize, usize)> {
+        (0..4 * LIMBS).map(|i| (0, i)).collect()
+    }
+
+    /// Number of public inputs, the accumulator limbs and the inputs of the snarks
+    pub fn num_instance(&self) -> Vec<usize> {
+        vec![4 * LIMBS + self.snarks.iter().map(SnarkWitness::num_instance).sum::<usize>()]
+    }
+
+    /// Public inputs of the aggregate proof, the limbs of the accumulator followed by the public
+    /// inputs of the snarks in order
+    pub fn inputs(&self) -> Vec<Num<Fr>> {
+        self.instances.iter().cloned().map(halo_fp_to_num).collect()
+    }
+
+    fn as_proof(&self) -> Value<&[u8]> {
+        self.as_proof.as_ref().map(Vec::as_slice)
+    }
+}
+
+impl Circuit<HaloFr> for AggregationCircuit {
+    type Config = AggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            svk: self.svk,
+            snarks: self.snarks.iter().map(SnarkWitness::without_witnesses).collect(),
+            instances: Vec::new(),
+            as_proof: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut HaloConstraintSystem<HaloFr>) -> Self::Config {
+        let main_gate_config = MainGate::<HaloFr>::configure(meta);
+        let range_config = RangeChip::<HaloFr>::configure(
+            meta,
+            &main_gate_config,
+            vec![BITS / LIMBS],
+            Rns::<HaloFq, HaloFr, LIMBS, BITS>::construct().overflow_lengths(),
+        );
+        AggregationConfig { main_gate_config, range_config }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<HaloFr>) -> Result<(), Error> {
+        let main_gate = config.main_gate();
+        config.range_chip().load_table(&mut layouter)?;
+
+        let (accumulator_limbs, snark_instances) = layouter.assign_region(
+            || "aggregate",
+            |region| {
+                let ctx = RegionCtx::new(region, 0);
+                let loader = Halo2Loader::new(config.ecc_chip(), ctx);
+                let (accumulator, snark_instances) =
+                    aggregate(&self.svk, &loader, &self.snarks, self.as_proof()).map_err(|_| Error::Synthesis)?;
+
+                let limbs = [accumulator.lhs, accumulator.rhs]
+                    .iter()
+                    .map(|p| loader.ecc_chip().assign_ec_point_to_limbs(&mut loader.ctx_mut(), p.assigned()))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let snark_instances = snark_instances.into_iter().map(|v| v.into_assigned()).collect_vec();
+                Ok((limbs.into_iter().flatten().collect_vec(), snark_instances))
+            },
+        )?;
+
+        for (row, limb) in accumulator_limbs.into_iter().enumerate() {
+            main_gate.expose_public(layouter.namespace(|| "accumulator"), limb, row)?;
+        }
+        for (i, value) in snark_instances.into_iter().enumerate() {
+            main_gate.expose_public(layouter.namespace(|| "snark instances"), value, 4 * LIMBS + i)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys of the aggregation circuit for a fixed list of inner circuits
+#[derive(Clone, Debug)]
+pub struct AggregationKeys {
+    pub vk: HaloVerifyingKey<G1Affine>,
+    pub pk: HaloProvingKey<G1Affine>,
+    pub num_instance: Vec<usize>,
+}
+
+/// Generate keys for aggregation of proofs of the same circuits as `snarks`
+pub fn setup(params: &Parameters<Bn256>, snarks: &[Snark]) -> Result<AggregationKeys, crate::error::Error> {
+    let circuit = AggregationCircuit::new(params, snarks)?.without_witnesses();
+    let num_instance = circuit.num_instance();
+    let setup_error = |e| crate::error::Error::Setup(format!("{:?}", e));
+    let vk = keygen_vk(&params.0, &circuit).map_err(setup_error)?;
+    let pk = keygen_pk(&params.0, vk.clone(), &circuit).map_err(setup_error)?;
+    Ok(AggregationKeys { vk, pk, num_instance })
+}
+
+/// Aggregate `snarks` into one proof, returns the accumulator limbs followed by the public inputs
+/// of `snarks`, and the proof
+pub fn aggregate_snarks(
+    params: &Parameters<Bn256>,
+    keys: &AggregationKeys,
+    snarks: &[Snark],
+) -> Result<(Vec<Num<Fr>>, Proof), crate::error::Error> {
+    let circuit = AggregationCircuit::new(params, snarks)?;
+    if circuit.num_instance() != keys.num_instance {
+        return Err(crate::error::Error::Synthesis("snarks do not match the aggregation keys".to_string()));
+    }
+    let inputs = circuit.inputs();
+    let instances = circuit.instances.clone();
+
+    let mut transcript = <EvmTranscript<G1Affine, _, _, _> as TranscriptWriterBuffer<_, _, _>>::init(Vec::new());
+    create_proof::<KZGCommitmentScheme<HaloBn256>, ProverGWC<_>, _, _, EvmTranscript<G1Affine, _, _, _>, _>(
+        &params.0,
+        &keys.pk,
+        &[circuit],
+        &[&[instances.as_slice()]],
+        OsRng,
+        &mut transcript,
+    )
+    .map_err(synthesis_error)?;
+
+    Ok((inputs, Proof(transcript.finalize())))
+}
+
+/// Verify the aggregate proof and the accumulator in its public inputs with one pairing check,
+/// `inputs` must contain the public inputs of the aggregated snarks after the accumulator limbs
+pub fn verify(
+    params: &Parameters<Bn256>,
+    keys: &AggregationKeys,
+    proof: &Proof,
+    inputs: &[Num<Fr>],
+) -> Result<bool, crate::error::Error> {
+    if inputs.len() != keys.num_instance.iter().sum::<usize>() {
+        return Err(crate::error::Error::Verification("wrong number of public inputs".to_string()));
+    }
+    let protocol = compile(
+        &params.0,
+        &keys.vk,
+        Config::kzg()
+            .with_num_instance(keys.num_instance.clone())
+            .with_accumulator_indices(Some(AggregationCircuit::accumulator_indices())),
+    );
+    let dk = (params.0.get_g()[0], params.0.g2(), params.0.s_g2()).into();
+    let instances = vec![inputs.iter().cloned().map(num_to_halo_fp).collect_vec()];
+
+    let mut transcript = <EvmTranscript<G1Affine, NativeLoader, _, _> as TranscriptReadBuffer<_, _, _>>::init(Cursor::new(
+        proof.0.clone(),
+    ));
+    let proof = match PlonkVerifier::read_proof(&dk, &protocol, &instances, &mut transcript) {
+        Ok(proof) => proof,
+        Err(_) => return Ok(false),
+    };
+    Ok(PlonkVerifier::verify(&dk, &protocol, &instances, &proof).is_ok())
+}
@@ -1,17 +1,142 @@
 use super::*;
+use crate::circuit::{cs::HandleKind, plonk::cs::{BuildCS, WitnessCS}};
+
+use halo2_curves::pairing::Engine as PairingEngine;
+use halo2_proofs::{
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::KZGCommitmentScheme,
+            multiopen::{ProverGWC, VerifierGWC},
+            strategy::AccumulatorStrategy,
+        },
+        VerificationStrategy,
+    },
+    transcript::{TranscriptReadBuffer, TranscriptWriterBuffer},
+    SerdeFormat,
+};
+use itertools::Itertools;
+use plonk_verifier::system::halo2::transcript::evm::EvmTranscript;
+use std::io::{self, Cursor, Read, Write};
+
+pub type G1Affine<E> = <<E as Engine>::BE as PairingEngine>::G1Affine;
 
 pub struct Bn256;
-pub struct Bls12_381;
 
-pub trait Engine {
+/// Curve for the PLONK backend. The halo2 calls are implemented per curve, because transcript and
+/// commitment scheme bounds differ between curves.
+pub trait Engine: Sized {
     type BE: halo2_curves::pairing::MultiMillerLoop+std::fmt::Debug;
     type Fq: PrimeField;
     type Fr: PrimeField;
+
+    fn keygen<const LOOKUP: bool>(
+        params: &ParamsKZG<Self::BE>,
+        circuit: &HaloCS<BuildCS<Self::Fr>, LOOKUP>,
+    ) -> Result<(VerifyingKey<G1Affine<Self>>, ProvingKey<G1Affine<Self>>), crate::error::Error>;
+
+    fn create_proof<H: HandleKind, R: RngCore, const LOOKUP: bool>(
+        params: &ParamsKZG<Self::BE>,
+        pk: &ProvingKey<G1Affine<Self>>,
+        circuit: HaloCS<WitnessCS<'_, Self::Fr, H>, LOOKUP>,
+        inputs: &[Num<Self::Fr>],
+        rng: R,
+    ) -> Result<Vec<u8>, crate::error::Error>;
+
+    fn verify_proof(
+        params: &ParamsKZG<Self::BE>,
+        vk: &VerifyingKey<G1Affine<Self>>,
+        proof: &[u8],
+        inputs: &[Num<Self::Fr>],
+    ) -> Result<bool, crate::error::Error>;
+
+    // `lookup` selects the circuit configuration the key was generated for, see `uses_lookup`
+    fn write_pk<W: Write>(pk: &ProvingKey<G1Affine<Self>>, writer: &mut W, format: SerdeFormat) -> io::Result<()>;
+    fn read_pk<R: Read>(reader: &mut R, format: SerdeFormat, lookup: bool) -> io::Result<ProvingKey<G1Affine<Self>>>;
+    fn write_vk<W: Write>(vk: &VerifyingKey<G1Affine<Self>>, writer: &mut W, format: SerdeFormat) -> io::Result<()>;
+    fn read_vk<R: Read>(reader: &mut R, format: SerdeFormat, lookup: bool) -> io::Result<VerifyingKey<G1Affine<Self>>>;
 }
 
 impl Engine for Bn256 {
     type BE = halo2_curves::bn256::Bn256;
     type Fq = crate::engines::bn256::Fq;
     type Fr = crate::engines::bn256::Fr;
-}
 
+    fn keygen<const LOOKUP: bool>(
+        params: &ParamsKZG<Self::BE>,
+        circuit: &HaloCS<BuildCS<Self::Fr>, LOOKUP>,
+    ) -> Result<(VerifyingKey<G1Affine<Self>>, ProvingKey<G1Affine<Self>>), crate::error::Error> {
+        let vk = keygen_vk(params, circuit).map_err(|e| crate::error::Error::Setup(format!("{:?}", e)))?;
+        let pk = keygen_pk(params, vk.clone(), circuit).map_err(|e| crate::error::Error::Setup(format!("{:?}", e)))?;
+        Ok((vk, pk))
+    }
+
+    fn create_proof<H: HandleKind, R: RngCore, const LOOKUP: bool>(
+        params: &ParamsKZG<Self::BE>,
+        pk: &ProvingKey<G1Affine<Self>>,
+        circuit: HaloCS<WitnessCS<'_, Self::Fr, H>, LOOKUP>,
+        inputs: &[Num<Self::Fr>],
+        rng: R,
+    ) -> Result<Vec<u8>, crate::error::Error> {
+        let instances = inputs.iter().cloned().map(num_to_halo_fp).collect_vec();
+
+        let mut transcript = <EvmTranscript<halo2_curves::bn256::G1Affine, _, _, _> as TranscriptWriterBuffer<_, _, _>>::init(Vec::new());
+        create_proof::<KZGCommitmentScheme<halo2_curves::bn256::Bn256>, ProverGWC<_>, _, _, EvmTranscript<halo2_curves::bn256::G1Affine, _, _, _>, _>(
+            params,
+            pk,
+            &[circuit],
+            &[&[instances.as_slice()]],
+            rng,
+            &mut transcript,
+        )
+        .map_err(|e| crate::error::Error::Synthesis(format!("{:?}", e)))?;
+        Ok(transcript.finalize())
+    }
+
+    fn verify_proof(
+        params: &ParamsKZG<Self::BE>,
+        vk: &VerifyingKey<G1Affine<Self>>,
+        proof: &[u8],
+        inputs: &[Num<Self::Fr>],
+    ) -> Result<bool, crate::error::Error> {
+        let instances = inputs.iter().cloned().map(num_to_halo_fp).collect_vec();
+
+        let mut transcript =
+            <EvmTranscript::<halo2_curves::bn256::G1Affine, _, _, _> as TranscriptReadBuffer<_, _, _>>
+            ::init(Cursor::new(proof.to_vec()));
+        let strategy = verify_proof::<_, VerifierGWC<_>, _, EvmTranscript::<halo2_curves::bn256::G1Affine, _, _, _>, _>(
+            params.verifier_params(),
+            vk,
+            AccumulatorStrategy::new(params.verifier_params()),
+            &[&[instances.as_slice()]],
+            &mut transcript,
+        ).map_err(|e| crate::error::Error::Verification(format!("{:?}", e)))?;
+
+        Ok(VerificationStrategy::<_, VerifierGWC<_>>::finalize(strategy))
+    }
+
+    fn write_pk<W: Write>(pk: &ProvingKey<G1Affine<Self>>, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        pk.write(writer, format)
+    }
+
+    fn read_pk<R: Read>(reader: &mut R, format: SerdeFormat, lookup: bool) -> io::Result<ProvingKey<G1Affine<Self>>> {
+        if lookup {
+            ProvingKey::read::<_, HaloCS<BuildCS<Self::Fr>, true>>(reader, format)
+        } else {
+            ProvingKey::read::<_, HaloCS<BuildCS<Self::Fr>, false>>(reader, format)
+        }
+    }
+
+    fn write_vk<W: Write>(vk: &VerifyingKey<G1Affine<Self>>, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        vk.write(writer, format)
+    }
+
+    fn read_vk<R: Read>(reader: &mut R, format: SerdeFormat, lookup: bool) -> io::Result<VerifyingKey<G1Affine<Self>>> {
+        if lookup {
+            VerifyingKey::read::<_, HaloCS<BuildCS<Self::Fr>, true>>(reader, format)
+        } else {
+            VerifyingKey::read::<_, HaloCS<BuildCS<Self::Fr>, false>>(reader, format)
+        }
+    }
+}
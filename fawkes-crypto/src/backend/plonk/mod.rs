@@ -3,11 +3,16 @@ pub mod verifier;
 pub mod plonk_config;
 pub mod engines;
 pub mod setup;
+pub mod solidity;
+pub mod aggregation;
 
 use std::fmt::Debug;
 use crate::{
+    backend::ProofSystem,
+    borsh::{BorshDeserialize, BorshSerialize},
     circuit::{
-        cs::{RCS, CS}
+        cs::{CSHandle, RCS, CS},
+        plonk::cs::{BuildCS, DebugCS, LookupTable, PlonkCS, WitnessCS},
     },
     core::signal::Signal,
     ff_uint::{Num, PrimeField, NumRepr},
@@ -19,6 +24,7 @@ use halo2_curves::{
 };
 
 use halo2_proofs::{
+    SerdeFormat,
     circuit::{AssignedCell,  Layouter, Region, SimpleFloorPlanner, Value},
     plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
     poly::{
@@ -29,7 +35,7 @@ use halo2_proofs::{
 
 use self::plonk_config::PlonkConfig;
 use engines::Engine;
-use halo2_rand::rngs::OsRng;
+use halo2_rand::{rngs::OsRng, RngCore};
 
 
 pub fn num_to_halo_fp<Fx: PrimeField, Fy: HaloPrimeField>(
@@ -77,16 +83,23 @@ pub fn num_to_halo_fp_value<Fx: PrimeField, Fy: HaloPrimeField>(
     }
 }
 
+/// halo2 circuit of a PLONK constraint system. The lookup argument and its tables are configured only with
+/// `LOOKUP`, it is enabled for circuits which have lookup gates.
 #[repr(transparent)]
 #[derive(Clone, Debug)]
-pub struct HaloCS<C:CS>(RCS<C>);
+pub struct HaloCS<C:PlonkCS, const LOOKUP: bool = false>(RCS<C>);
 
-impl <C:CS> HaloCS<C> {
+impl <C:PlonkCS, const LOOKUP: bool> HaloCS<C, LOOKUP> {
     pub fn new(inner:RCS<C>) -> Self {
         Self(inner)
     }
 }
 
+/// Whether the key was generated for a circuit with lookup gates, that is, for `HaloCS<_, true>`
+pub fn uses_lookup<C: halo2_proofs::arithmetic::CurveAffine>(vk: &halo2_proofs::plonk::VerifyingKey<C>) -> bool {
+    !vk.cs().lookups().is_empty()
+}
+
 #[derive(Clone, Debug)]
 enum Halo2Cell<F:HaloPrimeField> {
     Input(usize),
@@ -135,7 +148,7 @@ fn assign_advice_ex<
 }
 
 
-impl<F: HaloPrimeField, C:CS> Circuit<F> for HaloCS<C> {
+impl<F: HaloPrimeField, C:PlonkCS, const LOOKUP: bool> Circuit<F> for HaloCS<C, LOOKUP> {
     type Config = plonk_config::PlonkConfig;
     type FloorPlanner = SimpleFloorPlanner;
 
@@ -144,7 +157,7 @@ impl<F: HaloPrimeField, C:CS> Circuit<F> for HaloCS<C> {
     }
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-        PlonkConfig::configure(meta)
+        PlonkConfig::configure(meta, LOOKUP)
     }
 
     fn synthesize(
@@ -158,6 +171,21 @@ impl<F: HaloPrimeField, C:CS> Circuit<F> for HaloCS<C> {
 
         let public_indexes = cs.as_public();
 
+        if let Some(lookup) = config.lookup.as_ref() {
+            layouter.assign_table(|| "lookup tables", |mut table| {
+                // the zero row is matched by rows without lookup
+                let rows = std::iter::once((0, [0; 3]))
+                    .chain(LookupTable::ALL.iter().flat_map(|t| t.rows().into_iter().map(move |r| (t.id(), r))));
+                for (offset, (id, row)) in rows.enumerate() {
+                    let values = [id as u64, row[0], row[1], row[2]];
+                    for (column, v) in lookup.table.iter().zip(values) {
+                        table.assign_cell(|| format!("table[{}]", offset), *column, offset, || Value::known(F::from(v)))?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
         layouter.assign_region(|| format!("syntesize circuit"), |mut region| {
 
             let mut var_cells = vec![Option::<Halo2Cell<F>>::None; num_var];
@@ -178,6 +206,7 @@ impl<F: HaloPrimeField, C:CS> Circuit<F> for HaloCS<C> {
                 adv_helper("x", config.a, g.x)?;
                 adv_helper("y", config.b, g.y)?;
                 adv_helper("z", config.c, g.z)?;
+                adv_helper("w", config.d, g.w)?;
 
                 let mut fixed_helper = |ann, fix, val| {
                     region.assign_fixed(
@@ -186,11 +215,39 @@ impl<F: HaloPrimeField, C:CS> Circuit<F> for HaloCS<C> {
                         || num_to_halo_fp_value::<_,F>(Some(val))
                     )
                 };
-                fixed_helper("a", config.q_a, g.a)?;
-                fixed_helper("b", config.q_b, g.b)?;
-                fixed_helper("c", config.q_c, g.c)?;
-                fixed_helper("d", config.q_ab, g.d)?;
-                fixed_helper("e", config.constant, g.e)?;
+                let zero = Num::ZERO;
+                let is_lookup = g.table != 0;
+                // lookup rows keep their coefficients in the lookup columns
+                let (arith, lookup) = if is_lookup {
+                    ([zero; 7], [g.a, g.b, g.c, g.d, g.e, g.f])
+                } else {
+                    ([g.a, g.b, g.c, g.f, g.d, g.g, g.e], [zero; 6])
+                };
+                fixed_helper("a", config.q_a, arith[0])?;
+                fixed_helper("b", config.q_b, arith[1])?;
+                fixed_helper("c", config.q_c, arith[2])?;
+                fixed_helper("f", config.q_d, arith[3])?;
+                fixed_helper("d", config.q_ab, arith[4])?;
+                fixed_helper("g", config.q_next, arith[5])?;
+
+                // the offset of the S-box input shares `e` with the arithmetic constant
+                let (constant, pow5_c) = if g.pow5 { (zero, arith[6]) } else { (arith[6], zero) };
+                fixed_helper("e", config.constant, constant)?;
+                fixed_helper("q_pow5", config.q_pow5, if g.pow5 { Num::ONE } else { zero })?;
+                fixed_helper("pow5_c", config.pow5_c, pow5_c)?;
+
+                match config.lookup.as_ref() {
+                    Some(l) => {
+                        fixed_helper("q_lookup", l.q_lookup, Num::from(g.table as u64))?;
+                        for i in 0..3 {
+                            fixed_helper("lookup_k", l.lookup_k[i], lookup[i])?;
+                            fixed_helper("lookup_c", l.lookup_c[i], lookup[i + 3])?;
+                        }
+                    }
+                    // lookup gate in a circuit configured without the lookup argument
+                    None if is_lookup => return Err(Error::Synthesis),
+                    None => {}
+                }
             }
 
             Ok(())
@@ -210,7 +267,12 @@ impl <E: Engine> Parameters<E> where
 
 {
     pub fn setup(k:usize) -> Self {
-        let params = ParamsKZG::<<E as Engine>::BE>::setup(k as u32, OsRng);
+        Self::setup_with_rng(k, OsRng)
+    }
+
+    // seeded rng gives reproducible parameters for tests, it is insecure for production
+    pub fn setup_with_rng<R: RngCore>(k:usize, rng: R) -> Self {
+        let params = ParamsKZG::<<E as Engine>::BE>::setup(k as u32, rng);
         Self(params)
     }
 
@@ -222,3 +284,96 @@ impl <E: Engine> Parameters<E> where
         Ok(Self(ParamsKZG::<E::BE>::read(reader)?))
     }
 }
+
+/// PLONK as a `ProofSystem`. Keys are serialized in `SerdeFormat::RawBytes`.
+pub struct Plonk<E: Engine>(std::marker::PhantomData<E>);
+
+impl<E: Engine> ProofSystem for Plonk<E> where
+    <<E as Engine>::BE as halo2_curves::pairing::Engine>::Scalar: HaloPrimeField,
+    <<E as Engine>::BE as halo2_curves::pairing::Engine>::G1Affine: SerdeObject,
+    <<E as Engine>::BE as halo2_curves::pairing::Engine>::G2Affine: SerdeObject,
+{
+    type Fr = E::Fr;
+    type SetupCS = BuildCS<E::Fr>;
+    type WitnessCS<'a> = WitnessCS<'a, E::Fr>;
+    type DebugCS = DebugCS<E::Fr>;
+
+    type Params = Parameters<E>;
+    type ProvingKey = setup::ProvingKey<E>;
+    type VerifyingKey = setup::VerifyingKey<E>;
+    type Proof = prover::Proof;
+
+    fn try_setup<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        params: &Self::Params,
+        circuit: C,
+    ) -> Result<(Self::VerifyingKey, Self::ProvingKey), crate::error::Error> {
+        setup::try_setup(params, circuit)
+    }
+
+    fn try_prove<'a, Pub: Signal<Self::WitnessCS<'a>>, Sec: Signal<Self::WitnessCS<'a>>, C: Fn(Pub, Sec)>(
+        params: &Self::Params,
+        pk: &'a Self::ProvingKey,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+        circuit: C,
+    ) -> Result<(Vec<Num<E::Fr>>, Self::Proof), crate::error::Error> {
+        prover::try_prove(params, pk, input_pub, input_sec, circuit)
+    }
+
+    fn try_verify(
+        params: &Self::Params,
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        inputs: &[Num<E::Fr>],
+    ) -> Result<bool, crate::error::Error> {
+        verifier::try_verify(params, vk, proof, inputs)
+    }
+
+    fn compute_public_inputs<Pub: Signal<Self::DebugCS>, Sec: Signal<Self::DebugCS>, C: Fn(Pub, Sec)>(
+        circuit: C,
+        input_pub: &Pub::Value,
+        input_sec: &Sec::Value,
+    ) -> Result<Vec<Num<E::Fr>>, crate::error::Error> {
+        prover::compute_public_inputs::<E, _, _, _>(circuit, input_pub, input_sec)
+    }
+
+    fn write_params<W: std::io::Write>(params: &Self::Params, writer: &mut W) -> std::io::Result<()> {
+        params.write(writer)
+    }
+
+    fn read_params(reader: &mut &[u8]) -> std::io::Result<Self::Params> {
+        Parameters::read(reader)
+    }
+
+    fn write_proving_key<W: std::io::Write>(pk: &Self::ProvingKey, writer: &mut W) -> std::io::Result<()> {
+        pk.write(writer, SerdeFormat::RawBytes)
+    }
+
+    fn write_verifying_key<W: std::io::Write>(vk: &Self::VerifyingKey, writer: &mut W) -> std::io::Result<()> {
+        vk.write(writer, SerdeFormat::RawBytes)
+    }
+
+    fn read_proving_key<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        reader: &mut &[u8],
+        params: &Self::Params,
+        circuit: C,
+    ) -> std::io::Result<Self::ProvingKey> {
+        setup::ProvingKey::read(reader, SerdeFormat::RawBytes, params, circuit)
+    }
+
+    fn read_verifying_key<Pub: Signal<Self::SetupCS>, Sec: Signal<Self::SetupCS>, C: Fn(Pub, Sec)>(
+        reader: &mut &[u8],
+        params: &Self::Params,
+        circuit: C,
+    ) -> std::io::Result<Self::VerifyingKey> {
+        setup::VerifyingKey::read(reader, SerdeFormat::RawBytes, params, circuit)
+    }
+
+    fn write_proof<W: std::io::Write>(proof: &Self::Proof, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(proof, writer)
+    }
+
+    fn read_proof(reader: &mut &[u8]) -> std::io::Result<Self::Proof> {
+        BorshDeserialize::deserialize(reader)
+    }
+}
@@ -1,68 +1,144 @@
 use halo2_curves::ff::PrimeField;
 use halo2_proofs::{
-    plonk::{Advice, Any, Column, ConstraintSystem, Fixed, Instance},
+    plonk::{Advice, Any, Column, ConstraintSystem, Fixed, Instance, TableColumn},
     poly::Rotation,
 };
 
+/// Four advice columns with two gates:
+///
+/// ```ignore
+/// q_a*a + q_b*b + q_c*c + q_d*d + q_ab*a*b + q_next*d[next] + constant == 0
+/// q_pow5*((a + pow5_c)^5 - b) == 0
+/// ```
+///
+/// and, for circuits with lookup gates, the lookup of `(q_lookup, lookup_k[0]*a + lookup_c[0],
+/// lookup_k[1]*b + lookup_c[1], lookup_k[2]*c + lookup_c[2])` in `table`, which is `(0, 0, 0, 0)` on rows
+/// without lookup.
+///
+/// Only the selectors of one kind are nonzero on each row.
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct PlonkConfig {
     pub a: Column<Advice>,
     pub b: Column<Advice>,
     pub c: Column<Advice>,
+    pub d: Column<Advice>,
     pub q_a: Column<Fixed>,
     pub q_b: Column<Fixed>,
     pub q_c: Column<Fixed>,
+    pub q_d: Column<Fixed>,
     pub q_ab: Column<Fixed>,
+    pub q_next: Column<Fixed>,
     pub constant: Column<Fixed>,
+    pub q_pow5: Column<Fixed>,
+    pub pow5_c: Column<Fixed>,
+    pub lookup: Option<LookupConfig>,
     pub instance: Column<Instance>,
 }
 
+/// Columns of the lookup argument, they are not configured for circuits without lookup gates
+#[derive(Clone)]
+pub struct LookupConfig {
+    pub q_lookup: Column<Fixed>,
+    pub lookup_k: [Column<Fixed>; 3],
+    pub lookup_c: [Column<Fixed>; 3],
+    // table id and the row of the table
+    pub table: [TableColumn; 4],
+}
+
 impl PlonkConfig {
-    pub fn configure<F: PrimeField>(meta: &mut ConstraintSystem<F>) -> Self {
+    pub fn configure<F: PrimeField>(meta: &mut ConstraintSystem<F>, lookup: bool) -> Self {
         let a = meta.advice_column();
         let b = meta.advice_column();
         let c = meta.advice_column();
+        let d = meta.advice_column();
 
         let q_a = meta.fixed_column();
         let q_b = meta.fixed_column();
         let q_c = meta.fixed_column();
+        let q_d = meta.fixed_column();
 
         let q_ab = meta.fixed_column();
+        let q_next = meta.fixed_column();
 
         let constant = meta.fixed_column();
+
+        let q_pow5 = meta.fixed_column();
+        let pow5_c = meta.fixed_column();
+
         let instance = meta.instance_column();
 
         meta.enable_equality(a);
         meta.enable_equality(b);
         meta.enable_equality(c);
+        meta.enable_equality(d);
         meta.enable_equality(instance);
 
-        meta.create_gate("", |meta| {
-            let [a, b, c, q_a, q_b, q_c, q_ab, constant] = [
+        meta.create_gate("arith", |meta| {
+            let [a, b, c, d, q_a, q_b, q_c, q_d, q_ab, q_next, constant] = [
                 a.into(),
                 b.into(),
                 c.into(),
+                d.into(),
                 q_a.into(),
                 q_b.into(),
                 q_c.into(),
+                q_d.into(),
                 q_ab.into(),
+                q_next.into(),
                 constant.into(),
             ]
             .map(|column: Column<Any>| meta.query_any(column, Rotation::cur()));
+            let d_next = meta.query_advice(d, Rotation::next());
+
+            vec![q_a * a.clone() + q_b * b.clone() + q_c * c + q_d * d + q_ab * a * b + q_next * d_next + constant]
+        });
+
+        meta.create_gate("pow5", |meta| {
+            let [a, b, q_pow5, pow5_c] = [a.into(), b.into(), q_pow5.into(), pow5_c.into()]
+                .map(|column: Column<Any>| meta.query_any(column, Rotation::cur()));
+
+            let x = a + pow5_c;
+            let x2 = x.clone() * x.clone();
+            vec![q_pow5 * (x2.clone() * x2 * x - b)]
+        });
+
+        let lookup = lookup.then(|| {
+            let q_lookup = meta.fixed_column();
+            let lookup_k = [(); 3].map(|_| meta.fixed_column());
+            let lookup_c = [(); 3].map(|_| meta.fixed_column());
+            let table = [(); 4].map(|_| meta.lookup_table_column());
+
+            meta.lookup("table", |meta| {
+                let tag = meta.query_any(q_lookup, Rotation::cur());
+                let mut res = vec![(tag, table[0])];
+                for (i, adv) in [a, b, c].into_iter().enumerate() {
+                    let k = meta.query_any(lookup_k[i], Rotation::cur());
+                    let c = meta.query_any(lookup_c[i], Rotation::cur());
+                    let v = meta.query_advice(adv, Rotation::cur());
+                    res.push((k * v + c, table[i + 1]));
+                }
+                res
+            });
 
-            vec![q_a * a.clone() + q_b * b.clone() + q_c * c + q_ab * a * b + constant]
+            LookupConfig { q_lookup, lookup_k, lookup_c, table }
         });
 
         PlonkConfig {
             a,
             b,
             c,
+            d,
             q_a,
             q_b,
             q_c,
+            q_d,
             q_ab,
+            q_next,
             constant,
+            q_pow5,
+            pow5_c,
+            lookup,
             instance,
         }
     }
@@ -1,37 +1,12 @@
-
 use super::*;
 
 #[cfg(feature = "serde_support")]
 use serde::{Serialize, Deserialize};
-#[cfg(feature = "borsh_support")]
 use borsh::{BorshSerialize, BorshDeserialize};
 
-use halo2_proofs::{
-    plonk::{
-        create_proof,Circuit, ProvingKey as PlonkProvingKey,
-    },
-    poly::{
-        kzg::{
-            commitment::{KZGCommitmentScheme, ParamsKZG},
-            multiopen::{ProverGWC},
-        },
-    },
-    transcript::{EncodedChallenge, TranscriptReadBuffer, TranscriptWriterBuffer}
-};
-
-use plonk_verifier::system::halo2::transcript::evm::EvmTranscript;
-use itertools::Itertools;
-use std::{
-    io::{Cursor},
-    rc::Rc,
-    cell::{RefCell}
-};
-
-use halo2_curves::pairing::{MultiMillerLoop};
-
-use halo2_rand::rngs::OsRng;
-
-use crate::circuit::cs::BuildCS;
+use halo2_rand::{rngs::OsRng, RngCore};
+
+use crate::circuit::{cs::{HandleKind, RcHandle}, plonk::cs::{DebugCS, WitnessCS}};
 
 use super::setup::{ProvingKey};
 
@@ -41,64 +16,209 @@ use super::setup::{ProvingKey};
 pub struct Proof(pub Vec<u8>);
 
 pub fn prove<
-    Pub: Signal<BuildCS<crate::engines::bn256::Fr>>,
-    Sec: Signal<BuildCS<crate::engines::bn256::Fr>>,
+    'a,
+    E: Engine,
+    Pub: Signal<WitnessCS<'a, E::Fr>>,
+    Sec: Signal<WitnessCS<'a, E::Fr>>,
+    C: Fn(Pub, Sec)
+>(
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+) -> (Vec<Num<E::Fr>>, Proof)
+{
+    try_prove(params, pk, input_pub, input_sec, circuit).unwrap()
+}
+
+pub fn try_prove<
+    'a,
+    E: Engine,
+    Pub: Signal<WitnessCS<'a, E::Fr>>,
+    Sec: Signal<WitnessCS<'a, E::Fr>>,
     C: Fn(Pub, Sec)
 >(
-    params: &Parameters<super::engines::Bn256>,
-    pk: &ProvingKey<super::engines::Bn256>,
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
     input_pub: &Pub::Value,
     input_sec: &Sec::Value,
     circuit: C,
-) -> (Vec<Num<crate::engines::bn256::Fr>>, Proof)
+) -> Result<(Vec<Num<E::Fr>>, Proof), crate::error::Error>
 {
-    let cs = BuildCS::<crate::engines::bn256::Fr>::new(false);
-    let ref rcs = Rc::new(RefCell::new(cs));
+    try_prove_with_rng(params, pk, input_pub, input_sec, circuit, OsRng)
+}
+
+/// Same as `prove`, but with caller provided randomness. Use a seeded rng only for reproducible tests.
+pub fn prove_with_rng<
+    'a,
+    E: Engine,
+    Pub: Signal<WitnessCS<'a, E::Fr>>,
+    Sec: Signal<WitnessCS<'a, E::Fr>>,
+    C: Fn(Pub, Sec),
+    R: RngCore
+>(
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+    rng: R,
+) -> (Vec<Num<E::Fr>>, Proof)
+{
+    try_prove_with_rng(params, pk, input_pub, input_sec, circuit, rng).unwrap()
+}
 
+pub fn try_prove_with_rng<
+    'a,
+    E: Engine,
+    Pub: Signal<WitnessCS<'a, E::Fr>>,
+    Sec: Signal<WitnessCS<'a, E::Fr>>,
+    C: Fn(Pub, Sec),
+    R: RngCore
+>(
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+    rng: R,
+) -> Result<(Vec<Num<E::Fr>>, Proof), crate::error::Error>
+{
+    let ref rcs = pk.get_witness_rcs::<RcHandle>();
     let signal_pub = Pub::alloc(rcs, Some(input_pub));
     signal_pub.inputize();
     let signal_sec = Sec::alloc(rcs, Some(input_sec));
 
     circuit(signal_pub, signal_sec);
 
-    let bcs = HaloCS::<BuildCS<crate::engines::bn256::Fr>>::new(rcs.clone());
+    try_prove_cs_with_rng(params, pk, rcs, rng)
+}
 
-    let inputs = {
-        let cs = rcs.borrow();
-        let mut res = Vec::with_capacity(cs.num_input());
-        for i in 0..cs.num_input() {
-            res.push(cs.get_value(cs.as_public()[i]).unwrap())
-        }
-        res
-    };
+/// Prove with witness built by the caller: `pk.get_witness_rcs()`, public signal allocated and inputized,
+/// secret signal allocated and the circuit applied to both, the same steps as `prove` does. `prove` and the
+/// `ProofSystem` impl use `RcHandle`, with `ArcHandle` the witness can be built on a thread pool or across
+/// await points.
+pub fn prove_cs<'a, E: Engine, H: HandleKind>(
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
+    rcs: &RCS<WitnessCS<'a, E::Fr, H>>,
+) -> (Vec<Num<E::Fr>>, Proof)
+{
+    try_prove_cs(params, pk, rcs).unwrap()
+}
+
+pub fn try_prove_cs<'a, E: Engine, H: HandleKind>(
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
+    rcs: &RCS<WitnessCS<'a, E::Fr, H>>,
+) -> Result<(Vec<Num<E::Fr>>, Proof), crate::error::Error>
+{
+    try_prove_cs_with_rng(params, pk, rcs, OsRng)
+}
+
+pub fn try_prove_cs_with_rng<'a, E: Engine, H: HandleKind, R: RngCore>(
+    params: &Parameters<E>,
+    pk: &'a ProvingKey<E>,
+    rcs: &RCS<WitnessCS<'a, E::Fr, H>>,
+    rng: R,
+) -> Result<(Vec<Num<E::Fr>>, Proof), crate::error::Error>
+{
+    let inputs = witness_inputs(rcs)?;
 
-    let inputs_converted = inputs.iter().cloned().map(num_to_halo_fp).collect_vec();
-
-    let proof = {
-        let params = &params.0;
-        let pk = &pk.0;
-        let instances = vec![inputs_converted];
-        // MockProver::run(params.k(), &circuit, instances.clone())
-        //     .unwrap()
-        //     .assert_satisfied();
-
-        let instances = instances
-            .iter()
-            .map(|instances| instances.as_slice())
-            .collect_vec();
-
-        let mut transcript = <EvmTranscript<halo2_curves::bn256::G1Affine, _, _, _> as TranscriptWriterBuffer<_, _, _>>::init(Vec::new());
-        create_proof::<KZGCommitmentScheme<halo2_curves::bn256::Bn256>, ProverGWC<_>, _, _, EvmTranscript<halo2_curves::bn256::G1Affine, _, _, _>, _>(
-            params,
-            pk,
-            &[bcs],
-            &[instances.as_slice()],
-            OsRng,
-            &mut transcript,
-        )
-        .unwrap();
-        transcript.finalize()
+    let proof = if uses_lookup(pk.key.get_vk()) {
+        E::create_proof(&params.0, &pk.key, HaloCS::<WitnessCS<E::Fr, H>, true>::new(rcs.clone()), &inputs, rng)?
+    } else {
+        E::create_proof(&params.0, &pk.key, HaloCS::<WitnessCS<E::Fr, H>, false>::new(rcs.clone()), &inputs, rng)?
     };
 
-    (inputs, Proof(proof))
+    Ok((inputs, Proof(proof)))
+}
+
+/// Run the circuit on `DebugCS` without keys and proving, which checks the gates as they are added and does not
+/// keep them, and return the public inputs in the `as_public` order, the same as `prove` returns them.
+pub fn compute_public_inputs<
+    E: Engine,
+    Pub: Signal<DebugCS<E::Fr>>,
+    Sec: Signal<DebugCS<E::Fr>>,
+    C: Fn(Pub, Sec)
+>(
+    circuit: C,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+) -> Result<Vec<Num<E::Fr>>, crate::error::Error>
+{
+    let ref rcs = DebugCS::<E::Fr>::rc_collecting();
+    let signal_pub = Pub::alloc(rcs, Some(input_pub));
+    signal_pub.inputize();
+    let signal_sec = Sec::alloc(rcs, Some(input_sec));
+
+    circuit(signal_pub, signal_sec);
+
+    let cs = rcs.borrow();
+    if let Some(v) = cs.unassigned {
+        return Err(crate::error::Error::Synthesis(format!("value of variable {} is not assigned", v)));
+    }
+    if let Some(g) = cs.first_unsatisfied() {
+        return Err(crate::error::Error::Synthesis(g.to_string()));
+    }
+    cs.as_public()
+        .iter()
+        .enumerate()
+        .map(|(i, v)| cs.get_value(*v).ok_or_else(||
+            crate::error::Error::Synthesis(format!("public input {} is not assigned", i))
+        ))
+        .collect()
+}
+
+// Replay `circuit` over the cached gate layout of `pk`, returns the witness and the public inputs
+pub(crate) fn build_witness<
+    'a,
+    E: Engine,
+    Pub: Signal<WitnessCS<'a, E::Fr>>,
+    Sec: Signal<WitnessCS<'a, E::Fr>>,
+    C: Fn(Pub, Sec)
+>(
+    pk: &'a ProvingKey<E>,
+    input_pub: &Pub::Value,
+    input_sec: &Sec::Value,
+    circuit: C,
+) -> Result<(RCS<WitnessCS<'a, E::Fr>>, Vec<Num<E::Fr>>), crate::error::Error>
+{
+    let ref rcs = pk.get_witness_rcs::<RcHandle>();
+
+    let signal_pub = Pub::alloc(rcs, Some(input_pub));
+    signal_pub.inputize();
+    let signal_sec = Sec::alloc(rcs, Some(input_sec));
+
+    circuit(signal_pub, signal_sec);
+
+    let inputs = witness_inputs(rcs)?;
+    Ok((rcs.clone(), inputs))
+}
+
+// Check that the replayed circuit is complete, returns its public inputs
+fn witness_inputs<Fr: PrimeField, H: HandleKind>(
+    rcs: &RCS<WitnessCS<'_, Fr, H>>,
+) -> Result<Vec<Num<Fr>>, crate::error::Error>
+{
+    {
+        let cs = rcs.borrow();
+        if let Some(v) = cs.unassigned {
+            return Err(crate::error::Error::Synthesis(format!("value of variable {} is not assigned", v)));
+        }
+        if cs.gate_index != cs.num_gates {
+            return Err(crate::error::Error::Synthesis("circuit does not match the gate layout of the proving key".to_string()));
+        }
+    }
+
+    let cs = rcs.borrow();
+    let mut res = Vec::with_capacity(cs.num_input());
+    for i in 0..cs.num_input() {
+        let v = cs.get_value(cs.as_public()[i]).ok_or_else(||
+            crate::error::Error::Synthesis(format!("public input {} is not assigned", i))
+        )?;
+        res.push(v)
+    }
+    Ok(res)
 }
@@ -1,51 +1,296 @@
-use super::{*, engines::Bn256};
+use super::*;
 use halo2_curves::pairing::Engine as PairingEngine;
-use crate::backend::plonk::engines::Engine;
-use halo2_proofs::plonk::{
-    keygen_pk,
-    keygen_vk,
-    VerifyingKey as HaloVerifyingKey,
-    ProvingKey as HaloProvingKey,
+use halo2_proofs::{
+    plonk::{
+        VerifyingKey as HaloVerifyingKey,
+        ProvingKey as HaloProvingKey,
+    },
+    SerdeFormat,
 };
 
 use std::{
+    io::{self, Read, Write},
     rc::Rc,
     cell::{RefCell}
 };
 
-use crate::{circuit::cs::BuildCS, engines::bn256::Fr};
+use blake2_rfc::blake2s::Blake2s;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
+use crate::{
+    borsh::BorshSerialize,
+    circuit::{cs::HandleKind, plonk::cs::{BuildCS, Gate, PlonkCS, WitnessCS}},
+    error::Error,
+};
+
+// version of the serialized key layout, bump on incompatible changes
+const KEY_VERSION: u32 = 1;
+const PK_MAGIC: &[u8; 4] = b"fkpk";
+const VK_MAGIC: &[u8; 4] = b"fkvk";
+
+/// Proving key together with the circuit it was generated for
 #[derive(Clone, Debug)]
-pub struct ProvingKey<E: Engine>(
-    pub HaloProvingKey<<E::BE as PairingEngine>::G1Affine>
-);
+pub struct ProvingKey<E: Engine> {
+    pub key: HaloProvingKey<<E::BE as PairingEngine>::G1Affine>,
+    // `circuit_hash` of the circuit
+    pub hash: [u8; 32],
+    pub num_gates: u32,
+    // brotli compressed gates replayed by `WitnessCS` at proving time
+    pub gates: Vec<u8>,
+    pub num_vars: usize,
+    pub public: Vec<usize>,
+}
 
+/// Verifying key together with the hash of the circuit it was generated for
 #[derive(Clone, Debug)]
 pub struct VerifyingKey<E: Engine>(
-    pub HaloVerifyingKey<<E::BE as PairingEngine>::G1Affine>
+    pub HaloVerifyingKey<<E::BE as PairingEngine>::G1Affine>,
+    pub [u8; 32],
 );
 
-pub fn setup<
-    'a,
+fn hash_num<Fr: PrimeField>(h: &mut Blake2s, n: Num<Fr>) {
+    n.to_uint().as_inner().as_ref().iter().for_each(|x| h.update(&x.to_le_bytes()));
+}
+
+/// Blake2s hash of the gates and public inputs of `cs`
+pub fn circuit_hash<Fr: PrimeField>(cs: &BuildCS<Fr>) -> [u8; 32] {
+    layout_hash(cs.values.len(), &cs.public, cs.gates.len(), cs.gates.iter().cloned())
+}
+
+// same as `circuit_hash`, for the layout stored in the proving key
+fn layout_hash<Fr: PrimeField, I: Iterator<Item = Gate<Fr>>>(num_vars: usize, public: &[usize], num_gates: usize, gates: I) -> [u8; 32] {
+    let mut h = Blake2s::new(32);
+    h.update(&(num_vars as u64).to_le_bytes());
+    h.update(&(public.len() as u64).to_le_bytes());
+    public.iter().for_each(|i| h.update(&(*i as u64).to_le_bytes()));
+    h.update(&(num_gates as u64).to_le_bytes());
+    for g in gates {
+        for (k, v) in [(g.a, g.x), (g.b, g.y), (g.c, g.z), (g.f, g.w)] {
+            hash_num(&mut h, k);
+            h.update(&(v as u64).to_le_bytes());
+        }
+        hash_num(&mut h, g.d);
+        hash_num(&mut h, g.e);
+        hash_num(&mut h, g.g);
+        h.update(&[g.pow5 as u8, g.table]);
+    }
+
+    let mut res = [0u8; 32];
+    res.copy_from_slice(h.finalize().as_bytes());
+    res
+}
+
+fn compress_gates<Fr: PrimeField>(cs: &BuildCS<Fr>) -> io::Result<Vec<u8>> {
+    let mut buf = std::io::Cursor::new(vec![]);
+    let mut c = brotli::CompressorWriter::new(&mut buf, 4096, 9, 22);
+    for g in cs.gates.iter() {
+        c.write_all(&g.try_to_vec()?)?;
+    }
+
+    c.flush()?;
+    drop(c);
+
+    Ok(buf.into_inner())
+}
+
+fn build_cs<
+    Fr: PrimeField,
     Pub: Signal<BuildCS<Fr>>,
     Sec: Signal<BuildCS<Fr>>,
     C: Fn(Pub, Sec)
->(
-    params: &'a Parameters<Bn256>,
-    circuit: C
-) -> (VerifyingKey<Bn256>, ProvingKey<Bn256>) {
+>(circuit: C) -> RCS<BuildCS<Fr>> {
     let cs = BuildCS::<Fr>::new(false);
-    let ref rcs = Rc::new(RefCell::new(cs));
+    let rcs = Rc::new(RefCell::new(cs));
 
-    let signal_pub = Pub::alloc(rcs, None);
+    let signal_pub = Pub::alloc(&rcs, None);
     signal_pub.inputize();
-    let signal_sec = Sec::alloc(rcs, None);
+    let signal_sec = Sec::alloc(&rcs, None);
 
     circuit(signal_pub, signal_sec);
-    let bcs = HaloCS::<BuildCS<Fr>>::new(rcs.clone());
+    rcs
+}
+
+fn format_id(format: SerdeFormat) -> u8 {
+    match format {
+        SerdeFormat::Processed => 0,
+        SerdeFormat::RawBytes => 1,
+        SerdeFormat::RawBytesUnchecked => 2,
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// magic, version, serde format, k, circuit hash and whether the circuit has the lookup argument
+fn write_header<W: Write>(writer: &mut W, magic: &[u8; 4], format: SerdeFormat, k: u32, hash: &[u8; 32], lookup: bool) -> io::Result<()> {
+    writer.write_all(magic)?;
+    writer.write_u32::<LittleEndian>(KEY_VERSION)?;
+    writer.write_u8(format_id(format))?;
+    writer.write_u32::<LittleEndian>(k)?;
+    writer.write_all(hash)?;
+    writer.write_u8(lookup as u8)
+}
+
+// returns the circuit hash of the key and the lookup flag
+fn read_header<R: Read>(reader: &mut R, magic: &[u8; 4], format: SerdeFormat, k: u32) -> io::Result<([u8; 32], bool)> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    if &buf != magic {
+        return Err(invalid_data("wrong magic"));
+    }
+    if reader.read_u32::<LittleEndian>()? != KEY_VERSION {
+        return Err(invalid_data("unsupported key version"));
+    }
+    if reader.read_u8()? != format_id(format) {
+        return Err(invalid_data("serde format mismatch"));
+    }
+    if reader.read_u32::<LittleEndian>()? != k {
+        return Err(invalid_data("key was generated for different parameters"));
+    }
+    let mut hash = [0u8; 32];
+    reader.read_exact(&mut hash)?;
+    let lookup = match reader.read_u8()? {
+        0 => false,
+        1 => true,
+        _ => return Err(invalid_data("wrong lookup flag")),
+    };
+    Ok((hash, lookup))
+}
+
+fn write_layout<W: Write, E: Engine>(writer: &mut W, pk: &ProvingKey<E>) -> io::Result<()> {
+    writer.write_u64::<LittleEndian>(pk.num_vars as u64)?;
+    writer.write_u64::<LittleEndian>(pk.public.len() as u64)?;
+    pk.public.iter().try_for_each(|i| writer.write_u64::<LittleEndian>(*i as u64))?;
+    writer.write_u32::<LittleEndian>(pk.num_gates)?;
+    writer.write_u64::<LittleEndian>(pk.gates.len() as u64)?;
+    writer.write_all(&pk.gates)
+}
+
+// number of variables, public variables, number of gates and compressed gates
+fn read_layout<R: Read>(reader: &mut R) -> io::Result<(usize, Vec<usize>, u32, Vec<u8>)> {
+    let num_vars = reader.read_u64::<LittleEndian>()? as usize;
+    let num_public = reader.read_u64::<LittleEndian>()?;
+    // sizes are untrusted, the buffers grow with the data actually read
+    let mut public = vec![];
+    for _ in 0..num_public {
+        public.push(reader.read_u64::<LittleEndian>()? as usize);
+    }
+    let num_gates = reader.read_u32::<LittleEndian>()?;
+    let size = reader.read_u64::<LittleEndian>()?;
+    let mut gates = vec![];
+    reader.by_ref().take(size).read_to_end(&mut gates)?;
+    if gates.len() as u64 != size {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated gate layout"));
+    }
+    Ok((num_vars, public, num_gates, gates))
+}
+
+impl<E: Engine> ProvingKey<E> {
+    pub fn get_witness_rcs<H: HandleKind>(&self) -> RCS<WitnessCS<E::Fr, H>> {
+        WitnessCS::rc_new(self.num_gates as usize, &self.gates)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        write_header(writer, PK_MAGIC, format, self.key.get_vk().get_domain().k(), &self.hash, uses_lookup(self.key.get_vk()))?;
+        write_layout(writer, self)?;
+        E::write_pk(&self.key, writer, format)
+    }
+
+    /// Read the key, checking that it was generated for `circuit` and `params`, and that the stored gate layout
+    /// is not corrupted
+    pub fn read<
+        R: Read,
+        Pub: Signal<BuildCS<E::Fr>>,
+        Sec: Signal<BuildCS<E::Fr>>,
+        C: Fn(Pub, Sec)
+    >(reader: &mut R, format: SerdeFormat, params: &Parameters<E>, circuit: C) -> io::Result<Self> {
+        let hash = circuit_hash(&build_cs(circuit).borrow());
+        let (key_hash, lookup) = read_header(reader, PK_MAGIC, format, params.0.k())?;
+        if key_hash != hash {
+            return Err(invalid_data("key was generated for different circuit"));
+        }
+        let (num_vars, public, num_gates, gates) = read_layout(reader)?;
+        let stored = WitnessCS::<E::Fr>::new(num_gates as usize, &gates).get_gate_iterator();
+        if layout_hash(num_vars, &public, num_gates as usize, stored) != hash {
+            return Err(invalid_data("gate layout does not match the key hash"));
+        }
+        Ok(Self {
+            key: E::read_pk(reader, format, lookup)?,
+            hash,
+            num_gates,
+            gates,
+            num_vars,
+            public,
+        })
+    }
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    pub fn write<W: Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        write_header(writer, VK_MAGIC, format, self.0.get_domain().k(), &self.1, uses_lookup(&self.0))?;
+        E::write_vk(&self.0, writer, format)
+    }
+
+    /// Read the key, checking that it was generated for `circuit` and `params`
+    pub fn read<
+        R: Read,
+        Pub: Signal<BuildCS<E::Fr>>,
+        Sec: Signal<BuildCS<E::Fr>>,
+        C: Fn(Pub, Sec)
+    >(reader: &mut R, format: SerdeFormat, params: &Parameters<E>, circuit: C) -> io::Result<Self> {
+        let hash = circuit_hash(&build_cs(circuit).borrow());
+        let (key_hash, lookup) = read_header(reader, VK_MAGIC, format, params.0.k())?;
+        if key_hash != hash {
+            return Err(invalid_data("key was generated for different circuit"));
+        }
+        Ok(Self(E::read_vk(reader, format, lookup)?, hash))
+    }
+}
+
+// key generation is deterministic, use `Parameters::setup_with_rng` for reproducible keys
+pub fn setup<
+    'a,
+    E: Engine,
+    Pub: Signal<BuildCS<E::Fr>>,
+    Sec: Signal<BuildCS<E::Fr>>,
+    C: Fn(Pub, Sec)
+>(
+    params: &'a Parameters<E>,
+    circuit: C
+) -> (VerifyingKey<E>, ProvingKey<E>) {
+    try_setup(params, circuit).unwrap()
+}
+
+pub fn try_setup<
+    'a,
+    E: Engine,
+    Pub: Signal<BuildCS<E::Fr>>,
+    Sec: Signal<BuildCS<E::Fr>>,
+    C: Fn(Pub, Sec)
+>(
+    params: &'a Parameters<E>,
+    circuit: C
+) -> Result<(VerifyingKey<E>, ProvingKey<E>), Error> {
+    let ref rcs = build_cs(circuit);
+    // the lookup argument and tables are added only for circuits which use them
+    let (vk, pk) = if rcs.borrow().gates.iter().any(|g| g.table != 0) {
+        E::keygen(&params.0, &HaloCS::<BuildCS<E::Fr>, true>::new(rcs.clone()))?
+    } else {
+        E::keygen(&params.0, &HaloCS::<BuildCS<E::Fr>, false>::new(rcs.clone()))?
+    };
 
-    let vk = keygen_vk(&params.0, &bcs).unwrap();
-    let pk = keygen_pk(&params.0, vk.clone(), &bcs).unwrap();
+    let cs = rcs.borrow();
+    let hash = circuit_hash(&cs);
+    let gates = compress_gates(&cs)?;
 
-    (VerifyingKey(vk), ProvingKey(pk))
+    let pk = ProvingKey {
+        key: pk,
+        hash,
+        num_gates: cs.gates.len() as u32,
+        gates,
+        num_vars: cs.values.len(),
+        public: cs.public.clone(),
+    };
+    Ok((VerifyingKey(vk, hash), pk))
 }
@@ -0,0 +1,60 @@
+//! EVM verifier for bn256 PLONK verifying keys
+//!
+//! The verifier is emitted by the snark-verifier EVM loader as Yul source, `solc --strict-assembly`
+//! compiles it to deployable bytecode. It replays the `EvmTranscript` used by the prover and reads
+//! raw calldata without a function selector: public inputs as 32-byte big-endian words followed
+//! by the proof bytes. The call reverts if the proof is invalid.
+
+use std::rc::Rc;
+
+use super::{
+    engines::{Bn256, Engine},
+    prover::Proof,
+    setup::VerifyingKey,
+    Parameters,
+};
+use crate::{
+    engines::bn256::Fr,
+    error::Error,
+    ff_uint::{Num, PrimeField, Uint},
+};
+
+use halo2_curves::bn256::{Fq as HaloFq, Fr as HaloFr};
+use plonk_verifier::{
+    loader::evm::EvmLoader,
+    pcs::kzg::{Gwc19, KzgAs},
+    system::halo2::{compile, transcript::evm::EvmTranscript, Config},
+    verifier::{self, SnarkVerifier},
+};
+
+// must match the multiopen scheme of `Engine::create_proof`
+type PlonkVerifier = verifier::plonk::PlonkVerifier<KzgAs<<Bn256 as Engine>::BE, Gwc19>>;
+
+fn put_word<F: PrimeField>(out: &mut Vec<u8>, n: Num<F>) {
+    out.extend_from_slice(&n.to_uint().0.to_big_endian());
+}
+
+/// Render Yul source of a verifier for `vk` and circuits with `num_inputs` public inputs
+pub fn generate_verifier(params: &Parameters<Bn256>, vk: &VerifyingKey<Bn256>, num_inputs: usize) -> Result<String, Error> {
+    let protocol = compile(&params.0, &vk.0, Config::kzg().with_num_instance(vec![num_inputs]));
+    let svk = (params.0.get_g()[0], params.0.g2(), params.0.s_g2()).into();
+
+    let loader = EvmLoader::new::<HaloFq, HaloFr>();
+    let protocol = protocol.loaded(&loader);
+    let mut transcript = EvmTranscript::<_, Rc<EvmLoader>, _, _>::new(&loader);
+
+    let instances = transcript.load_instances(vec![num_inputs]);
+    let proof = PlonkVerifier::read_proof(&svk, &protocol, &instances, &mut transcript)
+        .map_err(|e| Error::Setup(format!("{:?}", e)))?;
+    PlonkVerifier::verify(&svk, &protocol, &instances, &proof).map_err(|e| Error::Setup(format!("{:?}", e)))?;
+
+    Ok(loader.yul_code())
+}
+
+/// Calldata of the generated verifier: public inputs followed by the proof
+pub fn encode_calldata(proof: &Proof, inputs: &[Num<Fr>]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(32 * inputs.len() + proof.0.len());
+    inputs.iter().for_each(|w| put_word(&mut res, *w));
+    res.extend_from_slice(&proof.0);
+    res
+}
@@ -1,44 +1,23 @@
 use super::*;
 
-use halo2_proofs::{
-    plonk::verify_proof,
-    poly::{
-        commitment::ParamsProver,
-        kzg::{
-            multiopen::{VerifierGWC},
-            strategy::AccumulatorStrategy,
-        },
-        VerificationStrategy,
-    },
-    transcript::{TranscriptReadBuffer}
-};
-
-use plonk_verifier::system::halo2::transcript::evm::EvmTranscript;
-use itertools::Itertools;
-use std::io::Cursor;
 use super::setup::VerifyingKey;
 use super::prover::Proof;
 
 
-pub fn verify(
-    params: &Parameters<super::engines::Bn256>,
-    vk: &VerifyingKey<super::engines::Bn256>,
+pub fn verify<E: Engine>(
+    params: &Parameters<E>,
+    vk: &VerifyingKey<E>,
     proof: &Proof,
-    inputs: &[Num<crate::engines::bn256::Fr>]
+    inputs: &[Num<E::Fr>]
 ) -> bool {
-    let instances = inputs.iter().cloned().map(num_to_halo_fp).collect_vec();
-
-    let mut transcript =
-        <EvmTranscript::<halo2_curves::bn256::G1Affine, _, _, _> as TranscriptReadBuffer<_, _, _>>
-        ::init(Cursor::new(proof.0.clone()));
-    VerificationStrategy::<_, VerifierGWC<_>>::finalize(
-        verify_proof::<_, VerifierGWC<_>, _, EvmTranscript::<halo2_curves::bn256::G1Affine, _, _, _>, _>(
-            params.0.verifier_params(),
-            &vk.0,
-            AccumulatorStrategy::new(params.0.verifier_params()),
-            &[&[instances.as_slice()]],
-            &mut transcript,
-        ).unwrap(),
-    )
+    try_verify(params, vk, proof, inputs).unwrap()
+}
 
+pub fn try_verify<E: Engine>(
+    params: &Parameters<E>,
+    vk: &VerifyingKey<E>,
+    proof: &Proof,
+    inputs: &[Num<E::Fr>]
+) -> Result<bool, crate::error::Error> {
+    E::verify_proof(&params.0, &vk.0, &proof.0, inputs)
 }
@@ -1,12 +1,15 @@
 use ff_uint::NumRepr;
 
+#[cfg(feature = "plonk")]
+use crate::circuit::plonk::cs::{LookupTable, PlonkCS};
 use crate::{
-    circuit::{bool::CBool, num::CNum, cs::CS},
+    circuit::{bool::CBool, num::CNum, cs::{scope, CS}},
     core::signal::Signal,
     ff_uint::{BitIterLE, Num, PrimeFieldParams},
 };
 
 pub fn c_into_bits_le<C: CS>(signal: &CNum<C>, limit: usize) -> Vec<CBool<C>> {
+    let _scope = scope(signal.get_cs(), "c_into_bits_le");
     match signal.as_const() {
         Some(value) => {
             let mut bits = Vec::<CBool<C>>::new();
@@ -62,6 +65,7 @@ pub fn c_comp<C: CS>(s1:&CNum<C>, s2:&CNum<C>, limit:usize) -> CBool<C> {
 pub fn c_comp_constant<C: CS>(signal: &[CBool<C>], ct: Num<C::Fr>) -> CBool<C> {
     let siglen = signal.len();
     assert!(siglen > 0, "should be at least one input signal");
+    let _scope = scope(signal[0].get_cs(), "c_comp_constant");
     let c_false = signal[0].derive_const(&false);
     if (ct.to_uint() >> (siglen as u32)).is_zero() {
         let cs = signal[0].get_cs();
@@ -105,6 +109,7 @@ pub fn c_comp_constant<C: CS>(signal: &[CBool<C>], ct: Num<C::Fr>) -> CBool<C> {
 }
 
 pub fn c_into_bits_le_strict<C: CS>(signal: &CNum<C>) -> Vec<CBool<C>> {
+    let _scope = scope(signal.get_cs(), "c_into_bits_le_strict");
     let bits = c_into_bits_le(signal, C::Fr::MODULUS_BITS as usize);
     let cmp_res = c_comp_constant(&bits, -Num::ONE);
     cmp_res.assert_const(&false);
@@ -121,3 +126,85 @@ pub fn c_from_bits_le<C: CS>(bits: &[CBool<C>]) -> CNum<C> {
     }
     acc
 }
+
+#[cfg(feature = "plonk")]
+fn value_bits<C: CS>(signal: &CNum<C>) -> Vec<Option<bool>> {
+    match signal.get_value() {
+        Some(v) => v.bit_iter_le().map(|x| Some(x)).collect::<Vec<_>>(),
+        None => vec![None; C::Fr::MODULUS_BITS as usize],
+    }
+}
+
+#[cfg(feature = "plonk")]
+fn limb_value<Fr: crate::ff_uint::PrimeField>(bits: &[Option<bool>]) -> Option<Num<Fr>> {
+    bits.iter().rev().try_fold(0u64, |acc, b| b.map(|b| 2 * acc + b as u64)).map(Num::from)
+}
+
+// Split `signal` into limbs of `width` bits, where the last limb may be shorter. The limbs are
+// constrained to sum up to `signal`, their range is checked by the caller.
+#[cfg(feature = "plonk")]
+fn c_split_limbs<C: PlonkCS>(signal: &CNum<C>, limit: usize, width: usize) -> Vec<CNum<C>> {
+    let bits = value_bits(signal);
+    let limbs = (0..limit)
+        .step_by(width)
+        .map(|i| signal.derive_alloc(limb_value::<C::Fr>(&bits[i..limit.min(i + width)]).as_ref()))
+        .collect::<Vec<CNum<C>>>();
+
+    let mut k = Num::ONE;
+    let terms = limbs
+        .iter()
+        .map(|l| {
+            let t = l * k;
+            k *= Num::from(1u64 << width);
+            t
+        })
+        .collect::<Vec<_>>();
+    C::enforce_sum(&terms, signal);
+    limbs
+}
+
+/// Same as `c_into_bits_le`, but each bit costs a single `And4` lookup against its nibble. The decomposition
+/// is unique only below the modulus, so `limit` should be less than `MODULUS_BITS`.
+#[cfg(feature = "plonk")]
+pub fn c_into_bits_le_lookup<C: PlonkCS>(signal: &CNum<C>, limit: usize) -> Vec<CBool<C>> {
+    assert!(limit < C::Fr::MODULUS_BITS as usize, "limit should be less than the modulus bit length");
+    if limit == 0 || signal.as_const().is_some() {
+        return c_into_bits_le(signal, limit);
+    }
+    let _scope = scope(signal.get_cs(), "c_into_bits_le_lookup");
+    let nibbles = c_split_limbs(signal, limit, 4);
+    let bits = value_bits(signal);
+
+    let mut res = Vec::with_capacity(limit);
+    for (j, nibble) in nibbles.iter().enumerate() {
+        let width = (limit - 4 * j).min(4);
+        for i in 0..width {
+            let mask = Num::from(1u64 << i);
+            let value = bits[4 * j + i].map(|b| if b { mask } else { Num::ZERO });
+            let masked: CNum<C> = signal.derive_alloc(value.as_ref());
+            C::enforce_lookup(LookupTable::And4, &[nibble.clone(), signal.derive_const(&mask), masked.clone()]);
+            res.push(CBool::new_unchecked(&(masked / mask)));
+        }
+        // the nibble is below 2^4 by the lookups above, a shorter one also must have zero high bits
+        if width < 4 {
+            C::enforce_lookup(LookupTable::Range8, &[nibble * Num::from(1u64 << (8 - width))]);
+        }
+    }
+    res
+}
+
+/// Assert `signal < 2^limit` with one `Range8` lookup per byte
+#[cfg(feature = "plonk")]
+pub fn c_assert_range_lookup<C: PlonkCS>(signal: &CNum<C>, limit: usize) {
+    assert!(limit < C::Fr::MODULUS_BITS as usize, "limit should be less than the modulus bit length");
+    if let Some(value) = signal.as_const() {
+        assert!(value.bit_iter_le().skip(limit).all(|b| !b), "value is out of range");
+        return;
+    }
+    let _scope = scope(signal.get_cs(), "c_assert_range_lookup");
+    assert!(limit > 0, "limit should be positive");
+    for (j, byte) in c_split_limbs(signal, limit, 8).iter().enumerate() {
+        let width = (limit - 8 * j).min(8);
+        C::enforce_lookup(LookupTable::Range8, &[byte * Num::from(1u64 << (8 - width))]);
+    }
+}
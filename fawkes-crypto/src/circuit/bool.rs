@@ -1,6 +1,6 @@
 use crate::{
     circuit::{
-        cs::{CS, RCS},
+        cs::{SignalOps, CS, RCS},
         num::CNum,
     },
     core::signal::Signal,
@@ -66,16 +66,11 @@ impl<C: CS> Signal<C> for CBool<C> {
     }
 
     fn alloc(cs: &RCS<C>, value: Option<&Self::Value>) -> Self {
-        let value = value.map(|&b| Into::<Num<C::Fr>>::into(b));
-        Self::new(&CNum::alloc(cs, value.as_ref()))
+        C::Ops::alloc_bool(cs, value)
     }
 
     fn assert_const(&self, value: &Self::Value) {
-        CS::enforce(
-            &self.to_num(),
-            &self.derive_const(&Num::ONE),
-            &self.derive_const(&(*value).into()),
-        )
+        C::Ops::enforce_eq(&self.to_num(), &self.derive_const(&(*value).into()))
     }
 
     fn switch(&self, bit: &CBool<C>, if_else: &Self) -> Self {
@@ -94,7 +89,7 @@ impl<C: CS> Signal<C> for CBool<C> {
             .map(|a| other.get_value().map(|b| a == b))
             .flatten();
         let signal: CBool<C> = self.derive_alloc(value.as_ref());
-        CS::enforce(
+        C::Ops::enforce_mul(
             &(self.to_num() * Num::from(2) - Num::ONE),
             &(other.to_num() * Num::from(2) - Num::ONE),
             &(signal.to_num() * Num::from(2) - Num::ONE),
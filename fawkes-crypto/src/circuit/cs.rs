@@ -0,0 +1,166 @@
+use crate::{
+    circuit::{bool::CBool, num::CNum},
+    ff_uint::{Num, PrimeField},
+};
+
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+// with a single arithmetization enabled, its constraint systems are also available from this module
+#[cfg(all(feature = "r1cs", not(feature = "plonk")))]
+pub use super::r1cs::cs::*;
+
+#[cfg(all(feature = "plonk", not(feature = "r1cs")))]
+pub use super::plonk::cs::*;
+
+/// Handle of the constraint system shared by all its signals, see `CS::Handle`
+pub type RCS<C> = <C as CS>::Handle;
+
+/// Shared mutable reference to a constraint system
+pub trait CSHandle<C>: Clone + Debug {
+    type Ref<'a>: Deref<Target = C> where Self: 'a;
+    type RefMut<'a>: DerefMut<Target = C> where Self: 'a;
+
+    fn new(cs: C) -> Self;
+    fn borrow(&self) -> Self::Ref<'_>;
+    fn borrow_mut(&self) -> Self::RefMut<'_>;
+}
+
+impl<C: Debug> CSHandle<C> for Rc<RefCell<C>> {
+    type Ref<'a> = Ref<'a, C> where Self: 'a;
+    type RefMut<'a> = RefMut<'a, C> where Self: 'a;
+
+    fn new(cs: C) -> Self {
+        Rc::new(RefCell::new(cs))
+    }
+
+    fn borrow(&self) -> Self::Ref<'_> {
+        RefCell::borrow(self)
+    }
+
+    fn borrow_mut(&self) -> Self::RefMut<'_> {
+        RefCell::borrow_mut(self)
+    }
+}
+
+// Signals never hold the lock between operations, so it is uncontended unless the same constraint system
+// is shared between threads. Poisoning means a gadget panicked in the middle of an operation.
+impl<C: Debug> CSHandle<C> for Arc<RwLock<C>> {
+    type Ref<'a> = RwLockReadGuard<'a, C> where Self: 'a;
+    type RefMut<'a> = RwLockWriteGuard<'a, C> where Self: 'a;
+
+    fn new(cs: C) -> Self {
+        Arc::new(RwLock::new(cs))
+    }
+
+    fn borrow(&self) -> Self::Ref<'_> {
+        self.read().expect("constraint system lock is poisoned")
+    }
+
+    fn borrow_mut(&self) -> Self::RefMut<'_> {
+        self.write().expect("constraint system lock is poisoned")
+    }
+}
+
+/// Selects the handle type of `BuildCS` and `WitnessCS`
+pub trait HandleKind: Clone + Debug {
+    type Handle<C: Debug>: CSHandle<C>;
+}
+
+/// `Rc<RefCell<_>>` handle, signals are neither `Send` nor `Sync`
+#[derive(Clone, Debug)]
+pub struct RcHandle;
+
+impl HandleKind for RcHandle {
+    type Handle<C: Debug> = Rc<RefCell<C>>;
+}
+
+/// `Arc<RwLock<_>>` handle, signals and the constraint system are `Send` and can be moved between threads
+#[derive(Clone, Debug)]
+pub struct ArcHandle;
+
+impl HandleKind for ArcHandle {
+    type Handle<C: Debug> = Arc<RwLock<C>>;
+}
+
+/// Constraint system as seen by the signals and the gadgets, common for all arithmetizations. Gates are
+/// built by the arithmetization specific traits, `r1cs::cs::R1CS` and `plonk::cs::PlonkCS`, so a gadget
+/// written against `CS` compiles for both.
+pub trait CS: Clone {
+    type Fr: PrimeField;
+    // linear combination stored in `CNum`
+    type LC: Clone + Debug;
+    type Handle: CSHandle<Self>;
+    type Ops: SignalOps<Self>;
+
+    fn num_gates(&self) -> usize;
+    fn num_input(&self) -> usize;
+    fn num_aux(&self) -> usize;
+
+    fn inputize(n: &CNum<Self>);
+    fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self>;
+
+    fn const_tracker_before(&mut self) -> Option<bool> {
+        None
+    }
+
+    fn const_tracker_after(&mut self, _:bool) {}
+
+    // Constant or coefficient used by the circuit code, the R1CS systems hash it into the circuit trace
+    fn trace_constant(&mut self, _: &Num<Self::Fr>) {}
+
+    // Enter a named scope. Scopes are nested and only used for diagnostics
+    fn push_scope(&mut self, _:&str) {}
+
+    fn pop_scope(&mut self) {}
+}
+
+/// Operations of `CNum` and `CBool` which depend on the arithmetization. Implemented once per
+/// arithmetization, by `r1cs::cs::R1csOps` and `plonk::cs::PlonkOps`.
+pub trait SignalOps<C: CS> {
+    fn lc_const(value: Num<C::Fr>) -> C::LC;
+    fn lc_as_const(lc: &C::LC) -> Option<Num<C::Fr>>;
+    fn lc_neg(lc: &C::LC) -> C::LC;
+    fn lc_mul(lc: &mut C::LC, k: &Num<C::Fr>);
+    fn lc_capacity(lc: &C::LC) -> usize;
+
+    // a += b
+    fn add_assign(a: &mut CNum<C>, b: &CNum<C>);
+
+    // a * b == c
+    fn enforce_mul(a: &CNum<C>, b: &CNum<C>, c: &CNum<C>);
+
+    // a == b
+    fn enforce_eq(a: &CNum<C>, b: &CNum<C>);
+
+    // `a` is not a constant
+    fn is_zero(a: &CNum<C>) -> CBool<C>;
+
+    fn pow5(a: &CNum<C>) -> CNum<C>;
+    fn sum(cs: &RCS<C>, items: &[CNum<C>]) -> CNum<C>;
+    fn alloc_bool(cs: &RCS<C>, value: Option<&bool>) -> CBool<C>;
+}
+
+/// RAII guard returned by [`scope`]. The scope is left when the guard is dropped.
+pub struct ScopeGuard<C: CS>(RCS<C>);
+
+impl<C: CS> Drop for ScopeGuard<C> {
+    fn drop(&mut self) {
+        self.0.borrow_mut().pop_scope();
+    }
+}
+
+/// Enter a named scope until the returned guard is dropped
+///
+/// ```ignore
+/// let _scope = scope(cs, "merkle/level_3");
+/// ```
+pub fn scope<C: CS>(cs: &RCS<C>, name: &str) -> ScopeGuard<C> {
+    cs.borrow_mut().push_scope(name);
+    ScopeGuard(cs.clone())
+}
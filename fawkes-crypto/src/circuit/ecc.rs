@@ -1,7 +1,7 @@
 // Assuming JubJub curves with cofactor=8 only
 
 use crate::{
-    circuit::{bool::CBool, cs::{CS, RCS}, mux::c_mux3, num::CNum},
+    circuit::{bool::CBool, cs::{scope, CS, RCS}, mux::c_mux3, num::CNum},
     core::signal::Signal,
     ff_uint::Num,
     native::ecc::{EdwardsPoint, EdwardsPointEx, JubJubParams, MontgomeryPoint},
@@ -67,6 +67,7 @@ impl<C: CS> CEdwardsPoint<C> {
     }
 
     pub fn subgroup_decompress<J: JubJubParams<Fr = C::Fr>>(x: &CNum<C>, params: &J) -> Self {
+        let _scope = scope(x.get_cs(), "CEdwardsPoint::subgroup_decompress");
         let preimage_value = x.get_value().map(|x| {
             EdwardsPoint::subgroup_decompress(x, params)
                 .unwrap_or(params.edwards_g().clone())
@@ -104,6 +105,7 @@ impl<C: CS> CEdwardsPoint<C> {
             vec![x_col, y_col]
         }
         let cs = self.get_cs();
+        let _scope = scope(cs, "CEdwardsPoint::mul");
 
         match self.as_const() {
             Some(c_base) => {
@@ -192,6 +194,7 @@ impl<C: CS> CEdwardsPoint<C> {
 
     // assuming t!=-0
     pub fn from_scalar<J: JubJubParams<Fr = C::Fr>>(t: &CNum<C>, params: &J) -> Self {
+        let _scope = scope(t.get_cs(), "CEdwardsPoint::from_scalar");
         fn check_and_get_y<C: CS, J: JubJubParams<Fr = C::Fr>>(
             x: &CNum<C>,
             t: &CNum<C>,
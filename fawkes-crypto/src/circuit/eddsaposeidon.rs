@@ -5,7 +5,7 @@ use crate::{
         ecc::CEdwardsPoint,
         num::CNum,
         poseidon::c_poseidon,
-        cs::CS,
+        cs::{scope, CS},
     },
     core::signal::Signal,
     ff_uint::Num,
@@ -26,6 +26,7 @@ pub fn c_eddsaposeidon_verify<C: CS, J: JubJubParams<Fr = C::Fr>>(
         "jubjub field should be lesser than snark field"
     );
     let cs = s.get_cs();
+    let _scope = scope(cs, "c_eddsaposeidon_verify");
 
     let p_a = CEdwardsPoint::subgroup_decompress(a, jubjub_params);
     let p_r = CEdwardsPoint::subgroup_decompress(r, jubjub_params);
@@ -1,17 +1,22 @@
 #[cfg(feature = "r1cs")]
-mod r1cs;
-
-#[cfg(feature = "r1cs")]
-pub use r1cs::*;
+pub mod r1cs;
 
 #[cfg(feature = "plonk")]
-mod plonk;
+pub mod plonk;
 
-#[cfg(feature = "plonk")]
-pub use self::plonk::*;
+// with a single arithmetization enabled, its modules are also available directly under `circuit`
+#[cfg(all(feature = "r1cs", not(feature = "plonk")))]
+pub use r1cs::{check, circom, lc, optimize};
+
+#[cfg(all(feature = "plonk", not(feature = "r1cs")))]
+pub use self::plonk::check;
 
 pub mod bitify;
+pub mod bool;
+pub mod cs;
 pub mod ecc;
 pub mod eddsaposeidon;
 pub mod mux;
+pub mod num;
+pub mod profile;
 pub mod poseidon;
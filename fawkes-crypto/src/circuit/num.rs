@@ -1,8 +1,7 @@
 use crate::{
     circuit::{
         bool::CBool,
-        cs::{CS, RCS},
-        lc::{Index},
+        cs::{CSHandle, SignalOps, CS, RCS},
         bitify::c_into_bits_le_strict
     },
     core::signal::Signal,
@@ -13,12 +12,9 @@ use std::{
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
-use super::lc::AbstractLC;
-
 #[derive(Clone, Debug)]
 pub struct CNum<C: CS> {
     pub value: Option<Num<C::Fr>>,
-    // a*x + b
     pub lc: C::LC,
     pub cs: RCS<C>,
 }
@@ -40,7 +36,7 @@ impl<C: CS> CNum<C> {
             _ => {
                 let value = self.value.map(|a| other.value.map(|b| a / b)).flatten();
                 let signal = self.derive_alloc(value.as_ref());
-                CS::enforce(&signal, other, self);
+                C::Ops::enforce_mul(&signal, other, self);
                 signal
             }
         }
@@ -56,7 +52,7 @@ impl<C: CS> CNum<C> {
                     .get_value()
                     .map(|v| v.checked_inv().unwrap_or(Num::ONE));
                 let inv_signal = self.derive_alloc(inv_value.as_ref());
-                CS::enforce(self, &inv_signal, &self.derive_const(&Num::ONE));
+                C::Ops::enforce_mul(self, &inv_signal, &self.derive_const(&Num::ONE));
             }
         }
     }
@@ -65,21 +61,12 @@ impl<C: CS> CNum<C> {
     pub fn is_zero(&self) -> CBool<C> {
         match self.as_const() {
             Some(c) => self.derive_const(&c.is_zero()),
-            _ => {
-                let inv_value = self
-                    .get_value()
-                    .map(|v| v.checked_inv().unwrap_or(Num::ZERO));
-                let inv_signal: CNum<C> = self.derive_alloc(inv_value.as_ref());
- 
-                let ref res_signal = -inv_signal * self + Num::ONE;
-                (res_signal*self).assert_zero();
-                CBool::new_unchecked(res_signal)
-            }   
+            _ => C::Ops::is_zero(self),
         }
     }
 
     pub fn assert_bit(&self) {
-        CS::enforce(self, &(self - Num::ONE), &self.derive_const(&Num::ZERO));
+        C::Ops::enforce_mul(self, &(self - Num::ONE), &self.derive_const(&Num::ZERO));
     }
 
     pub fn to_bool(&self) -> CBool<C> {
@@ -103,6 +90,17 @@ impl<C: CS> CNum<C> {
     pub fn square(&self) -> Self {
         self * self
     }
+
+    /// `self^5`, a single S-box gate in PLONK
+    pub fn pow5(&self) -> Self {
+        C::Ops::pow5(self)
+    }
+
+    /// Sum of `items`, free in R1CS and packed into rows of four cells in PLONK. The sum of no items is
+    /// zero constant of `cs`.
+    pub fn sum(cs: &RCS<C>, items: &[Self]) -> Self {
+        C::Ops::sum(cs, items)
+    }
 }
 
 impl<C: CS> Signal<C> for CNum<C> {
@@ -119,7 +117,7 @@ impl<C: CS> Signal<C> for CNum<C> {
             }
         }
 
-        let res = self.lc.as_const();
+        let res = C::Ops::lc_as_const(&self.lc);
 
         rcs.const_tracker_after(res.is_some());
         res
@@ -135,9 +133,10 @@ impl<C: CS> Signal<C> for CNum<C> {
 
     fn from_const(cs: &RCS<C>, value: &Self::Value) -> Self {
         let value = value.clone();
+        cs.borrow_mut().trace_constant(&value);
         Self {
             value: Some(value),
-            lc: C::LC::from_parts(value, Index::Input(0)),
+            lc: C::Ops::lc_const(value),
             cs: cs.clone(),
         }
     }
@@ -151,11 +150,7 @@ impl<C: CS> Signal<C> for CNum<C> {
     }
 
     fn assert_const(&self, value: &Self::Value) {
-        CS::enforce(
-            self,
-            &self.derive_const(&Num::ONE),
-            &self.derive_const(value),
-        )
+        C::Ops::enforce_eq(self, &self.derive_const(value))
     }
 
     fn switch(&self, bit: &CBool<C>, if_else: &Self) -> Self {
@@ -171,7 +166,7 @@ impl<C: CS> Signal<C> for CNum<C> {
     }
 
     fn assert_eq(&self, other: &Self) {
-        CS::enforce(self, &self.derive_const(&Num::ONE), other);
+        C::Ops::enforce_eq(self, other);
     }
 
     fn is_eq(&self, other: &Self) -> CBool<C> {
@@ -181,7 +176,7 @@ impl<C: CS> Signal<C> for CNum<C> {
 
 impl<C: CS> CNum<C> {
     pub fn capacity(&self) -> usize {
-        self.lc.capacity()
+        C::Ops::lc_capacity(&self.lc)
     }
 }
 
@@ -191,20 +186,18 @@ impl<C: CS> std::ops::Neg for CNum<C> {
     #[inline]
     fn neg(mut self) -> Self::Output {
         self.value = self.value.map(|x| -x);
-        self.lc = self.lc.neg();
+        self.lc = C::Ops::lc_neg(&self.lc);
+        self.cs.borrow_mut().trace_constant(&-Num::ONE);
         self
     }
 }
 
 forward_unop_ex!(impl<C: CS> Neg for CNum<C>, neg);
 
-
-
 impl<'l, C: CS> AddAssign<&'l CNum<C>> for CNum<C> {
     #[inline]
     fn add_assign(&mut self, other: &'l CNum<C>) {
-        self.value = self.value.map(|a| other.value.map(|b| a + b)).flatten();
-        self.lc.add_assign(&other.lc);
+        C::Ops::add_assign(self, other);
     }
 }
 
@@ -218,8 +211,7 @@ impl<'l, C: CS> AddAssign<&'l Num<C::Fr>> for CNum<C> {
 impl<'l, C: CS> SubAssign<&'l CNum<C>> for CNum<C> {
     #[inline]
     fn sub_assign(&mut self, other: &'l CNum<C>) {
-        self.value = self.value.map(|a| other.value.map(|b| a - b)).flatten();
-        self.lc.sub_assign(&other.lc);
+        self.add_assign(&-other)
     }
 }
 
@@ -237,7 +229,8 @@ impl<'l, C: CS> MulAssign<&'l Num<C::Fr>> for CNum<C> {
             *self = self.derive_const(&Num::ZERO)
         } else {
             self.value = self.value.map(|v| v * other);
-            self.lc.mul_assign(other);
+            C::Ops::lc_mul(&mut self.lc, other);
+            self.cs.borrow_mut().trace_constant(other);
         }
     }
 }
@@ -264,7 +257,7 @@ impl<'l, C: CS> MulAssign<&'l CNum<C>> for CNum<C> {
                 let value = self.value.map(|a| other.value.map(|b| a * b)).flatten();
 
                 let signal = self.derive_alloc(value.as_ref());
-                CS::enforce(self, other, &signal);
+                C::Ops::enforce_mul(self, other, &signal);
                 *self = signal;
             }
         }
@@ -0,0 +1,110 @@
+use crate::{
+    circuit::plonk::cs::{Gate, LookupTable, PlonkCS},
+    ff_uint::{Num, PrimeField},
+};
+
+/// Gate which is not satisfied by the assignment, or references a variable without value
+#[derive(Clone, Debug)]
+pub struct UnsatisfiedGate<Fr: PrimeField> {
+    pub index: usize,
+    pub gate: Gate<Fr>,
+    pub x: Option<Num<Fr>>,
+    pub y: Option<Num<Fr>>,
+    pub z: Option<Num<Fr>>,
+    pub w: Option<Num<Fr>>,
+    // `w` of the next gate
+    pub w_next: Option<Num<Fr>>,
+}
+
+impl<Fr: PrimeField> std::fmt::Display for UnsatisfiedGate<Fr> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let g = &self.gate;
+        write!(
+            f,
+            "gate {} is not satisfied: a={}, b={}, c={}, d={}, e={}, f={}, g={}, pow5={}, table={}",
+            self.index, g.a, g.b, g.c, g.d, g.e, g.f, g.g, g.pow5, g.table
+        )?;
+        for (name, var, v) in [("x", g.x, self.x), ("y", g.y, self.y), ("z", g.z, self.z), ("w", g.w, self.w)] {
+            match v {
+                Some(v) => write!(f, ", {}[{}]={}", name, var, v)?,
+                None => write!(f, ", {}[{}]=<missing>", name, var)?,
+            }
+        }
+        if let Some(v) = self.w_next {
+            write!(f, ", w'={}", v)?;
+        }
+        Ok(())
+    }
+}
+
+// variables with zero coefficients are not constrained and may be unassigned
+fn term<Fr: PrimeField>(k: Num<Fr>, v: Option<Num<Fr>>) -> Option<Num<Fr>> {
+    if k.is_zero() {
+        Some(Num::ZERO)
+    } else {
+        v.map(|v| k * v)
+    }
+}
+
+pub(crate) fn check_gate<C: PlonkCS>(cs: &C, index: usize, gate: Gate<C::Fr>, next: Option<&Gate<C::Fr>>) -> Result<(), UnsatisfiedGate<C::Fr>> {
+    let (x, y, z) = (cs.get_value(gate.x), cs.get_value(gate.y), cs.get_value(gate.z));
+    let w = if gate.f.is_zero() { None } else { cs.get_value(gate.w) };
+    let w_next = if gate.g.is_zero() { None } else { next.and_then(|n| cs.get_value(n.w)) };
+
+    let res = if gate.table != 0 {
+        let row = [term(gate.a, x), term(gate.b, y), term(gate.c, z)];
+        match (LookupTable::from_id(gate.table), row) {
+            (Some(table), [Some(a), Some(b), Some(c)]) if table.contains(&[a + gate.d, b + gate.e, c + gate.f]) => {
+                Some(Num::ZERO)
+            }
+            _ => None,
+        }
+    } else if gate.pow5 {
+        x.and_then(|x| y.map(|y| {
+            let x = x + gate.e;
+            let x2 = x * x;
+            x2 * x2 * x - y
+        }))
+    } else {
+        let xy = if gate.d.is_zero() {
+            Some(Num::ZERO)
+        } else {
+            x.and_then(|x| y.map(|y| gate.d * x * y))
+        };
+        let w_next_term = if gate.g.is_zero() { Some(Num::ZERO) } else { w_next.map(|v| gate.g * v) };
+        [term(gate.a, x), term(gate.b, y), term(gate.c, z), term(gate.f, w), xy, w_next_term]
+            .into_iter()
+            .try_fold(gate.e, |acc, t| t.map(|t| acc + t))
+    };
+
+    match res {
+        Some(r) if r.is_zero() => Ok(()),
+        _ => Err(UnsatisfiedGate { index, gate, x, y, z, w, w_next }),
+    }
+}
+
+// gates together with the next gate, which is referenced by rotation
+fn with_next<C: PlonkCS>(cs: &C) -> impl Iterator<Item = (Gate<C::Fr>, Option<Gate<C::Fr>>)> {
+    let mut it = cs.get_gate_iterator().peekable();
+    std::iter::from_fn(move || {
+        let gate = it.next()?;
+        Some((gate, it.peek().cloned()))
+    })
+}
+
+/// Evaluate every gate of `cs` against its witness values and return the first failing gate.
+/// This is a lightweight replacement for running halo2 `MockProver`.
+pub fn check_satisfied<C: PlonkCS>(cs: &C) -> Result<(), UnsatisfiedGate<C::Fr>> {
+    for (index, (gate, next)) in with_next(cs).enumerate() {
+        check_gate(cs, index, gate, next.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Same as `check_satisfied`, but collects all failing gates
+pub fn check_satisfied_all<C: PlonkCS>(cs: &C) -> Vec<UnsatisfiedGate<C::Fr>> {
+    with_next(cs)
+        .enumerate()
+        .filter_map(|(index, (gate, next))| check_gate(cs, index, gate, next.as_ref()).err())
+        .collect()
+}
@@ -1,23 +1,36 @@
 use crate::{
-    circuit::num::CNum,
+    circuit::{
+        bool::CBool,
+        cs::{CSHandle, HandleKind, RcHandle, SignalOps, CS, RCS},
+        num::CNum,
+        plonk::check::{check_gate, UnsatisfiedGate},
+        profile::Profiler,
+    },
     core::signal::Signal,
-    ff_uint::{Num, PrimeField},
+    ff_uint::{Num, NumRepr, PrimeField},
 };
 
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, marker::PhantomData, rc::Rc};
 
-pub type RCS<C> = Rc<RefCell<C>>;
+/// Linear combination of a PLONK signal, `k*v + c` for the variable with index `v`
+pub type PlonkLC<Fr> = (Num<Fr>, usize, Num<Fr>);
+
+use crate::borsh::{BorshSerialize, BorshDeserialize};
 
 /// A `Gate` describes constraint of the form
 ///
 /// ```ignore
-/// a*x + b*y + c*z + d*x*y + e == 0
+/// a*x + b*y + c*z + f*w + d*x*y + g*w' + e == 0
 /// ```
 ///
-/// where `x`, `y`, `z` are variable witness elements (represented here as
-/// indices), while the `a` ... `e` values are concrete constants represented
-/// here as field values.
-#[derive(Clone, Debug)]
+/// where `x`, `y`, `z`, `w` are variable witness elements (represented here as
+/// indices), `w'` is the `w` of the next gate, while the `a` ... `g` values are
+/// concrete constants represented here as field values.
+///
+/// If `pow5` is set, the gate is the S-box constraint `(x + e)^5 == y` instead,
+/// and the other constants are zero. If `table` is nonzero, the gate is the lookup
+/// of `(a*x + d, b*y + e, c*z + f)` in the `LookupTable` with this id.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub struct Gate<Fr: PrimeField> {
     pub a: Num<Fr>,
     pub x: usize,
@@ -27,15 +40,89 @@ pub struct Gate<Fr: PrimeField> {
     pub z: usize,
     pub d: Num<Fr>,
     pub e: Num<Fr>,
+    pub f: Num<Fr>,
+    pub w: usize,
+    pub g: Num<Fr>,
+    pub pow5: bool,
+    pub table: u8,
+}
+
+impl<Fr: PrimeField> Gate<Fr> {
+    fn zero() -> Self {
+        Self {
+            a: Num::ZERO,
+            x: 0,
+            b: Num::ZERO,
+            y: 0,
+            c: Num::ZERO,
+            z: 0,
+            d: Num::ZERO,
+            e: Num::ZERO,
+            f: Num::ZERO,
+            w: 0,
+            g: Num::ZERO,
+            pow5: false,
+            table: 0,
+        }
+    }
 }
 
-pub trait CS: Clone {
-    type Fr: PrimeField;
+/// Fixed lookup tables, loaded into every circuit. Rows are triples, unused
+/// positions are zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LookupTable {
+    /// `(v, 0, 0)` for `v < 2^8`
+    Range8 = 1,
+    /// `(a, b, a ^ b)` for `a, b < 2^4`
+    Xor4 = 2,
+    /// `(a, b, a & b)` for `a, b < 2^4`
+    And4 = 3,
+}
+
+impl LookupTable {
+    pub const ALL: [LookupTable; 3] = [LookupTable::Range8, LookupTable::Xor4, LookupTable::And4];
+
+    pub fn id(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.iter().cloned().find(|t| t.id() == id)
+    }
+
+    /// Number of meaningful positions in a row
+    pub fn arity(&self) -> usize {
+        match self {
+            LookupTable::Range8 => 1,
+            LookupTable::Xor4 | LookupTable::And4 => 3,
+        }
+    }
+
+    pub fn rows(&self) -> Vec<[u64; 3]> {
+        match self {
+            LookupTable::Range8 => (0..256).map(|v| [v, 0, 0]).collect(),
+            LookupTable::Xor4 => (0..256).map(|v| [v & 15, v >> 4, (v & 15) ^ (v >> 4)]).collect(),
+            LookupTable::And4 => (0..256).map(|v| [v & 15, v >> 4, (v & 15) & (v >> 4)]).collect(),
+        }
+    }
+
+    pub fn contains<Fr: PrimeField>(&self, row: &[Num<Fr>; 3]) -> bool {
+        let to_u64 = |n: &Num<Fr>| {
+            let n = n.to_uint();
+            if n < NumRepr::from(256u64) { Some(n.as_inner().as_ref()[0]) } else { None }
+        };
+        match (to_u64(&row[0]), to_u64(&row[1]), to_u64(&row[2])) {
+            (Some(a), Some(b), Some(c)) => self.rows().contains(&[a, b, c]),
+            _ => false,
+        }
+    }
+}
+
+/// PLONK constraint system, every signal is a single variable with an affine transformation
+pub trait PlonkCS: CS {
     type GateIterator: Iterator<Item=Gate<Self::Fr>>;
 
-    fn num_gates(&self) -> usize;
-    fn num_input(&self) -> usize;
-    fn num_aux(&self) -> usize;
     fn get_value(&self, index: usize) -> Option<Num<Self::Fr>>;
     fn get_gate_iterator(&self) -> Self::GateIterator;
 
@@ -47,42 +134,326 @@ pub trait CS: Clone {
     // a + b == c
     fn enforce_add(a: &CNum<Self>, b: &CNum<Self>, c: &CNum<Self>);
 
-    fn inputize(n: &CNum<Self>);
-    fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self>;
+    // x^5 == y, where x is `v + c` and y is a variable
+    fn enforce_pow5(x: &CNum<Self>, y: &CNum<Self>);
 
-    fn const_tracker_before(&mut self) -> Option<bool> {
-        None
+    // terms[0] + ... + terms[n-1] == z
+    fn enforce_sum(terms: &[CNum<Self>], z: &CNum<Self>);
+
+    // (args[0], ...) is a row of `table`, missing positions are zero
+    fn enforce_lookup(table: LookupTable, args: &[CNum<Self>]);
+}
+
+/// `SignalOps` of all PLONK constraint systems. Sums of different variables do not fit into `PlonkLC`,
+/// so they allocate a new variable.
+#[derive(Clone, Debug)]
+pub struct PlonkOps;
+
+impl PlonkOps {
+    // variable made public by `inputize`, a new one equal to `n` unless `n` is a variable itself
+    pub(crate) fn input_var<C>(n: &CNum<C>) -> usize where C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>> {
+        if n.lc.0 == Num::ONE && n.lc.2 == Num::ZERO {
+            n.lc.1
+        } else {
+            let m: CNum<C> = n.derive_alloc(n.value.as_ref());
+            m.assert_eq(n);
+            m.lc.1
+        }
+    }
+}
+
+impl<C> SignalOps<C> for PlonkOps where C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>> {
+    fn lc_const(value: Num<C::Fr>) -> C::LC {
+        (Num::ZERO, 0, value)
+    }
+
+    fn lc_as_const(lc: &C::LC) -> Option<Num<C::Fr>> {
+        if lc.0 == Num::ZERO {
+            Some(lc.2)
+        } else {
+            None
+        }
     }
 
-    fn const_tracker_after(&mut self, _:bool) {}
+    fn lc_neg(lc: &C::LC) -> C::LC {
+        (-lc.0, lc.1, -lc.2)
+    }
+
+    fn lc_mul(lc: &mut C::LC, k: &Num<C::Fr>) {
+        lc.0 *= k;
+        lc.2 *= k;
+    }
+
+    fn lc_capacity(lc: &C::LC) -> usize {
+        if lc.0 == Num::ZERO {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn add_assign(a: &mut CNum<C>, b: &CNum<C>) {
+        let cs = a.cs.clone();
+        *a = if let Some(c) = a.as_const() {
+            let value = b.value.map(|v| v + c);
+            let mut lc = b.lc;
+            lc.2 += c;
+            CNum { value, lc, cs }
+        } else if let Some(c) = b.as_const() {
+            let value = a.value.map(|v| v + c);
+            let mut lc = a.lc;
+            lc.2 += c;
+            CNum { value, lc, cs }
+        } else if a.lc.1 == b.lc.1 {
+            CNum {
+                value: a.value.map(|x| b.value.map(|y| x + y)).flatten(),
+                lc: (a.lc.0 + b.lc.0, a.lc.1, a.lc.2 + b.lc.2),
+                cs,
+            }
+        } else {
+            let value = a.value.map(|x| b.value.map(|y| x + y)).flatten();
+            let var: CNum<C> = a.derive_alloc(value.as_ref());
+            C::enforce_add(a, b, &var);
+            var
+        }
+    }
+
+    fn enforce_mul(a: &CNum<C>, b: &CNum<C>, c: &CNum<C>) {
+        C::enforce_mul(a, b, c)
+    }
+
+    fn enforce_eq(a: &CNum<C>, b: &CNum<C>) {
+        C::enforce_add(a, &a.derive_const(&Num::ZERO), b)
+    }
+
+    fn is_zero(a: &CNum<C>) -> CBool<C> {
+        let inv_value = a
+            .get_value()
+            .map(|v| v.checked_inv().unwrap_or(Num::ONE));
+        let inv_signal: CNum<C> = a.derive_alloc(inv_value.as_ref());
+        inv_signal.assert_nonzero();
+        let res_signal = inv_signal * a;
+        (Num::ONE - res_signal).to_bool()
+    }
+
+    fn pow5(a: &CNum<C>) -> CNum<C> {
+        match a.as_const() {
+            Some(c) => a.derive_const(&pow5(c)),
+            _ => {
+                // k*v + c == k*(v + c/k)
+                let k = a.lc.0;
+                let base = a / k;
+                let value = base.value.map(pow5);
+                let signal: CNum<C> = a.derive_alloc(value.as_ref());
+                C::enforce_pow5(&base, &signal);
+                signal * pow5(k)
+            }
+        }
+    }
+
+    fn sum(cs: &RCS<C>, items: &[CNum<C>]) -> CNum<C> {
+        if items.iter().filter(|x| x.as_const().is_none()).count() <= 1 {
+            let mut res = CNum::from_const(cs, &Num::ZERO);
+            items.iter().for_each(|x| res += x);
+            res
+        } else {
+            let value = items.iter().try_fold(Num::ZERO, |s, x| x.value.map(|v| s + v));
+            let signal: CNum<C> = items[0].derive_alloc(value.as_ref());
+            C::enforce_sum(items, &signal);
+            signal
+        }
+    }
+
+    fn alloc_bool(cs: &RCS<C>, value: Option<&bool>) -> CBool<C> {
+        let value = value.map(|&b| Into::<Num<C::Fr>>::into(b));
+        CBool::new_unchecked(&CNum::alloc(cs, value.as_ref()))
+    }
+}
+
+pub(crate) fn pow5<Fr: PrimeField>(x: Num<Fr>) -> Num<Fr> {
+    let x2 = x * x;
+    x2 * x2 * x
+}
+
+// a*b === c
+fn mul_gate<C>(x: &CNum<C>, y: &CNum<C>, z: &CNum<C>) -> Gate<C::Fr>
+where
+    C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>>,
+{
+    Gate {
+        a: x.lc.0 * y.lc.2,
+        x: x.lc.1,
+        b: x.lc.2 * y.lc.0,
+        y: y.lc.1,
+        c: -z.lc.0,
+        z: z.lc.1,
+        d: x.lc.0 * y.lc.0,
+        e: x.lc.2 * y.lc.2 - z.lc.2,
+        ..Gate::zero()
+    }
 }
 
+fn add_gate<C>(x: &CNum<C>, y: &CNum<C>, z: &CNum<C>) -> Gate<C::Fr>
+where
+    C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>>,
+{
+    Gate {
+        a: x.lc.0,
+        x: x.lc.1,
+        b: y.lc.0,
+        y: y.lc.1,
+        c: -z.lc.0,
+        z: z.lc.1,
+        d: Num::ZERO,
+        e: x.lc.2 + y.lc.2 - z.lc.2,
+        ..Gate::zero()
+    }
+}
+
+fn pow5_gate<C>(x: &CNum<C>, y: &CNum<C>) -> Gate<C::Fr>
+where
+    C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>>,
+{
+    assert!(x.lc.0 == Num::ONE && y.lc.0 == Num::ONE && y.lc.2 == Num::ZERO, "pow5 gate arguments are not normalized");
+    Gate {
+        x: x.lc.1,
+        y: y.lc.1,
+        e: x.lc.2,
+        pow5: true,
+        ..Gate::zero()
+    }
+}
+
+fn lookup_gate<C>(table: LookupTable, args: &[CNum<C>]) -> Gate<C::Fr>
+where
+    C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>>,
+{
+    assert!(args.len() <= table.arity(), "too many lookup arguments");
+    let mut gate = Gate { table: table.id(), ..Gate::zero() };
+    for (i, t) in args.iter().enumerate() {
+        match i {
+            0 => { gate.a = t.lc.0; gate.x = t.lc.1; gate.d = t.lc.2; }
+            1 => { gate.b = t.lc.0; gate.y = t.lc.1; gate.e = t.lc.2; }
+            _ => { gate.c = t.lc.0; gate.z = t.lc.1; gate.f = t.lc.2; }
+        }
+    }
+    gate
+}
+
+// Rows of the sum. The first row takes four items, every next row takes three items and the
+// partial sum of the previous rows, which is passed in the `w` cell of the next row. The same
+// accumulators are allocated on every CS, so the replayed gates refer to the same variables.
+fn sum_gates<C>(terms: &[CNum<C>], z: &CNum<C>) -> Vec<Gate<C::Fr>>
+where
+    C: PlonkCS<LC = PlonkLC<<C as CS>::Fr>>,
+{
+    let minus_z = -z.clone();
+    let items = terms.iter().chain(std::iter::once(&minus_z)).collect::<Vec<_>>();
+
+    let mut gates = vec![];
+    let mut acc: Option<CNum<C>> = None;
+    let mut rest = &items[..];
+    while !rest.is_empty() {
+        let width = if acc.is_none() { 4 } else { 3 };
+        let (row, tail) = rest.split_at(width.min(rest.len()));
+
+        let mut gate = Gate::zero();
+        for (i, t) in row.iter().enumerate() {
+            let (k, v) = (t.lc.0, t.lc.1);
+            match i {
+                0 => { gate.a = k; gate.x = v; }
+                1 => { gate.b = k; gate.y = v; }
+                2 => { gate.c = k; gate.z = v; }
+                _ => { gate.f = k; gate.w = v; }
+            }
+            gate.e += t.lc.2;
+        }
+
+        let mut value = acc.as_ref().map_or(Some(Num::ZERO), |acc| acc.value);
+        if let Some(acc) = acc.as_ref() {
+            gate.f = Num::ONE;
+            gate.w = acc.lc.1;
+        }
+        for t in row.iter() {
+            value = value.and_then(|s| t.value.map(|v| s + v));
+        }
+
+        acc = if tail.is_empty() {
+            None
+        } else {
+            gate.g = -Num::ONE;
+            Some(z.derive_alloc(value.as_ref()))
+        };
+        gates.push(gate);
+        rest = tail;
+    }
+    gates
+}
+
+/// Constraint system collecting the gates for setup. `H` selects the handle, see `HandleKind`.
 #[derive(Clone, Debug)]
-pub struct BuildCS<Fr: PrimeField> {
+pub struct BuildCS<Fr: PrimeField, H: HandleKind = RcHandle> {
     pub values: Vec<Option<Num<Fr>>>,
     pub gates: Vec<Gate<Fr>>,
     pub tracking: bool,
     pub public: Vec<usize>,
+    pub phantom: PhantomData<H>,
+}
+
+/// Witness-only constraint system. Gates are not built, they are replayed from the
+/// compressed layout captured at setup. Use `ArcHandle` to build the witness on another
+/// thread or across an await point, and prove it with `prover::prove_cs`.
+#[derive(Clone, Debug)]
+pub struct WitnessCS<'a, Fr: PrimeField, H: HandleKind = RcHandle> {
+    pub values: Vec<Num<Fr>>,
+    pub public: Vec<usize>,
+    pub num_gates: usize,
+    pub gates_data: &'a [u8],
+    pub gate_index: usize,
+    // first variable allocated without a value, reported by the prover instead of a panic
+    pub unassigned: Option<usize>,
+    pub phantom: PhantomData<H>,
+}
+
+impl<'a, Fr: PrimeField, H: HandleKind> WitnessCS<'a, Fr, H> {
+    pub fn new(num_gates: usize, gates_data: &'a [u8]) -> Self {
+        Self {
+            values: vec![],
+            public: vec![],
+            num_gates,
+            gates_data,
+            gate_index: 0,
+            unassigned: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn rc_new(num_gates: usize, gates_data: &'a [u8]) -> RCS<Self> {
+        CSHandle::new(Self::new(num_gates, gates_data))
+    }
 }
 
-impl<Fr: PrimeField> BuildCS<Fr> {
+impl<Fr: PrimeField, H: HandleKind> BuildCS<Fr, H> {
     pub fn new(tracking: bool) -> Self {
         Self {
             values: vec![],
             gates: vec![],
             tracking,
             public: vec![],
+            phantom: PhantomData,
         }
     }
 
     pub fn rc_new(tracking: bool) -> RCS<Self> {
-        Rc::new(RefCell::new(Self::new(tracking)))
+        CSHandle::new(Self::new(tracking))
     }
 }
 
-impl<Fr: PrimeField> CS for BuildCS<Fr> {
+impl<Fr: PrimeField, H: HandleKind> CS for BuildCS<Fr, H> {
     type Fr = Fr;
-    type GateIterator = std::vec::IntoIter<Gate<Self::Fr>>;
+    type LC = PlonkLC<Fr>;
+    type Handle = H::Handle<Self>;
+    type Ops = PlonkOps;
 
     fn num_gates(&self) -> usize {
         self.gates.len()
@@ -96,6 +467,28 @@ impl<Fr: PrimeField> CS for BuildCS<Fr> {
         self.values.len() - self.public.len()
     }
 
+    fn inputize(n: &CNum<Self>) {
+        let v = PlonkOps::input_var(n);
+
+        n.get_cs().borrow_mut().public.push(v);
+    }
+
+    fn alloc(cs: &RCS<Self>, value: Option<&Num<Fr>>) -> CNum<Self> {
+        let mut rcs = cs.borrow_mut();
+        let n_vars = rcs.values.len();
+        let v = n_vars;
+        rcs.values.push(value.cloned());
+        CNum {
+            value: value.cloned(),
+            lc: (Num::ONE, v, Num::ZERO),
+            cs: cs.clone(),
+        }
+    }
+}
+
+impl<Fr: PrimeField, H: HandleKind> PlonkCS for BuildCS<Fr, H> {
+    type GateIterator = std::vec::IntoIter<Gate<Self::Fr>>;
+
     fn get_value(&self, index: usize) -> Option<Num<Self::Fr>> {
         self.values[index]
     }
@@ -119,16 +512,7 @@ impl<Fr: PrimeField> CS for BuildCS<Fr> {
                 _ => {}
             }
         }
-        rcs.gates.push(Gate {
-            a: x.lc.0 * y.lc.2,
-            x: x.lc.1,
-            b: x.lc.2 * y.lc.0,
-            y: y.lc.1,
-            c: -z.lc.0,
-            z: z.lc.1,
-            d: x.lc.0 * y.lc.0,
-            e: x.lc.2 * y.lc.2 - z.lc.2,
-        })
+        rcs.gates.push(mul_gate(x, y, z))
     }
 
     fn enforce_add(x: &CNum<Self>, y: &CNum<Self>, z: &CNum<Self>) {
@@ -141,34 +525,269 @@ impl<Fr: PrimeField> CS for BuildCS<Fr> {
                 _ => {}
             }
         }
-        rcs.gates.push(Gate {
-            a: x.lc.0,
-            x: x.lc.1,
-            b: y.lc.0,
-            y: y.lc.1,
-            c: -z.lc.0,
-            z: z.lc.1,
-            d: Num::ZERO,
-            e: x.lc.2 + y.lc.2 - z.lc.2,
-        })
+        rcs.gates.push(add_gate(x, y, z))
+    }
+
+    fn enforce_pow5(x: &CNum<Self>, y: &CNum<Self>) {
+        let mut rcs = x.get_cs().borrow_mut();
+        if rcs.tracking {
+            match (x.value, y.value) {
+                (Some(x), Some(y)) => {
+                    assert!(pow5(x) == y, "Not satisfied constraint");
+                }
+                _ => {}
+            }
+        }
+        rcs.gates.push(pow5_gate(x, y))
+    }
+
+    fn enforce_sum(terms: &[CNum<Self>], z: &CNum<Self>) {
+        let tracking = z.get_cs().borrow().tracking;
+        if tracking {
+            let value = terms.iter().try_fold(Num::ZERO, |s, t| t.value.map(|v| s + v));
+            match (value, z.value) {
+                (Some(value), Some(z)) => {
+                    assert!(value == z, "Not satisfied constraint");
+                }
+                _ => {}
+            }
+        }
+        let gates = sum_gates(terms, z);
+        z.get_cs().borrow_mut().gates.extend(gates);
+    }
+
+    fn enforce_lookup(table: LookupTable, args: &[CNum<Self>]) {
+        let mut rcs = args[0].get_cs().borrow_mut();
+        if rcs.tracking {
+            let mut row = [Some(Num::ZERO); 3];
+            args.iter().zip(row.iter_mut()).for_each(|(a, r)| *r = a.value);
+            if let [Some(a), Some(b), Some(c)] = row {
+                assert!(table.contains(&[a, b, c]), "Not satisfied constraint");
+            }
+        }
+        rcs.gates.push(lookup_gate(table, args))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ProfilingCS<Fr: PrimeField> {
+    pub num_vars: usize,
+    pub num_gates: usize,
+    pub public: Vec<usize>,
+    pub profiler: Profiler,
+    pub phantom: PhantomData<Fr>,
+}
+
+impl<Fr: PrimeField> ProfilingCS<Fr> {
+    pub fn new() -> Self {
+        Self {
+            num_vars: 0,
+            num_gates: 0,
+            public: vec![],
+            profiler: Profiler::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn rc_new() -> RCS<Self> {
+        Rc::new(RefCell::new(Self::new()))
+    }
+}
+
+impl<Fr: PrimeField> CS for ProfilingCS<Fr> {
+    type Fr = Fr;
+    type LC = PlonkLC<Fr>;
+    type Handle = Rc<RefCell<Self>>;
+    type Ops = PlonkOps;
+
+    fn num_gates(&self) -> usize {
+        self.num_gates
+    }
+
+    fn num_input(&self) -> usize {
+        self.public.len()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.num_vars - self.public.len()
     }
 
     fn inputize(n: &CNum<Self>) {
-        let v = if n.lc.0 == Num::ONE && n.lc.2 == Num::ZERO {
-            n.lc.1
-        } else {
-            let m: CNum<Self> = n.derive_alloc(n.value.as_ref());
-            m.assert_eq(n);
-            m.lc.1
-        };
+        let v = PlonkOps::input_var(n);
+
+        let mut rcs = n.get_cs().borrow_mut();
+        rcs.public.push(v);
+        rcs.profiler.add_input();
+    }
+
+    fn alloc(cs: &RCS<Self>, value: Option<&Num<Fr>>) -> CNum<Self> {
+        let mut rcs = cs.borrow_mut();
+        let v = rcs.num_vars;
+        rcs.num_vars += 1;
+        rcs.profiler.add_aux();
+        CNum {
+            value: value.cloned(),
+            lc: (Num::ONE, v, Num::ZERO),
+            cs: cs.clone(),
+        }
+    }
+
+    fn push_scope(&mut self, name: &str) {
+        self.profiler.push_scope(name);
+    }
+
+    fn pop_scope(&mut self) {
+        self.profiler.pop_scope();
+    }
+}
+
+impl<Fr: PrimeField> PlonkCS for ProfilingCS<Fr> {
+    type GateIterator = core::iter::Empty<Gate<Self::Fr>>;
+
+    fn get_value(&self, _: usize) -> Option<Num<Self::Fr>> {
+        None
+    }
+
+    fn get_gate_iterator(&self) -> Self::GateIterator {
+        core::iter::empty()
+    }
+
+    fn as_public(&self) -> &[usize] {
+        &self.public
+    }
+
+    fn enforce_mul(x: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
+        let mut rcs = x.get_cs().borrow_mut();
+        rcs.num_gates += 1;
+        rcs.profiler.add_gate();
+    }
+
+    fn enforce_add(x: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
+        let mut rcs = x.get_cs().borrow_mut();
+        rcs.num_gates += 1;
+        rcs.profiler.add_gate();
+    }
+
+    fn enforce_pow5(x: &CNum<Self>, _: &CNum<Self>) {
+        let mut rcs = x.get_cs().borrow_mut();
+        rcs.num_gates += 1;
+        rcs.profiler.add_gate();
+    }
+
+    fn enforce_sum(terms: &[CNum<Self>], z: &CNum<Self>) {
+        let n = sum_gates(terms, z).len();
+        let mut rcs = z.get_cs().borrow_mut();
+        for _ in 0..n {
+            rcs.num_gates += 1;
+            rcs.profiler.add_gate();
+        }
+    }
+
+    fn enforce_lookup(_: LookupTable, args: &[CNum<Self>]) {
+        let mut rcs = args[0].get_cs().borrow_mut();
+        rcs.num_gates += 1;
+        rcs.profiler.add_gate();
+    }
+}
+
+/// Value-only constraint system, every gate is checked against the values of its variables as it is added and
+/// is not kept, with the same checks as `check::check_satisfied`.
+///
+/// An unsatisfied gate panics with its index and values, the same as the R1CS `DebugCS`. A system created with
+/// `collecting` does not panic: failing gates are collected in `unsatisfied` with the index in the order the
+/// circuit adds them, so the circuit runs to the end and `is_satisfied` reports them afterwards.
+#[derive(Clone, Debug)]
+pub struct DebugCS<Fr: PrimeField> {
+    pub values: Vec<Option<Num<Fr>>>,
+    pub public: Vec<usize>,
+    pub num_gates: usize,
+    // collect unsatisfied gates instead of panicking
+    pub collect: bool,
+    pub unsatisfied: Vec<UnsatisfiedGate<Fr>>,
+    // first variable allocated without a value
+    pub unassigned: Option<usize>,
+}
+
+impl<Fr: PrimeField> DebugCS<Fr> {
+    pub fn new() -> Self {
+        Self {
+            values: vec![],
+            public: vec![],
+            num_gates: 0,
+            collect: false,
+            unsatisfied: vec![],
+            unassigned: None,
+        }
+    }
+
+    pub fn collecting() -> Self {
+        Self {
+            collect: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn rc_new() -> RCS<Self> {
+        Rc::new(RefCell::new(Self::new()))
+    }
+
+    pub fn rc_collecting() -> RCS<Self> {
+        Rc::new(RefCell::new(Self::collecting()))
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied.is_empty()
+    }
+
+    pub fn first_unsatisfied(&self) -> Option<&UnsatisfiedGate<Fr>> {
+        self.unsatisfied.first()
+    }
+
+    // gates of one constraint, a gate may refer to the next one by rotation
+    fn check_gates(&mut self, gates: Vec<Gate<Fr>>) {
+        for (i, gate) in gates.iter().enumerate() {
+            let index = self.num_gates;
+            self.num_gates += 1;
+            if let Err(e) = check_gate(&*self, index, gate.clone(), gates.get(i + 1)) {
+                if !self.collect {
+                    panic!("{}", e);
+                }
+                self.unsatisfied.push(e);
+            }
+        }
+    }
+}
+
+impl<Fr: PrimeField> CS for DebugCS<Fr> {
+    type Fr = Fr;
+    type LC = PlonkLC<Fr>;
+    type Handle = Rc<RefCell<Self>>;
+    type Ops = PlonkOps;
+
+    fn num_gates(&self) -> usize {
+        self.num_gates
+    }
+
+    fn num_input(&self) -> usize {
+        self.public.len()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.values.len() - self.public.len()
+    }
+
+    fn inputize(n: &CNum<Self>) {
+        let v = PlonkOps::input_var(n);
 
         n.get_cs().borrow_mut().public.push(v);
     }
 
     fn alloc(cs: &RCS<Self>, value: Option<&Num<Fr>>) -> CNum<Self> {
         let mut rcs = cs.borrow_mut();
-        let n_vars = rcs.values.len();
-        let v = n_vars;
+        let v = rcs.values.len();
+        if value.is_none() {
+            rcs.unassigned.get_or_insert(v);
+        }
         rcs.values.push(value.cloned());
         CNum {
             value: value.cloned(),
@@ -177,3 +796,133 @@ impl<Fr: PrimeField> CS for BuildCS<Fr> {
         }
     }
 }
+
+impl<Fr: PrimeField> PlonkCS for DebugCS<Fr> {
+    type GateIterator = core::iter::Empty<Gate<Self::Fr>>;
+
+    fn get_value(&self, index: usize) -> Option<Num<Self::Fr>> {
+        self.values.get(index).cloned().flatten()
+    }
+
+    fn get_gate_iterator(&self) -> Self::GateIterator {
+        core::iter::empty()
+    }
+
+    fn as_public(&self) -> &[usize] {
+        &self.public
+    }
+
+    fn enforce_mul(x: &CNum<Self>, y: &CNum<Self>, z: &CNum<Self>) {
+        x.get_cs().borrow_mut().check_gates(vec![mul_gate(x, y, z)]);
+    }
+
+    fn enforce_add(x: &CNum<Self>, y: &CNum<Self>, z: &CNum<Self>) {
+        x.get_cs().borrow_mut().check_gates(vec![add_gate(x, y, z)]);
+    }
+
+    fn enforce_pow5(x: &CNum<Self>, y: &CNum<Self>) {
+        x.get_cs().borrow_mut().check_gates(vec![pow5_gate(x, y)]);
+    }
+
+    fn enforce_sum(terms: &[CNum<Self>], z: &CNum<Self>) {
+        // the accumulators are allocated while the gates are built
+        let gates = sum_gates(terms, z);
+        z.get_cs().borrow_mut().check_gates(gates);
+    }
+
+    fn enforce_lookup(table: LookupTable, args: &[CNum<Self>]) {
+        args[0].get_cs().borrow_mut().check_gates(vec![lookup_gate(table, args)]);
+    }
+}
+
+pub struct GateStreamedIterator<Fr: PrimeField, R: std::io::Read>(R, PhantomData<Fr>);
+
+impl<Fr: PrimeField, R: std::io::Read> Iterator for GateStreamedIterator<Fr, R> {
+    type Item = Gate<Fr>;
+    fn next(&mut self) -> Option<Self::Item> {
+        // 7 coefficients, 4 variable indices serialized as u64, the pow5 flag and the table id
+        let item_size = 7 * std::mem::size_of::<Fr>() + 4 * std::mem::size_of::<u64>() + 2;
+        let mut buf = vec![0; item_size];
+        self.0.read_exact(&mut buf).ok()?;
+        Gate::deserialize(&mut &buf[..]).ok()
+    }
+}
+
+impl<'a, Fr: PrimeField, H: HandleKind> CS for WitnessCS<'a, Fr, H> {
+    type Fr = Fr;
+    type LC = PlonkLC<Fr>;
+    type Handle = H::Handle<Self>;
+    type Ops = PlonkOps;
+
+    fn num_gates(&self) -> usize {
+        self.num_gates
+    }
+
+    fn num_input(&self) -> usize {
+        self.public.len()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.values.len() - self.public.len()
+    }
+
+    // variables must be allocated in the same order as in `BuildCS`, so the replayed gates match
+    fn inputize(n: &CNum<Self>) {
+        let v = PlonkOps::input_var(n);
+
+        n.get_cs().borrow_mut().public.push(v);
+    }
+
+    fn alloc(cs: &RCS<Self>, value: Option<&Num<Fr>>) -> CNum<Self> {
+        let mut rcs = cs.borrow_mut();
+        let v = rcs.values.len();
+        let witness = value.cloned().unwrap_or_else(|| {
+            rcs.unassigned.get_or_insert(v);
+            Num::ZERO
+        });
+        rcs.values.push(witness);
+        CNum {
+            value: value.cloned(),
+            lc: (Num::ONE, v, Num::ZERO),
+            cs: cs.clone(),
+        }
+    }
+}
+
+impl<'a, Fr: PrimeField, H: HandleKind> PlonkCS for WitnessCS<'a, Fr, H> {
+    type GateIterator = GateStreamedIterator<Fr, brotli::Decompressor<&'a [u8]>>;
+
+    fn get_value(&self, index: usize) -> Option<Num<Self::Fr>> {
+        Some(self.values[index])
+    }
+
+    fn get_gate_iterator(&self) -> Self::GateIterator {
+        GateStreamedIterator(brotli::Decompressor::new(self.gates_data, 4096), PhantomData)
+    }
+
+    fn as_public(&self) -> &[usize] {
+        &self.public
+    }
+
+    fn enforce_mul(x: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
+        x.get_cs().borrow_mut().gate_index += 1;
+    }
+
+    fn enforce_add(x: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
+        x.get_cs().borrow_mut().gate_index += 1;
+    }
+
+    fn enforce_pow5(x: &CNum<Self>, _: &CNum<Self>) {
+        x.get_cs().borrow_mut().gate_index += 1;
+    }
+
+    fn enforce_sum(terms: &[CNum<Self>], z: &CNum<Self>) {
+        // only the accumulators are needed, the gates are replayed from the cached layout
+        let n = sum_gates(terms, z).len();
+        z.get_cs().borrow_mut().gate_index += n;
+    }
+
+    fn enforce_lookup(_: LookupTable, args: &[CNum<Self>]) {
+        args[0].get_cs().borrow_mut().gate_index += 1;
+    }
+}
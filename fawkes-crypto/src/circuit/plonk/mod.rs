@@ -1,3 +1,2 @@
-pub mod bool;
+pub mod check;
 pub mod cs;
-pub mod num;
@@ -1,5 +1,5 @@
 use crate::{
-    circuit::{bool::CBool, cs::{CS, RCS}, num::CNum},
+    circuit::{bool::CBool, cs::{scope, CS, RCS}, num::CNum},
     core::{signal::Signal, sizedvec::SizedVec},
     ff_uint::Num,
     native::poseidon::{MerkleProof, PoseidonParams},
@@ -18,20 +18,18 @@ fn ark<C: CS>(state: &mut [CNum<C>], c: &[Num<C::Fr>]) {
 }
 
 fn sigma<C: CS>(a: &CNum<C>) -> CNum<C> {
-    let a_sq = a * a;
-    let a_quad = &a_sq * &a_sq;
-    a_quad * a
+    a.pow5()
 }
 
 fn mix<C: CS>(state: &mut [CNum<C>], params: &PoseidonParams<C::Fr>) {
-    let statelen = state.len();
-    let cs = state[0].get_cs();
-    let mut new_state = vec![CNum::from_const(cs, &Num::ZERO); statelen];
-    for i in 0..statelen {
-        for j in 0..statelen {
-            new_state[i] += params.m[i][j] * &state[j];
-        }
-    }
+    let new_state = params
+        .m
+        .iter()
+        .map(|m| {
+            let terms = m.iter().zip(state.iter()).map(|(m, s)| *m * s).collect::<Vec<_>>();
+            CNum::sum(state[0].get_cs(), &terms)
+        })
+        .collect::<Vec<_>>();
     state.clone_from_slice(&new_state);
 }
 
@@ -60,6 +58,7 @@ pub fn c_poseidon<C: CS>(inputs: &[CNum<C>], params: &PoseidonParams<C::Fr>) ->
     );
     assert!(n_inputs > 0, "number of inputs should be positive nonzero");
     let cs = inputs[0].get_cs();
+    let _scope = scope(cs, "c_poseidon");
     let mut state = vec![CNum::from_const(cs, &Num::ZERO); params.t];
     (&mut state[0..n_inputs]).clone_from_slice(inputs);
 
@@ -70,6 +69,7 @@ pub fn c_poseidon<C: CS>(inputs: &[CNum<C>], params: &PoseidonParams<C::Fr>) ->
 
 pub fn c_poseidon_sponge<C: CS>(inputs: &[CNum<C>], params: &PoseidonParams<C::Fr>) -> CNum<C> {
     let cs = inputs[0].get_cs();
+    let _scope = scope(cs, "c_poseidon_sponge");
     let mut state = vec![CNum::from_const(cs, &Num::ZERO); params.t];
     let size = CNum::from_const(cs, &Num::from(inputs.len() as u64));
     core::iter::once(&size).chain(inputs.iter()).chunks(params.t-1).into_iter().for_each(|c| {
@@ -85,6 +85,7 @@ pub fn c_poseidon_merkle_proof_root<C: CS, const L: usize>(
     proof: &CMerkleProof<C, L>,
     params: &PoseidonParams<C::Fr>,
 ) -> CNum<C> {
+    let _scope = scope(leaf.get_cs(), "c_poseidon_merkle_proof_root");
     let mut root = leaf.clone();
     for (p, s) in proof.path.iter().zip(proof.sibling.iter()) {
         let first = s.switch(p, &root);
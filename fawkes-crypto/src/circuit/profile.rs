@@ -0,0 +1,136 @@
+use std::fmt::Write;
+
+/// Counters collected for one scope. Repeated scopes with the same name under
+/// the same parent are merged into one node.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileNode {
+    pub name: String,
+    pub calls: usize,
+    pub gates: usize,
+    pub aux: usize,
+    pub inputs: usize,
+    pub children: Vec<ProfileNode>,
+}
+
+impl ProfileNode {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn total_gates(&self) -> usize {
+        self.gates + self.children.iter().map(|c| c.total_gates()).sum::<usize>()
+    }
+
+    pub fn total_aux(&self) -> usize {
+        self.aux + self.children.iter().map(|c| c.total_aux()).sum::<usize>()
+    }
+
+    pub fn total_inputs(&self) -> usize {
+        self.inputs + self.children.iter().map(|c| c.total_inputs()).sum::<usize>()
+    }
+
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        writeln!(
+            out,
+            "{:indent$}{} (calls: {}, gates: {}, aux: {}, inputs: {})",
+            "",
+            self.name,
+            self.calls,
+            self.total_gates(),
+            self.total_aux(),
+            self.total_inputs(),
+            indent = 2 * depth
+        )
+        .unwrap();
+        for c in self.children.iter() {
+            c.write_tree(out, depth + 1);
+        }
+    }
+
+    fn write_folded(&self, out: &mut String, prefix: &str) {
+        let path = if prefix.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{};{}", prefix, self.name)
+        };
+        if self.gates > 0 {
+            writeln!(out, "{} {}", path, self.gates).unwrap();
+        }
+        for c in self.children.iter() {
+            c.write_folded(out, &path);
+        }
+    }
+}
+
+/// Hierarchical gate, aux and input counter, shared by r1cs and plonk `ProfilingCS`
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    pub root: ProfileNode,
+    // child indexes from the root to the current scope
+    stack: Vec<usize>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let mut root = ProfileNode::new("circuit");
+        root.calls = 1;
+        Self {
+            root,
+            stack: vec![],
+        }
+    }
+
+    fn current(&mut self) -> &mut ProfileNode {
+        let mut node = &mut self.root;
+        for &i in self.stack.iter() {
+            node = &mut node.children[i];
+        }
+        node
+    }
+
+    pub fn push_scope(&mut self, name: &str) {
+        let node = self.current();
+        let i = match node.children.iter().position(|c| c.name == name) {
+            Some(i) => i,
+            None => {
+                node.children.push(ProfileNode::new(name));
+                node.children.len() - 1
+            }
+        };
+        node.children[i].calls += 1;
+        self.stack.push(i);
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.stack.pop();
+    }
+
+    pub fn add_gate(&mut self) {
+        self.current().gates += 1;
+    }
+
+    pub fn add_aux(&mut self) {
+        self.current().aux += 1;
+    }
+
+    pub fn add_input(&mut self) {
+        self.current().inputs += 1;
+    }
+
+    /// Indented tree with cumulative counters for every scope
+    pub fn to_tree(&self) -> String {
+        let mut out = String::new();
+        self.root.write_tree(&mut out, 0);
+        out
+    }
+
+    /// Folded stacks (`a;b;c <gates>`) accepted by flamegraph.pl and inferno
+    pub fn to_folded(&self) -> String {
+        let mut out = String::new();
+        self.root.write_folded(&mut out, "");
+        out
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    circuit::{
+        r1cs::{
+            cs::{Gate, R1CS},
+            lc::Index,
+        },
+    },
+    ff_uint::{Num, PrimeField},
+};
+
+/// Gate which is not satisfied by the assignment, or references a variable without value
+#[derive(Clone, Debug)]
+pub struct UnsatisfiedGate<Fr: PrimeField> {
+    pub index: usize,
+    pub gate: Gate<Fr>,
+    pub values: Vec<(Index, Option<Num<Fr>>)>,
+}
+
+impl<Fr: PrimeField> std::fmt::Display for UnsatisfiedGate<Fr> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "gate {} is not satisfied:", self.index)?;
+        for (i, v) in self.values.iter() {
+            match v {
+                Some(v) => write!(f, " {:?}={}", i, v)?,
+                None => write!(f, " {:?}=<missing>", i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn eval_lc<C: R1CS>(cs: &C, lc: &[(Num<C::Fr>, Index)]) -> Option<Num<C::Fr>> {
+    let mut acc = Num::ZERO;
+    for (k, i) in lc.iter() {
+        acc += *k * cs.get_value(*i)?;
+    }
+    Some(acc)
+}
+
+fn check_gate<C: R1CS>(cs: &C, index: usize, gate: Gate<C::Fr>) -> Result<(), UnsatisfiedGate<C::Fr>> {
+    let res = (eval_lc(cs, &gate.0), eval_lc(cs, &gate.1), eval_lc(cs, &gate.2));
+    match res {
+        (Some(a), Some(b), Some(c)) if a * b == c => Ok(()),
+        _ => {
+            let mut values = gate.0.iter().chain(gate.1.iter()).chain(gate.2.iter())
+                .map(|(_, i)| (*i, cs.get_value(*i)))
+                .collect::<Vec<_>>();
+            values.sort_by_key(|(i, _)| *i);
+            values.dedup_by_key(|(i, _)| *i);
+            Err(UnsatisfiedGate { index, gate, values })
+        }
+    }
+}
+
+/// Evaluate every gate of `cs` against its witness values and return the first failing gate.
+/// Works with `WitnessCS` and with `BuildCS` created by `BuildCS::new_tracking`.
+pub fn check_satisfied<C: R1CS>(cs: &C) -> Result<(), UnsatisfiedGate<C::Fr>> {
+    for (index, gate) in cs.get_gate_iterator().enumerate() {
+        check_gate(cs, index, gate)?;
+    }
+    Ok(())
+}
+
+/// Same as `check_satisfied`, but collects all failing gates
+pub fn check_satisfied_all<C: R1CS>(cs: &C) -> Vec<UnsatisfiedGate<C::Fr>> {
+    cs.get_gate_iterator()
+        .enumerate()
+        .filter_map(|(index, gate)| check_gate(cs, index, gate).err())
+        .collect()
+}
@@ -0,0 +1,283 @@
+//! Import and export of circom constraint systems and witnesses in the iden3 binary formats
+//!
+//! Circom wire `0` is the constant one, wires `1..=n_pub` are public signals (outputs, then
+//! public inputs), and all the other wires are private. They are mapped to `Index::Input(wire)`
+//! and `Index::Aux(wire - n_pub - 1)` respectively, so the witness vector is split at
+//! `n_pub + 1` into input and aux values. Exported constraint systems declare all public
+//! signals as public inputs and all aux variables as internal wires.
+
+use crate::{
+    circuit::{
+        r1cs::{
+            cs::{BuildCS, CircuitTrace, Gate, TraceCheckpoints, R1CS},
+            lc::{AbstractLC, Index},
+        },
+    },
+    ff_uint::{Num, NumRepr, PrimeField, PrimeFieldParams, Uint},
+};
+
+use bit_vec::BitVec;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    io::{Error, ErrorKind, Read, Result, Write},
+};
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+
+const R1CS_HEADER: u32 = 1;
+const R1CS_CONSTRAINTS: u32 = 2;
+const R1CS_WIRE2LABEL: u32 = 3;
+
+const WTNS_HEADER: u32 = 1;
+const WTNS_VALUES: u32 = 2;
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn read_sections<R: Read>(reader: &mut R, magic: &[u8; 4]) -> Result<HashMap<u32, Vec<u8>>> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    if &buf != magic {
+        return Err(invalid_data("wrong magic"));
+    }
+    let _version = reader.read_u32::<LittleEndian>()?;
+    let n_sections = reader.read_u32::<LittleEndian>()?;
+
+    let mut sections = HashMap::new();
+    for _ in 0..n_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
+        // the size is untrusted, grow the buffer with the data actually read instead of allocating it upfront
+        let mut data = vec![];
+        reader.by_ref().take(size).read_to_end(&mut data)?;
+        if data.len() as u64 != size {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated section"));
+        }
+        sections.insert(section_type, data);
+    }
+    Ok(sections)
+}
+
+fn n8<Fr: PrimeField>() -> usize {
+    <Fr as PrimeFieldParams>::Inner::NUM_WORDS * 8
+}
+
+// field element size and prime, shared by r1cs and wtns headers
+fn read_field<Fr: PrimeField>(data: &mut &[u8]) -> Result<()> {
+    let field_size = data.read_u32::<LittleEndian>()? as usize;
+    if field_size != n8::<Fr>() {
+        return Err(invalid_data("wrong field size"));
+    }
+    let mut prime = vec![0u8; field_size];
+    data.read_exact(&mut prime)?;
+    if <Fr as PrimeFieldParams>::Inner::from_little_endian(&prime) != Num::<Fr>::MODULUS.0 {
+        return Err(invalid_data("wrong prime"));
+    }
+    Ok(())
+}
+
+fn read_num<Fr: PrimeField>(data: &mut &[u8]) -> Result<Num<Fr>> {
+    let mut buf = vec![0u8; n8::<Fr>()];
+    data.read_exact(&mut buf)?;
+    let n = NumRepr::new(<Fr as PrimeFieldParams>::Inner::from_little_endian(&buf));
+    Num::from_uint(n).ok_or_else(|| invalid_data("field element overflow"))
+}
+
+fn section<'a>(sections: &'a HashMap<u32, Vec<u8>>, section_type: u32) -> Result<&'a [u8]> {
+    sections
+        .get(&section_type)
+        .map(|s| s.as_slice())
+        .ok_or_else(|| invalid_data("missing section"))
+}
+
+fn wire_index(wire: u32, n_pub: u32) -> Index {
+    if wire <= n_pub {
+        Index::Input(wire)
+    } else {
+        Index::Aux(wire - n_pub - 1)
+    }
+}
+
+fn read_lc<Fr: PrimeField>(data: &mut &[u8], n_wires: u32, n_pub: u32) -> Result<Vec<(Num<Fr>, Index)>> {
+    let n = data.read_u32::<LittleEndian>()?;
+    let mut lc = Vec::with_capacity((n as usize).min(data.len() / (4 + n8::<Fr>())));
+    for _ in 0..n {
+        let wire = data.read_u32::<LittleEndian>()?;
+        if wire >= n_wires {
+            return Err(invalid_data("wire index overflow"));
+        }
+        lc.push((read_num(data)?, wire_index(wire, n_pub)));
+    }
+    lc.sort_by_key(|(_, i)| *i);
+    Ok(lc)
+}
+
+/// Parse iden3 `.r1cs` file into r1cs `BuildCS`
+pub fn read_r1cs<Fr: PrimeField, R: Read>(reader: &mut R) -> Result<BuildCS<Fr>> {
+    let sections = read_sections(reader, R1CS_MAGIC)?;
+
+    let mut header = section(&sections, R1CS_HEADER)?;
+    read_field::<Fr>(&mut header)?;
+    let n_wires = header.read_u32::<LittleEndian>()?;
+    let n_pub_out = header.read_u32::<LittleEndian>()?;
+    let n_pub_in = header.read_u32::<LittleEndian>()?;
+    let _n_prv_in = header.read_u32::<LittleEndian>()?;
+    let _n_labels = header.read_u64::<LittleEndian>()?;
+    let n_constraints = header.read_u32::<LittleEndian>()?;
+
+    let n_pub = n_pub_out + n_pub_in;
+    if n_pub >= n_wires {
+        return Err(invalid_data("inconsistent number of wires"));
+    }
+
+    let mut data = section(&sections, R1CS_CONSTRAINTS)?;
+    // each constraint holds at least 3 empty linear combinations
+    let mut gates = Vec::with_capacity((n_constraints as usize).min(data.len() / 12));
+    for _ in 0..n_constraints {
+        let a = read_lc(&mut data, n_wires, n_pub)?;
+        let b = read_lc(&mut data, n_wires, n_pub)?;
+        let c = read_lc(&mut data, n_wires, n_pub)?;
+        gates.push(Gate(a, b, c));
+    }
+
+    Ok(BuildCS {
+        num_input: n_pub as usize + 1,
+        num_aux: (n_wires - n_pub - 1) as usize,
+        gates,
+        const_tracker: BitVec::new(),
+        tracking: false,
+        values_input: vec![],
+        values_aux: vec![],
+        aux_mask: BitVec::new(),
+        trace: CircuitTrace::default(),
+        checkpoints: TraceCheckpoints::default(),
+        phantom: PhantomData,
+    })
+}
+
+/// Parse iden3 `.wtns` file. Values are ordered by circom wire index.
+pub fn read_wtns<Fr: PrimeField, R: Read>(reader: &mut R) -> Result<Vec<Num<Fr>>> {
+    let sections = read_sections(reader, WTNS_MAGIC)?;
+
+    let mut header = section(&sections, WTNS_HEADER)?;
+    read_field::<Fr>(&mut header)?;
+    let n_witness = header.read_u32::<LittleEndian>()?;
+
+    let mut data = section(&sections, WTNS_VALUES)?;
+    let witness = (0..n_witness)
+        .map(|_| read_num(&mut data))
+        .collect::<Result<Vec<_>>>()?;
+
+    if witness.first() != Some(&Num::ONE) {
+        return Err(invalid_data("first witness element should be one"));
+    }
+    Ok(witness)
+}
+
+fn write_sections<W: Write>(writer: &mut W, magic: &[u8; 4], version: u32, sections: &[(u32, Vec<u8>)]) -> Result<()> {
+    writer.write_all(magic)?;
+    writer.write_u32::<LittleEndian>(version)?;
+    writer.write_u32::<LittleEndian>(sections.len() as u32)?;
+    for (section_type, data) in sections.iter() {
+        writer.write_u32::<LittleEndian>(*section_type)?;
+        writer.write_u64::<LittleEndian>(data.len() as u64)?;
+        writer.write_all(data)?;
+    }
+    Ok(())
+}
+
+fn write_field<Fr: PrimeField>(data: &mut Vec<u8>) -> Result<()> {
+    data.write_u32::<LittleEndian>(n8::<Fr>() as u32)?;
+    data.write_all(&Num::<Fr>::MODULUS.0.to_little_endian())
+}
+
+fn write_num<Fr: PrimeField>(data: &mut Vec<u8>, n: &Num<Fr>) -> Result<()> {
+    data.write_all(&n.to_uint().0.to_little_endian())
+}
+
+fn wire_id(index: Index, num_input: usize) -> u32 {
+    match index {
+        Index::Input(i) => i,
+        Index::Aux(i) => num_input as u32 + i,
+    }
+}
+
+fn write_lc<Fr: PrimeField>(data: &mut Vec<u8>, lc: &[(Num<Fr>, Index)], num_input: usize) -> Result<()> {
+    data.write_u32::<LittleEndian>(lc.len() as u32)?;
+    for (k, i) in lc.iter() {
+        data.write_u32::<LittleEndian>(wire_id(*i, num_input))?;
+        write_num(data, k)?;
+    }
+    Ok(())
+}
+
+/// Write r1cs `BuildCS` in iden3 `.r1cs` format
+pub fn write_r1cs<Fr: PrimeField, L: AbstractLC<Fr>, W: Write>(cs: &BuildCS<Fr, L>, writer: &mut W) -> Result<()> {
+    let n_wires = (cs.num_input + cs.num_aux) as u32;
+
+    let mut header = vec![];
+    write_field::<Fr>(&mut header)?;
+    header.write_u32::<LittleEndian>(n_wires)?;
+    header.write_u32::<LittleEndian>(0)?;
+    header.write_u32::<LittleEndian>(cs.num_input as u32 - 1)?;
+    header.write_u32::<LittleEndian>(0)?;
+    header.write_u64::<LittleEndian>(n_wires as u64)?;
+    header.write_u32::<LittleEndian>(cs.gates.len() as u32)?;
+
+    let mut constraints = vec![];
+    for g in cs.gates.iter() {
+        write_lc(&mut constraints, &g.0, cs.num_input)?;
+        write_lc(&mut constraints, &g.1, cs.num_input)?;
+        write_lc(&mut constraints, &g.2, cs.num_input)?;
+    }
+
+    let mut wire2label = Vec::with_capacity(8 * n_wires as usize);
+    for i in 0..n_wires as u64 {
+        wire2label.write_u64::<LittleEndian>(i)?;
+    }
+
+    write_sections(writer, R1CS_MAGIC, 1, &[
+        (R1CS_HEADER, header),
+        (R1CS_CONSTRAINTS, constraints),
+        (R1CS_WIRE2LABEL, wire2label),
+    ])
+}
+
+/// Write witness in iden3 `.wtns` format. Values should be ordered by circom wire index.
+pub fn write_wtns<Fr: PrimeField, W: Write>(witness: &[Num<Fr>], writer: &mut W) -> Result<()> {
+    let mut header = vec![];
+    write_field::<Fr>(&mut header)?;
+    header.write_u32::<LittleEndian>(witness.len() as u32)?;
+
+    let mut values = Vec::with_capacity(n8::<Fr>() * witness.len());
+    for w in witness.iter() {
+        write_num(&mut values, w)?;
+    }
+
+    write_sections(writer, WTNS_MAGIC, 2, &[
+        (WTNS_HEADER, header),
+        (WTNS_VALUES, values),
+    ])
+}
+
+/// Collect witness of `WitnessCS` or tracking `BuildCS` in circom wire order.
+/// Returns None if some value is not assigned.
+pub fn get_witness<C: R1CS>(cs: &C) -> Option<Vec<Num<C::Fr>>> {
+    let inputs = (0..cs.num_input() as u32).map(Index::Input);
+    let aux = (0..cs.num_aux() as u32).map(Index::Aux);
+    inputs.chain(aux).map(|i| cs.get_value(i)).collect()
+}
+
+impl<Fr: PrimeField> BuildCS<Fr> {
+    pub fn write_r1cs<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_r1cs(self, writer)
+    }
+
+    pub fn read_r1cs<R: Read>(reader: &mut R) -> Result<Self> {
+        read_r1cs(reader)
+    }
+}
@@ -1,18 +1,24 @@
 use crate::{
     circuit::{
+        bool::CBool,
+        cs::{CSHandle, HandleKind, RcHandle, SignalOps, CS, RCS},
         num::CNum,
-        lc::{LC, Index, AbstractLC, ZeroLC}
+        r1cs::lc::{LC, VecLC, Index, AbstractLC, ZeroLC},
+        profile::Profiler,
     },
     core::signal::Signal,
     ff_uint::{Num, PrimeField}
 };
 
-use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    ops::Range,
+    rc::Rc,
+};
 use bit_vec::BitVec;
 use byteorder::{ByteOrder, LittleEndian};
 
-pub type RCS<C> = Rc<RefCell<C>>;
-
 #[cfg(feature="borsh_support")]
 use crate::borsh::{BorshSerialize, BorshDeserialize};
 
@@ -25,35 +31,224 @@ pub struct Gate<Fr:PrimeField>(
     pub Vec<(Num<Fr>, Index)>,
 );
 
-pub trait CS: Clone {
-    type Fr: PrimeField;
-    type LC: AbstractLC<Self::Fr>;
+/// Running fingerprint of the circuit: allocations, public inputs, gates, the const tracker and the constants and
+/// coefficients used by the circuit code, in the order the circuit code produces them. It does not depend on the
+/// linear combinations, so `WitnessCS` computes it as well and compares it with the `TraceCheckpoints` recorded
+/// by `BuildCS` at setup. Circuits which differ only in wiring of the gates, i.e. which variables are combined,
+/// have the same trace.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CircuitTrace(u64);
+
+impl CircuitTrace {
+    fn update(&mut self, x: u64) {
+        self.0 = (self.0.rotate_left(5) ^ x).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    pub fn state(&self) -> u64 {
+        self.0
+    }
+
+    pub fn alloc(&mut self) {
+        self.update(1);
+    }
+
+    pub fn const_tracker(&mut self, v: bool) {
+        self.update(2 + v as u64);
+    }
+
+    // a constant or a coefficient, see `CS::trace_constant`
+    pub fn constant<Fr: PrimeField>(&mut self, v: &Num<Fr>) {
+        self.update(6);
+        v.to_mont_uint().as_inner().as_ref().iter().for_each(|x| self.update(*x));
+    }
+
+    // the gate `a*b === c`
+    pub fn gate(&mut self) {
+        self.update(4);
+    }
+
+    // the gate added by `inputize`
+    pub fn input(&mut self) {
+        self.update(5);
+    }
+}
+
+// number of checkpoints kept by `TraceCheckpoints`, between this and twice as many, at most 16 KB
+const MAX_CHECKPOINTS: usize = 1024;
+
+/// `CircuitTrace` states after every `interval` gates and after the whole circuit, so the size does not depend
+/// on the number of gates. The interval is doubled when there are too many checkpoints. A zero interval means
+/// the trace is not known, e.g. for a constraint system imported from circom, and the circuit is not checked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "borsh_support", derive(BorshSerialize, BorshDeserialize))]
+pub struct TraceCheckpoints {
+    pub interval: u64,
+    pub states: Vec<u64>,
+    // number of gates in the order the circuit adds them, not changed by `optimize`
+    pub num_gates: u64,
+    pub last: u64,
+}
+
+impl TraceCheckpoints {
+    pub fn new() -> Self {
+        Self {
+            interval: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interval == 0
+    }
+
+    // record the state after the next gate
+    fn push(&mut self, trace: &CircuitTrace) {
+        self.num_gates += 1;
+        if !self.is_empty() && self.num_gates % self.interval == 0 {
+            self.states.push(trace.state());
+            if self.states.len() == 2 * MAX_CHECKPOINTS {
+                // keep the checkpoints at multiples of the doubled interval
+                self.states = self.states.iter().skip(1).step_by(2).cloned().collect();
+                self.interval *= 2;
+            }
+        }
+    }
+
+    // expected state after the gate with index `gate`, if there is a checkpoint
+    fn get(&self, gate: usize) -> Option<u64> {
+        if self.is_empty() || (gate as u64 + 1) % self.interval != 0 {
+            return None;
+        }
+        self.states.get(((gate as u64 + 1) / self.interval - 1) as usize).cloned()
+    }
+
+    // the checkpoints with the state after the whole circuit
+    fn finish(&self, trace: &CircuitTrace) -> Self {
+        let mut res = self.clone();
+        if !res.is_empty() {
+            res.last = trace.state();
+        }
+        res
+    }
+}
+
+/// R1CS constraint system, every gate is `a*b === c` for linear combinations `a`, `b` and `c`
+pub trait R1CS: CS {
     type GateIterator: Iterator<Item=Gate<Self::Fr>>;
 
-    fn num_gates(&self) -> usize;
-    fn num_input(&self) -> usize;
-    fn num_aux(&self) -> usize;
     fn get_value(&self, index:Index) -> Option<Num<Self::Fr>>;
     fn get_gate_iterator(&self) -> Self::GateIterator;
 
     // a*b === c
     fn enforce(a: &CNum<Self>, b: &CNum<Self>, c: &CNum<Self>);
-    fn inputize(n: &CNum<Self>);
-    fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self>;
+}
 
-    fn const_tracker_before(&mut self) -> Option<bool> {
-        None
+/// `SignalOps` of all R1CS constraint systems. Linear combinations are free, so additions never allocate.
+#[derive(Clone, Debug)]
+pub struct R1csOps;
+
+impl<C: R1CS> SignalOps<C> for R1csOps where C::LC: AbstractLC<C::Fr> {
+    fn lc_const(value: Num<C::Fr>) -> C::LC {
+        <C::LC as AbstractLC<C::Fr>>::from_parts(value, Index::Input(0))
+    }
+
+    fn lc_as_const(lc: &C::LC) -> Option<Num<C::Fr>> {
+        lc.as_const()
+    }
+
+    fn lc_neg(lc: &C::LC) -> C::LC {
+        lc.neg()
+    }
+
+    fn lc_mul(lc: &mut C::LC, k: &Num<C::Fr>) {
+        lc.mul_assign(k)
+    }
+
+    fn lc_capacity(lc: &C::LC) -> usize {
+        lc.capacity()
+    }
+
+    fn add_assign(a: &mut CNum<C>, b: &CNum<C>) {
+        a.value = a.value.map(|x| b.value.map(|y| x + y)).flatten();
+        a.lc.add_assign(&b.lc);
+    }
+
+    fn enforce_mul(a: &CNum<C>, b: &CNum<C>, c: &CNum<C>) {
+        C::enforce(a, b, c)
+    }
+
+    fn enforce_eq(a: &CNum<C>, b: &CNum<C>) {
+        C::enforce(a, &a.derive_const(&Num::ONE), b)
+    }
+
+    fn is_zero(a: &CNum<C>) -> CBool<C> {
+        let inv_value = a
+            .get_value()
+            .map(|v| v.checked_inv().unwrap_or(Num::ZERO));
+        let inv_signal: CNum<C> = a.derive_alloc(inv_value.as_ref());
+
+        let ref res_signal = -inv_signal * a + Num::ONE;
+        (res_signal*a).assert_zero();
+        CBool::new_unchecked(res_signal)
+    }
+
+    fn pow5(a: &CNum<C>) -> CNum<C> {
+        let sq = a * a;
+        let quad = &sq * &sq;
+        quad * a
+    }
+
+    fn sum(cs: &RCS<C>, items: &[CNum<C>]) -> CNum<C> {
+        let mut res = CNum::from_const(cs, &Num::ZERO);
+        items.iter().for_each(|x| res += x);
+        res
+    }
+
+    fn alloc_bool(cs: &RCS<C>, value: Option<&bool>) -> CBool<C> {
+        let value = value.map(|&b| Into::<Num<C::Fr>>::into(b));
+        CBool::new(&CNum::alloc(cs, value.as_ref()))
     }
+}
+
 
-    fn const_tracker_after(&mut self, _:bool) {}
+#[derive(Clone, Debug)]
+pub struct UnsatisfiedConstraint<Fr: PrimeField> {
+    pub gate: usize,
+    pub scope: String,
+    pub a: Num<Fr>,
+    pub b: Num<Fr>,
+    pub c: Num<Fr>,
+}
+
+impl<Fr: PrimeField> std::fmt::Display for UnsatisfiedConstraint<Fr> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "constraint {} at `{}` is not satisfied: a={}, b={}, c={}, a*b={}",
+            self.gate, self.scope, self.a, self.b, self.c, self.a * self.b
+        )
+    }
 }
 
 
+/// Constraint system checking the gates against the signal values as they are added.
+///
+/// An unsatisfied gate panics with its index, scope path and values. A system created with `collecting` does not
+/// panic: failing gates are collected in `unsatisfied`, so the circuit runs to the end and `is_satisfied` or
+/// `assert_satisfied` report them afterwards. Gates with a missing value are not checked.
 #[derive(Clone, Debug)]
 pub struct DebugCS<Fr: PrimeField> {
     pub num_input:usize,
     pub num_aux:usize,
     pub num_gates: usize,
+    pub scope: Vec<String>,
+    // collect unsatisfied gates instead of panicking
+    pub collect: bool,
+    pub unsatisfied: Vec<UnsatisfiedConstraint<Fr>>,
+    // values of the public inputs in the order of `inputize`
+    pub inputs: Vec<Option<Num<Fr>>>,
+    // first variable allocated without a value
+    pub unassigned: Option<Index>,
     pub phantom: PhantomData<Fr>
 }
 
@@ -63,6 +258,65 @@ impl<Fr: PrimeField> DebugCS<Fr> {
             num_input: 1,
             num_aux: 0,
             num_gates: 0,
+            scope: vec![],
+            collect: false,
+            unsatisfied: vec![],
+            inputs: vec![],
+            unassigned: None,
+            phantom: PhantomData
+        }
+    }
+
+    pub fn collecting() -> Self {
+        Self {
+            collect: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn rc_new() -> RCS<Self> {
+        Rc::new(RefCell::new(Self::new()))
+    }
+
+    pub fn rc_collecting() -> RCS<Self> {
+        Rc::new(RefCell::new(Self::collecting()))
+    }
+
+    pub fn scope_path(&self) -> String {
+        self.scope.join("/")
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied.is_empty()
+    }
+
+    pub fn first_unsatisfied(&self) -> Option<&UnsatisfiedConstraint<Fr>> {
+        self.unsatisfied.first()
+    }
+
+    pub fn assert_satisfied(&self) {
+        if let Some(e) = self.first_unsatisfied() {
+            panic!("{}", e);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ProfilingCS<Fr: PrimeField> {
+    pub num_input:usize,
+    pub num_aux:usize,
+    pub num_gates: usize,
+    pub profiler: Profiler,
+    pub phantom: PhantomData<Fr>
+}
+
+impl<Fr: PrimeField> ProfilingCS<Fr> {
+    pub fn new() -> Self {
+        Self {
+            num_input: 1,
+            num_aux: 0,
+            num_gates: 0,
+            profiler: Profiler::new(),
             phantom: PhantomData
         }
     }
@@ -72,53 +326,171 @@ impl<Fr: PrimeField> DebugCS<Fr> {
     }
 }
 
+/// Constraint system collecting the gates for setup. `L` is the linear combination used by the signals, the
+/// vector one by default, `LC` keeps the linked list representation. `H` selects the handle, see `HandleKind`.
 #[derive(Clone, Debug)]
-pub struct BuildCS<Fr: PrimeField> {
+pub struct BuildCS<Fr: PrimeField, L: AbstractLC<Fr> = VecLC<Fr>, H: HandleKind = RcHandle> {
     pub num_input:usize,
     pub num_aux:usize,
     pub gates: Vec<Gate<Fr>>,
-    pub const_tracker: BitVec
+    pub const_tracker: BitVec,
+    // witness values are stored only if tracking is enabled
+    pub tracking: bool,
+    pub values_input: Vec<Option<Num<Fr>>>,
+    pub values_aux: Vec<Option<Num<Fr>>>,
+    // aux variables kept by `optimize`, in allocation order. Empty if the system is not optimized
+    pub aux_mask: BitVec,
+    pub trace: CircuitTrace,
+    pub checkpoints: TraceCheckpoints,
+    pub phantom: PhantomData<(L, H)>,
 }
 
-impl<Fr: PrimeField> BuildCS<Fr> {
+impl<Fr: PrimeField, L: AbstractLC<Fr>, H: HandleKind> BuildCS<Fr, L, H> {
     pub fn new() -> Self {
         Self {
             num_input: 1,
             num_aux: 0,
             gates: vec![],
-            const_tracker: BitVec::new()
+            const_tracker: BitVec::new(),
+            tracking: false,
+            values_input: vec![],
+            values_aux: vec![],
+            aux_mask: BitVec::new(),
+            trace: CircuitTrace::default(),
+            checkpoints: TraceCheckpoints::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn new_tracking() -> Self {
+        Self {
+            tracking: true,
+            values_input: vec![Some(Num::ONE)],
+            ..Self::new()
         }
     }
 
     pub fn rc_new() -> RCS<Self> {
-        Rc::new(RefCell::new(Self::new()))
+        CSHandle::new(Self::new())
+    }
+
+    pub fn rc_new_tracking() -> RCS<Self> {
+        CSHandle::new(Self::new_tracking())
+    }
+
+    /// Checkpoints of the circuit trace stored in `Parameters`, including the state after the whole circuit
+    pub fn trace_checkpoints(&self) -> TraceCheckpoints {
+        self.checkpoints.finish(&self.trace)
+    }
+
+    fn record_gate(&mut self) {
+        let trace = self.trace;
+        self.checkpoints.push(&trace);
     }
 }
 
+/// Constraint system replaying the circuit against the gates cached at setup. Use `ArcHandle` to build
+/// the witness on another thread or across an await point.
 #[derive(Clone, Debug)]
-pub struct WitnessCS<'a, Fr: PrimeField> {
+pub struct WitnessCS<'a, Fr: PrimeField, H: HandleKind = RcHandle> {
     pub values_input: Vec<Num<Fr>>,
     pub values_aux: Vec<Num<Fr>>,
     pub num_gates: usize,
     pub gates_data: &'a[u8],
     pub const_tracker: &'a BitVec,
-    pub const_tracker_index: usize
+    pub const_tracker_index: usize,
+    pub aux_mask: &'a BitVec,
+    pub aux_index: usize,
+    // trace of the circuit recorded at setup, empty if unknown
+    pub checkpoints: &'a TraceCheckpoints,
+    // trace of the circuit being proven, recorded the same way
+    pub recorded: TraceCheckpoints,
+    pub trace: CircuitTrace,
+    // gates containing the first one where the circuit differs from the one used at setup
+    pub diverged: Option<Range<usize>>,
+    // first variable allocated without a value, reported by the prover instead of a panic
+    pub unassigned: Option<Index>,
+    pub phantom: PhantomData<H>,
 }
 
-impl<'a, Fr: PrimeField> WitnessCS<'a, Fr> {
-    pub fn new(num_gates:usize, gates_data: &'a[u8], const_tracker: &'a BitVec) -> Self {
+impl<'a, Fr: PrimeField, H: HandleKind> WitnessCS<'a, Fr, H> {
+    pub fn new(num_gates:usize, gates_data: &'a[u8], const_tracker: &'a BitVec, aux_mask: &'a BitVec, checkpoints: &'a TraceCheckpoints) -> Self {
         Self {
             values_input: vec![Num::ONE],
             values_aux: vec![],
             num_gates,
             gates_data,
             const_tracker,
-            const_tracker_index: 0
+            const_tracker_index: 0,
+            aux_mask,
+            aux_index: 0,
+            checkpoints,
+            recorded: if checkpoints.is_empty() { TraceCheckpoints::default() } else { TraceCheckpoints::new() },
+            trace: CircuitTrace::default(),
+            diverged: None,
+            unassigned: None,
+            phantom: PhantomData,
         }
     }
 
-    pub fn rc_new(num_gates:usize, gates_data: &'a[u8], const_tracker: &'a BitVec) -> RCS<Self> {
-        Rc::new(RefCell::new(Self::new(num_gates, gates_data, const_tracker)))
+    pub fn rc_new(num_gates:usize, gates_data: &'a[u8], const_tracker: &'a BitVec, aux_mask: &'a BitVec, checkpoints: &'a TraceCheckpoints) -> RCS<Self> {
+        CSHandle::new(Self::new(num_gates, gates_data, const_tracker, aux_mask, checkpoints))
+    }
+
+    // index of the current gate
+    fn gate_index(&self) -> usize {
+        self.recorded.num_gates as usize
+    }
+
+    // gates since the last checkpoint up to the current one
+    fn window(&self) -> Range<usize> {
+        let interval = self.checkpoints.interval as usize;
+        (self.gate_index() / interval * interval)..(self.gate_index() + 1)
+    }
+
+    fn check_gate(&mut self) {
+        if self.diverged.is_none() {
+            if let Some(state) = self.checkpoints.get(self.gate_index()) {
+                if state != self.trace.state() {
+                    self.diverged = Some(self.window());
+                }
+            }
+        }
+        let trace = self.trace;
+        self.recorded.push(&trace);
+    }
+
+    /// Checkpoints of the trace of the circuit being proven. They are equal to the ones recorded at setup, if the
+    /// circuit is the same, the prover compares their fingerprint with the one stored in `Parameters`.
+    pub fn trace_checkpoints(&self) -> TraceCheckpoints {
+        self.recorded.finish(&self.trace)
+    }
+
+    /// Gates, in the order the circuit adds them, between the last checkpoint where the circuit matches the one
+    /// used at setup and the first one where it differs. The range is a single gate for circuits with up to 1024
+    /// gates and at most `num_gates / 1024` gates long for larger ones. Locating the exact gate in a large circuit
+    /// needs the trace after every gate, which is not stored to keep `Parameters` small.
+    ///
+    /// Allocations, public inputs, the number and order of the gates, the const tracker and the constants and
+    /// coefficients used by the circuit code are compared. `WitnessCS` does not see the linear combinations, so a
+    /// circuit which differs only in wiring, i.e. which variables are combined, is not detected and gives a proof
+    /// which does not verify.
+    pub fn first_divergence(&self) -> Option<Range<usize>> {
+        if self.checkpoints.is_empty() {
+            return None;
+        }
+        self.diverged.clone().or_else(|| {
+            let differs = self.recorded.num_gates != self.checkpoints.num_gates
+                || self.trace.state() != self.checkpoints.last
+                || self.const_tracker_index != self.const_tracker.len();
+            // a longer circuit differs after the last gate recorded at setup at the latest
+            let interval = self.checkpoints.interval as usize;
+            let last = self.checkpoints.num_gates as usize / interval * interval;
+            differs.then(|| {
+                let window = self.window();
+                window.start.min(last)..window.end
+            })
+        })
     }
 }
 
@@ -126,7 +498,8 @@ impl<'a, Fr: PrimeField> WitnessCS<'a, Fr> {
 impl<Fr: PrimeField>  CS for DebugCS<Fr> {
     type Fr = Fr;
     type LC = LC<Fr>;
-    type GateIterator = core::iter::Empty<Gate<Self::Fr>>;
+    type Handle = Rc<RefCell<Self>>;
+    type Ops = R1csOps;
 
     fn num_gates(&self) -> usize {
         self.num_gates
@@ -139,38 +512,99 @@ impl<Fr: PrimeField>  CS for DebugCS<Fr> {
         self.num_aux
     }
 
+    fn inputize(n: &CNum<Self>) {
+        let mut rcs = n.get_cs().borrow_mut();
+        rcs.num_gates+=1;
+        rcs.num_input+=1;
+        rcs.inputs.push(n.get_value());
+    }
+
+    fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self> {
+        let mut rcs = cs.borrow_mut();
+        let v = rcs.num_aux as u32;
+        rcs.num_aux+=1;
+        if value.is_none() {
+            rcs.unassigned.get_or_insert(Index::Aux(v));
+        }
+        CNum {
+            value: value.cloned(),
+            lc: LC::from_index(Index::Aux(v)),
+            cs: cs.clone(),
+        }
+    }
+
+    fn push_scope(&mut self, name: &str) {
+        self.scope.push(name.to_string());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scope.pop();
+    }
+}
+
+impl<Fr: PrimeField> R1CS for DebugCS<Fr> {
+    type GateIterator = core::iter::Empty<Gate<Self::Fr>>;
+
     fn get_value(&self, _:Index) -> Option<Num<Fr>> {
         None
     }
 
     fn get_gate_iterator(&self) -> Self::GateIterator {
-        std::unimplemented!();
+        core::iter::empty()
     }
 
     // a*b === c
     fn enforce(a: &CNum<Self>, b: &CNum<Self>, c: &CNum<Self>) {
         let mut rcs = a.get_cs().borrow_mut();
+        let gate = rcs.num_gates;
         rcs.num_gates+=1;
 
         match (a.value, b.value, c.value) {
-            (Some(a), Some(b), Some(c)) => {
-                assert!(a * b == c, "Not satisfied constraint");
+            (Some(a), Some(b), Some(c)) if a * b != c => {
+                let scope = rcs.scope_path();
+                let e = UnsatisfiedConstraint { gate, scope, a, b, c };
+                if !rcs.collect {
+                    panic!("{}", e);
+                }
+                rcs.unsatisfied.push(e);
             },
             _ => {}
         }
         
     }
+}
+
+
+impl<Fr: PrimeField> CS for ProfilingCS<Fr> {
+    type Fr = Fr;
+    type LC = LC<Fr>;
+    type Handle = Rc<RefCell<Self>>;
+    type Ops = R1csOps;
+
+    fn num_gates(&self) -> usize {
+        self.num_gates
+    }
+
+    fn num_input(&self) -> usize {
+        self.num_input
+    }
+    fn num_aux(&self) -> usize {
+        self.num_aux
+    }
 
     fn inputize(n: &CNum<Self>) {
         let mut rcs = n.get_cs().borrow_mut();
         rcs.num_gates+=1;
         rcs.num_input+=1;
+        rcs.profiler.add_gate();
+        rcs.profiler.add_input();
     }
 
     fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self> {
         let mut rcs = cs.borrow_mut();
         let v = rcs.num_aux as u32;
         rcs.num_aux+=1;
+        rcs.profiler.add_aux();
         CNum {
             value: value.cloned(),
             lc: LC::from_index(Index::Aux(v)),
@@ -178,6 +612,31 @@ impl<Fr: PrimeField>  CS for DebugCS<Fr> {
         }
     }
 
+    fn push_scope(&mut self, name: &str) {
+        self.profiler.push_scope(name);
+    }
+
+    fn pop_scope(&mut self) {
+        self.profiler.pop_scope();
+    }
+}
+
+impl<Fr: PrimeField> R1CS for ProfilingCS<Fr> {
+    type GateIterator = core::iter::Empty<Gate<Self::Fr>>;
+
+    fn get_value(&self, _:Index) -> Option<Num<Fr>> {
+        None
+    }
+
+    fn get_gate_iterator(&self) -> Self::GateIterator {
+        core::iter::empty()
+    }
+
+    fn enforce(a: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
+        let mut rcs = a.get_cs().borrow_mut();
+        rcs.num_gates+=1;
+        rcs.profiler.add_gate();
+    }
 }
 
 
@@ -222,10 +681,11 @@ impl<Fr:PrimeField, R:std::io::Read> Iterator for GateStreamedIterator<Fr, R> {
     }
 }
 
-impl<'a, Fr: PrimeField> CS for WitnessCS<'a, Fr> {
+impl<'a, Fr: PrimeField, H: HandleKind> CS for WitnessCS<'a, Fr, H> {
     type Fr = Fr;
     type LC = ZeroLC;
-    type GateIterator = GateStreamedIterator<Fr, brotli::Decompressor<&'a [u8]>>;
+    type Handle = H::Handle<Self>;
+    type Ops = R1csOps;
 
     fn num_gates(&self) -> usize {
         self.num_gates
@@ -238,28 +698,31 @@ impl<'a, Fr: PrimeField> CS for WitnessCS<'a, Fr> {
         self.values_aux.len()
     }
 
-    fn get_value(&self, index:Index) -> Option<Num<Fr>> {
-        match index {
-            Index::Input(i) => Some(self.values_input[i as usize]),
-            Index::Aux(i) => Some(self.values_aux[i as usize]),
-        }
-    }
-
-    fn get_gate_iterator(&self) -> Self::GateIterator {
-        GateStreamedIterator(brotli::Decompressor::new(self.gates_data, 4096), PhantomData)
-    }
-
-    fn enforce(_: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
-    }
-
     fn inputize(n: &CNum<Self>) {
         let mut rcs = n.get_cs().borrow_mut();
-        rcs.values_input.push(n.get_value().expect("value is empty"));
+        let index = Index::Input(rcs.values_input.len() as u32);
+        let value = n.get_value().unwrap_or_else(|| {
+            rcs.unassigned.get_or_insert(index);
+            Num::ZERO
+        });
+        rcs.values_input.push(value);
+        rcs.trace.input();
+        rcs.check_gate();
     }
 
     fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self> {
         let mut rcs = cs.borrow_mut();
-        rcs.values_aux.push(value.cloned().expect("value is empty"));
+        let index = Index::Aux(rcs.aux_index as u32);
+        let witness = value.cloned().unwrap_or_else(|| {
+            rcs.unassigned.get_or_insert(index);
+            Num::ZERO
+        });
+        rcs.trace.alloc();
+        // variables removed by `optimize` are not a part of the witness
+        if rcs.aux_mask.get(rcs.aux_index).unwrap_or(true) {
+            rcs.values_aux.push(witness);
+        }
+        rcs.aux_index+=1;
         CNum {
             value: value.cloned(),
             lc: ZeroLC,
@@ -270,16 +733,53 @@ impl<'a, Fr: PrimeField> CS for WitnessCS<'a, Fr> {
     fn const_tracker_before(&mut self) -> Option<bool> {
         let i = self.const_tracker_index;
         self.const_tracker_index+=1;
-        Some(self.const_tracker[i])
+        // the circuit is longer than the one used at setup, keep going to report the divergence
+        let v = match self.const_tracker.get(i) {
+            Some(v) => v,
+            None if !self.checkpoints.is_empty() => {
+                if self.diverged.is_none() {
+                    self.diverged = Some(self.window());
+                }
+                false
+            }
+            None => panic!("not enough cached data"),
+        };
+        self.trace.const_tracker(v);
+        Some(v)
     }
 
+    fn trace_constant(&mut self, v: &Num<Fr>) {
+        self.trace.constant(v);
+    }
 }
 
+impl<'a, Fr: PrimeField, H: HandleKind> R1CS for WitnessCS<'a, Fr, H> {
+    type GateIterator = GateStreamedIterator<Fr, brotli::Decompressor<&'a [u8]>>;
 
-impl<Fr: PrimeField> CS for BuildCS<Fr> {
+    fn get_value(&self, index:Index) -> Option<Num<Fr>> {
+        match index {
+            Index::Input(i) => Some(self.values_input[i as usize]),
+            Index::Aux(i) => Some(self.values_aux[i as usize]),
+        }
+    }
+
+    fn get_gate_iterator(&self) -> Self::GateIterator {
+        GateStreamedIterator(brotli::Decompressor::new(self.gates_data, 4096), PhantomData)
+    }
+
+    fn enforce(a: &CNum<Self>, _: &CNum<Self>, _: &CNum<Self>) {
+        let mut rcs = a.get_cs().borrow_mut();
+        rcs.trace.gate();
+        rcs.check_gate();
+    }
+}
+
+
+impl<Fr: PrimeField, L: AbstractLC<Fr>, H: HandleKind> CS for BuildCS<Fr, L, H> {
     type Fr = Fr;
-    type LC = LC<Fr>;
-    type GateIterator = std::vec::IntoIter<Gate<Self::Fr>>;
+    type LC = L;
+    type Handle = H::Handle<Self>;
+    type Ops = R1csOps;
 
     fn num_gates(&self) -> usize {
         self.gates.len()
@@ -292,24 +792,15 @@ impl<Fr: PrimeField> CS for BuildCS<Fr> {
         self.num_aux
     }
 
-    fn get_value(&self, _:Index) -> Option<Num<Fr>> {
-        None
-    }
-
-    fn get_gate_iterator(&self) -> Self::GateIterator {
-        self.gates.clone().into_iter()
-    }
-
-    // a*b === c
-    fn enforce(a: &CNum<Self>, b: &CNum<Self>, c: &CNum<Self>) {
-        let mut rcs = a.get_cs().borrow_mut();
-        rcs.gates.push(Gate(a.lc.to_vec(), b.lc.to_vec(), c.lc.to_vec()))
-    }
-
     fn inputize(n: &CNum<Self>) {
         let mut rcs = n.get_cs().borrow_mut();
         let v = rcs.num_input as u32;
         rcs.num_input+=1;
+        if rcs.tracking {
+            rcs.values_input.push(n.get_value());
+        }
+        rcs.trace.input();
+        rcs.record_gate();
         rcs.gates.push(Gate(
             n.lc.to_vec(),
             vec![(Num::ONE, Index::Input(0))],
@@ -317,18 +808,53 @@ impl<Fr: PrimeField> CS for BuildCS<Fr> {
         ));
     }
 
-    fn alloc(cs: &RCS<Self>, _: Option<&Num<Self::Fr>>) -> CNum<Self> {
+    fn alloc(cs: &RCS<Self>, value: Option<&Num<Self::Fr>>) -> CNum<Self> {
         let mut rcs = cs.borrow_mut();
         let v = rcs.num_aux as u32;
         rcs.num_aux+=1;
+        rcs.trace.alloc();
+        if rcs.tracking {
+            rcs.values_aux.push(value.cloned());
+        }
         CNum {
-            value: None,
-            lc: LC::from_index(Index::Aux(v)),
+            value: if rcs.tracking { value.cloned() } else { None },
+            lc: L::from_index(Index::Aux(v)),
             cs: cs.clone(),
         }
     }
 
     fn const_tracker_after(&mut self, v:bool) {
         self.const_tracker.push(v);
+        self.trace.const_tracker(v);
+    }
+
+    fn trace_constant(&mut self, v: &Num<Fr>) {
+        self.trace.constant(v);
+    }
+}
+
+impl<Fr: PrimeField, L: AbstractLC<Fr>, H: HandleKind> R1CS for BuildCS<Fr, L, H> {
+    type GateIterator = std::vec::IntoIter<Gate<Self::Fr>>;
+
+    fn get_value(&self, index:Index) -> Option<Num<Fr>> {
+        if !self.tracking {
+            return None;
+        }
+        match index {
+            Index::Input(i) => self.values_input[i as usize],
+            Index::Aux(i) => self.values_aux[i as usize],
+        }
+    }
+
+    fn get_gate_iterator(&self) -> Self::GateIterator {
+        self.gates.clone().into_iter()
+    }
+
+    // a*b === c
+    fn enforce(a: &CNum<Self>, b: &CNum<Self>, c: &CNum<Self>) {
+        let mut rcs = a.get_cs().borrow_mut();
+        rcs.trace.gate();
+        rcs.record_gate();
+        rcs.gates.push(Gate(a.lc.to_vec(), b.lc.to_vec(), c.lc.to_vec()))
     }
 }
@@ -141,7 +141,130 @@ impl<Fr:PrimeField> AbstractLC<Fr> for LC<Fr> {
 
 
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+/// Sorted vector of terms, merges in one pass and keeps the terms in one allocation
+#[derive(Clone, Debug)]
+pub struct VecLC<Fr:PrimeField>(pub Vec<(Num<Fr>, Index)>);
+
+impl<Fr:PrimeField> VecLC<Fr> {
+    // self + other or self - other, same as the linked list version zero terms are removed only after addition
+    #[inline]
+    fn merge(&mut self, other: &Self, neg: bool) {
+        let sign = |v: Num<Fr>| if neg { -v } else { v };
+
+        // the most common case is adding a single variable, update in place
+        if other.0.len() == 1 {
+            let (v, k) = other.0[0];
+            match self.0.binary_search_by(|e| e.1.cmp(&k)) {
+                Ok(i) => {
+                    self.0[i].0 += sign(v);
+                    if self.0[i].0.is_zero() {
+                        self.0.remove(i);
+                    }
+                },
+                Err(i) => self.0.insert(i, (sign(v), k)),
+            }
+            return;
+        }
+
+        let mut res = Vec::with_capacity(self.0.len() + other.0.len());
+        let (mut a, mut b) = (self.0.iter().peekable(), other.0.iter().peekable());
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some(&&x), Some(&&y)) => match x.1.cmp(&y.1) {
+                    core::cmp::Ordering::Less => {
+                        res.push(x);
+                        a.next();
+                    },
+                    core::cmp::Ordering::Greater => {
+                        res.push((sign(y.0), y.1));
+                        b.next();
+                    },
+                    core::cmp::Ordering::Equal => {
+                        let v = x.0 + sign(y.0);
+                        if !v.is_zero() {
+                            res.push((v, x.1));
+                        }
+                        a.next();
+                        b.next();
+                    }
+                },
+                (Some(_), None) => {
+                    res.extend(a);
+                    break;
+                },
+                (None, Some(_)) => {
+                    res.extend(b.map(|&(v, k)| (sign(v), k)));
+                    break;
+                },
+                (None, None) => break,
+            }
+        }
+        self.0 = res;
+    }
+}
+
+impl<Fr:PrimeField> AbstractLC<Fr> for VecLC<Fr> {
+    fn to_vec(&self) -> Vec<(Num<Fr>, Index)> {
+        self.0.clone()
+    }
+
+    fn new() -> Self {
+        VecLC(Vec::new())
+    }
+
+    fn from_index(index:Index) -> Self {
+        Self::from_parts(Num::ONE, index)
+    }
+
+    fn from_parts(value:Num<Fr>, index:Index) -> Self {
+        VecLC(vec![(value, index)])
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn as_const(&self) -> Option<Num<Fr>> {
+        match self.0.as_slice() {
+            [] => Some(Num::ZERO),
+            [(v, Index::Input(0))] => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    fn add_assign(&mut self, other: &Self) {
+        self.merge(other, false)
+    }
+
+    #[inline]
+    fn sub_assign(&mut self, other: &Self) {
+        self.merge(other, true)
+    }
+
+    #[inline]
+    fn mul_assign(&mut self, other: &Num<Fr>) {
+        if other.is_zero() {
+            self.0.clear();
+        } else {
+            for (v, _) in self.0.iter_mut() {
+                *v *= other;
+            }
+        }
+    }
+
+    #[inline]
+    fn neg(&self) -> Self {
+        VecLC(self.0.iter().map(|&(v, k)| (-v, k)).collect())
+    }
+}
+
+
+#[derive(PartialEq, Hash, Copy, Clone, Debug)]
 #[cfg_attr(feature = "borsh_support", derive(BorshSerialize, BorshDeserialize))]
 pub enum Index {
     Input(u32),
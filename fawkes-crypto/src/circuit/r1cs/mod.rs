@@ -1,4 +1,5 @@
-pub mod bool;
+pub mod check;
+pub mod circom;
 pub mod cs;
-pub mod num;
-pub mod lc;
\ No newline at end of file
+pub mod optimize;
+pub mod lc;
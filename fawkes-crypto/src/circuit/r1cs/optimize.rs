@@ -0,0 +1,202 @@
+use crate::{
+    circuit::r1cs::{
+        cs::{BuildCS, Gate},
+        lc::{AbstractLC, Index},
+    },
+    ff_uint::{Num, NumRepr, PrimeField},
+};
+
+use bit_vec::BitVec;
+use std::collections::{HashMap, HashSet};
+
+// linear definitions longer than this are kept as gates, substituting them would blow up the
+// remaining linear combinations
+const MAX_SUBST_TERMS: usize = 8;
+
+type Terms<Fr> = Vec<(Num<Fr>, Index)>;
+type GateKey<Fr> = Vec<(NumRepr<<Fr as PrimeField>::Inner>, Index)>;
+
+/// Summary of the `optimize` pass
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptimizeStats {
+    pub substituted: usize,
+    pub duplicates: usize,
+    pub trivial: usize,
+    pub removed_aux: usize,
+}
+
+// a + k*b, both sorted by index
+fn lc_add<Fr: PrimeField>(a: &[(Num<Fr>, Index)], b: &[(Num<Fr>, Index)], k: Num<Fr>) -> Terms<Fr> {
+    let mut res = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let (v, index) = if j == b.len() || i < a.len() && a[i].1 < b[j].1 {
+            i += 1;
+            a[i - 1]
+        } else if i == a.len() || b[j].1 < a[i].1 {
+            j += 1;
+            (b[j - 1].0 * k, b[j - 1].1)
+        } else {
+            i += 1;
+            j += 1;
+            (a[i - 1].0 + b[j - 1].0 * k, a[i - 1].1)
+        };
+        if !v.is_zero() {
+            res.push((v, index));
+        }
+    }
+    res
+}
+
+fn lc_const<Fr: PrimeField>(lc: &[(Num<Fr>, Index)]) -> Option<Num<Fr>> {
+    match lc {
+        [] => Some(Num::ZERO),
+        [(k, Index::Input(0))] => Some(*k),
+        _ => None,
+    }
+}
+
+fn substitute<Fr: PrimeField>(lc: &[(Num<Fr>, Index)], defs: &HashMap<u32, Terms<Fr>>) -> Terms<Fr> {
+    let mut res = Vec::with_capacity(lc.len());
+    let mut subst = vec![];
+    for &(k, i) in lc.iter() {
+        match i {
+            Index::Aux(v) if defs.contains_key(&v) => subst.push((k, v)),
+            _ => res.push((k, i)),
+        }
+    }
+    for (k, v) in subst {
+        res = lc_add(&res, &defs[&v], k);
+    }
+    res
+}
+
+fn gate_key<Fr: PrimeField>(lc: &[(Num<Fr>, Index)]) -> GateKey<Fr> {
+    lc.iter().map(|(k, i)| (k.to_mont_uint(), *i)).collect()
+}
+
+// linear constraint `lc == 0` expressed by the gate, if one of the multipliers is constant
+fn linear_part<Fr: PrimeField>(g: &Gate<Fr>) -> Option<Terms<Fr>> {
+    let (k, other) = match (lc_const(&g.0), lc_const(&g.1)) {
+        (Some(k), _) => (k, &g.1),
+        (_, Some(k)) => (k, &g.0),
+        _ => return None,
+    };
+    Some(lc_add(&g.2, other, -k))
+}
+
+/// Simplify the gates of `cs` before setup.
+///
+/// The pass removes trivially satisfied and duplicate gates, substitutes aux variables defined by linear gates
+/// (`x * 1 = lc`) into the rest of the system and drops aux variables that are no longer referenced. The
+/// remaining aux variables are renumbered, `cs.aux_mask` records which of the allocated ones were kept, so
+/// `WitnessCS` can replay the unoptimized circuit against the optimized parameters. Public inputs are never
+/// touched. The const tracker stays valid as is, it depends only on the circuit code, not on the gates.
+///
+/// Optimization is opt-in: `setup_optimized` and `setup_optimized_with_rng` call this for a circuit closure,
+/// `setup` does not. Prebuilt systems passed to `setup_cs` should be optimized explicitly, if needed.
+pub fn optimize<Fr: PrimeField, L: AbstractLC<Fr>>(cs: &mut BuildCS<Fr, L>) -> OptimizeStats {
+    let mut stats = OptimizeStats::default();
+    let mut defs: HashMap<u32, Terms<Fr>> = HashMap::new();
+    // aux variable -> variables whose definitions reference it
+    let mut users: HashMap<u32, Vec<u32>> = HashMap::new();
+
+    let mut gates = Vec::with_capacity(cs.gates.len());
+    for g in cs.gates.drain(..) {
+        let g = Gate(substitute(&g.0, &defs), substitute(&g.1, &defs), substitute(&g.2, &defs));
+        let lc = match linear_part(&g) {
+            Some(lc) => lc,
+            None => {
+                gates.push(g);
+                continue;
+            }
+        };
+
+        match lc.last() {
+            None => stats.trivial += 1,
+            Some(&(k, Index::Aux(x))) if lc.len() <= MAX_SUBST_TERMS + 1 => {
+                let def = lc_add(&[], &lc[..lc.len() - 1], -k.checked_inv().unwrap());
+                for u in users.remove(&x).unwrap_or_default() {
+                    let d = &defs[&u];
+                    if let Ok(p) = d.binary_search_by(|t| t.1.cmp(&Index::Aux(x))) {
+                        let mut rest = d.clone();
+                        let (kx, _) = rest.remove(p);
+                        let d = lc_add(&rest, &def, kx);
+                        for &(_, i) in d.iter() {
+                            if let Index::Aux(v) = i {
+                                users.entry(v).or_default().push(u);
+                            }
+                        }
+                        defs.insert(u, d);
+                    }
+                }
+                for &(_, i) in def.iter() {
+                    if let Index::Aux(v) = i {
+                        users.entry(v).or_default().push(x);
+                    }
+                }
+                defs.insert(x, def);
+                stats.substituted += 1;
+            }
+            _ => gates.push(g),
+        }
+    }
+
+    // definitions found later may reference variables of the gates kept earlier
+    let mut seen = HashSet::new();
+    for g in gates.into_iter() {
+        let g = Gate(substitute(&g.0, &defs), substitute(&g.1, &defs), substitute(&g.2, &defs));
+        if linear_part(&g).map(|lc| lc.is_empty()).unwrap_or(false) {
+            stats.trivial += 1;
+            continue;
+        }
+        let (a, b, c) = (gate_key(&g.0), gate_key(&g.1), gate_key(&g.2));
+        let swapped = (b.clone(), a.clone(), c.clone());
+        if seen.contains(&swapped) || !seen.insert((a, b, c)) {
+            stats.duplicates += 1;
+            continue;
+        }
+        cs.gates.push(g);
+    }
+
+    let mut used = BitVec::from_elem(cs.num_aux, false);
+    for g in cs.gates.iter() {
+        for &(_, i) in g.0.iter().chain(g.1.iter()).chain(g.2.iter()) {
+            if let Index::Aux(v) = i {
+                used.set(v as usize, true);
+            }
+        }
+    }
+
+    let mut remap = Vec::with_capacity(cs.num_aux);
+    let mut num_aux = 0u32;
+    for u in used.iter() {
+        remap.push(num_aux);
+        num_aux += u as u32;
+    }
+    stats.removed_aux = cs.num_aux - num_aux as usize;
+
+    for g in cs.gates.iter_mut() {
+        for t in g.0.iter_mut().chain(g.1.iter_mut()).chain(g.2.iter_mut()) {
+            if let Index::Aux(v) = t.1 {
+                t.1 = Index::Aux(remap[v as usize]);
+            }
+        }
+    }
+
+    if cs.tracking {
+        let values = std::mem::take(&mut cs.values_aux);
+        cs.values_aux = values.into_iter().zip(used.iter()).filter(|(_, u)| *u).map(|(v, _)| v).collect();
+    }
+
+    // compose with the mask of a previous pass, so the mask is always indexed by allocation order
+    cs.aux_mask = if cs.aux_mask.is_empty() {
+        used
+    } else {
+        let mut used = used.into_iter();
+        cs.aux_mask.iter().map(|kept| kept && used.next().unwrap()).collect()
+    };
+    cs.num_aux = num_aux as usize;
+
+    stats
+}
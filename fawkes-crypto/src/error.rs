@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// Errors returned by the fallible backend entry points
+#[derive(Debug)]
+pub enum Error {
+    // witness is incomplete or the circuit could not be synthesized
+    Synthesis(String),
+    // key or parameters generation failed
+    Setup(String),
+    // malformed or truncated serialized data
+    Serialization(std::io::Error),
+    // verifier could not process the key, the proof or the inputs
+    Verification(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // malformed encoded data which is not read from an `io::Read`, such as points or keys
+    pub(crate) fn invalid_data(msg: impl Into<String>) -> Self {
+        Error::Serialization(std::io::Error::new(std::io::ErrorKind::InvalidData, msg.into()))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Synthesis(e) => write!(f, "synthesis error: {}", e),
+            Error::Setup(e) => write!(f, "setup error: {}", e),
+            Error::Serialization(e) => write!(f, "serialization error: {}", e),
+            Error::Verification(e) => write!(f, "verification error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Serialization(e)
+    }
+}
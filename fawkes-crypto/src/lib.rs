@@ -6,8 +6,11 @@ pub mod circuit;
 pub mod constants;
 pub mod core;
 pub mod engines;
+pub mod error;
 pub mod native;
 
+pub use error::Error;
+
 #[cfg(feature = "borsh_support")]
 pub extern crate borsh;
 #[cfg(feature = "borsh_support")]
@@ -21,5 +24,13 @@ pub use serde::{Deserialize, Serialize};
 pub extern crate ff_uint;
 #[cfg(feature = "rand_support")]
 pub extern crate rand;
+#[cfg(feature = "backend_bellman_groth16")]
+pub extern crate bellman;
+#[cfg(feature = "plonk")]
+pub extern crate halo2_rand;
+#[cfg(feature = "plonk")]
+pub extern crate halo2_proofs;
+#[cfg(feature = "plonk")]
+pub extern crate plonk_verifier;
 
 pub extern crate fawkes_crypto_derive;
@@ -11,10 +11,14 @@ use fawkes_crypto::{
     core::signal::Signal,
     core::sizedvec::SizedVec,
     engines::bn256::Fr,
+    ff_uint::Num,
     native::poseidon::{poseidon_merkle_proof_root, MerkleProof, PoseidonParams},
     rand::{thread_rng, Rng}
 };
 
+#[cfg(feature="backend_bellman_groth16")]
+mod common;
+
 #[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
 #[test]
 fn test_circuit_poseidon_merkle_root() {
@@ -38,7 +42,7 @@ fn test_circuit_poseidon_merkle_root() {
     let proof = MerkleProof { sibling, path };
     let root = poseidon_merkle_proof_root(leaf, &proof, &poseidon_params);
 
-    println!("BitVec length {}", params.2.len());
+    println!("BitVec length {}", params.gates.len());
 
     let (inputs, snark_proof) = prover::prove(&params, &root, &(leaf, proof), circuit);
 
@@ -46,3 +50,428 @@ fn test_circuit_poseidon_merkle_root() {
     assert!(res, "Verifier result should be true");
 }
 
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_verify_batch() {
+    use crate::common::mul_circuit as circuit;
+    let params = setup::<Bn256, _, _, _>(circuit);
+    let vk = params.get_vk();
+    let pvk = vk.prepare();
+
+    let mut rng = thread_rng();
+    let mut proofs = (0..4).map(|_| {
+        let a: Num<Fr> = rng.gen();
+        let b: Num<Fr> = rng.gen();
+        let (inputs, proof) = prover::prove(&params, &(a * b), &(a, b), circuit);
+        assert!(verifier::verify_prepared(&pvk, &proof, &inputs));
+        (proof, inputs)
+    }).collect::<Vec<_>>();
+
+    assert!(verifier::verify_batch(&vk, &proofs), "Batch verifier result should be true");
+
+    // malformed proofs and inputs are rejected without a panic
+    proofs[1].1.push(Num::ONE);
+    assert!(!verifier::verify_prepared(&pvk, &proofs[1].0, &proofs[1].1));
+    assert!(!verifier::verify_batch(&vk, &proofs));
+    assert!(matches!(verifier::try_verify_batch_prepared(&pvk, &proofs), Ok(false)));
+    proofs[1].1.pop();
+
+    proofs[2].1[0] += Num::ONE;
+    assert!(!verifier::verify_batch_prepared(&pvk, &proofs), "Batch verifier result should be false");
+
+    proofs[3].0.a = G1Point(Num::ONE, Num::ONE);
+    assert!(!verifier::verify_batch_prepared(&pvk, &proofs));
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_prove_different_circuit() {
+    use crate::common::mul_circuit as circuit;
+    fn other_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let square = secret.0.clone() * &secret.0;
+        (square * &secret.1).assert_eq(&public);
+    }
+    // the same structure with a different coefficient
+    fn scaled_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        (secret.0 * secret.1 * Num::from(2)).assert_eq(&public);
+    }
+    let params = setup::<Bn256, _, _, _>(circuit);
+
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+    assert!(prover::try_prove(&params, &(a * b), &(a, b), circuit).is_ok());
+
+    // gates 0 and 1 (public input and the first multiplication) have the same structure
+    match prover::try_prove(&params, &(a * a * b), &(a, b), other_circuit) {
+        Err(fawkes_crypto::Error::Synthesis(msg)) => assert!(msg.ends_with("first differing gate 2"), "{}", msg),
+        _ => panic!("different circuit should be detected"),
+    }
+    match prover::try_prove(&params, &(a * b * Num::from(2)), &(a, b), scaled_circuit) {
+        Err(fawkes_crypto::Error::Synthesis(msg)) => assert!(msg.ends_with("first differing gate 2"), "{}", msg),
+        _ => panic!("different coefficient should be detected"),
+    }
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_prove_different_long_circuit() {
+    const N: usize = 5000;
+    fn circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let mut x = secret.0;
+        for _ in 0..N {
+            x = &x * &secret.1;
+        }
+        x.assert_eq(&public);
+    }
+    // gate N / 2 + 1 is preceded by the gates of `assert_nonzero`
+    fn other_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let mut x = secret.0;
+        for i in 0..N {
+            if i == N / 2 {
+                x.assert_nonzero();
+            }
+            x = &x * &secret.1;
+        }
+        x.assert_eq(&public);
+    }
+    // the same structure with different wiring is not detected
+    fn rewired_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let mut x = secret.0;
+        for i in 0..N {
+            x = if i == N / 2 { &x * &x } else { &x * &secret.1 };
+        }
+        x.assert_eq(&public);
+    }
+    let params = setup::<Bn256, _, _, _>(circuit);
+    // checkpoints instead of a tag per gate
+    assert!(params.checkpoints.states.len() <= 2048 && params.checkpoints.interval > 1);
+
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+    let public = (0..N).fold(a, |x, _| x * b);
+    assert!(prover::try_prove(&params, &public, &(a, b), circuit).is_ok());
+
+    match prover::try_prove(&params, &public, &(a, b), other_circuit) {
+        Err(fawkes_crypto::Error::Synthesis(msg)) => {
+            let range = msg.rsplit("first differing gate in ").next().unwrap();
+            let (start, end) = range.split_once("..").unwrap();
+            let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+            let gate = N / 2 + 1;
+            assert!(start <= gate && gate < end && end - start <= params.checkpoints.interval as usize, "{}", msg);
+        }
+        _ => panic!("different circuit should be detected"),
+    }
+
+    let (inputs, proof) = prover::try_prove(&params, &public, &(a, b), rewired_circuit).unwrap();
+    assert!(!verifier::verify(&params.get_vk(), &proof, &inputs));
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_error_paths() {
+    use fawkes_crypto::{backend::bellman_groth16::group::G1Point, Error};
+
+    use crate::common::mul_circuit as circuit;
+    // the value of `extra` is never assigned, even at proving time
+    fn unassigned_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let extra = CNum::alloc(public.get_cs(), None);
+        (secret.0 * secret.1 + &extra).assert_eq(&public);
+    }
+
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+
+    let params = setup::<Bn256, _, _, _>(unassigned_circuit);
+    match prover::try_prove(&params, &(a * b), &(a, b), unassigned_circuit) {
+        Err(Error::Synthesis(msg)) => assert!(msg.contains("is not assigned"), "{}", msg),
+        _ => panic!("missing value should be reported"),
+    }
+
+    let params = setup::<Bn256, _, _, _>(circuit);
+    let (inputs, proof) = prover::prove(&params, &(a * b), &(a, b), circuit);
+    // a wrong number of inputs fails the check instead of panicking
+    assert!(matches!(verifier::try_verify(&params.get_vk(), &proof, &[]), Ok(false)));
+    let mut vk = params.get_vk();
+    vk.alpha = G1Point(Num::ONE, Num::ONE);
+    assert!(matches!(verifier::try_verify(&vk, &proof, &inputs), Err(Error::Serialization(_))));
+
+    let mut buf = vec![];
+    params.write(&mut buf).unwrap();
+    assert!(Parameters::<Bn256>::read(&mut &buf[..buf.len() / 2], false, false).is_err());
+    assert!(Parameters::<Bn256>::read(&mut &buf[..16], false, false).is_err());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_parameters_format() {
+    use fawkes_crypto::{backend::bellman_groth16::setup::setup_optimized, BorshSerialize};
+
+    fn circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let c = &secret.0 * &secret.1;
+        let d = &c + &secret.0;
+        (&d * &d).assert_eq(&public);
+    }
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+    let public = (a * b + a) * (a * b + a);
+
+    // optimization is opt-in
+    let params = setup::<Bn256, _, _, _>(circuit);
+    assert!(params.aux_mask.is_empty());
+    let optimized = setup_optimized::<Bn256, _, _, _>(circuit);
+    assert!(!optimized.aux_mask.is_empty());
+    assert!(optimized.num_gates < params.num_gates);
+
+    let mut buf = vec![];
+    optimized.write(&mut buf).unwrap();
+    let optimized = Parameters::<Bn256>::read(&mut buf.as_slice(), false, true).unwrap();
+    let (inputs, proof) = prover::prove(&optimized, &public, &(a, b), circuit);
+    assert!(verifier::verify(&optimized.get_vk(), &proof, &inputs));
+
+    // parameters written before the versioned header
+    let mut legacy = vec![];
+    params.num_gates.serialize(&mut legacy).unwrap();
+    params.gates.serialize(&mut legacy).unwrap();
+    (params.const_tracker.len() as u32).serialize(&mut legacy).unwrap();
+    params.const_tracker.to_bytes().serialize(&mut legacy).unwrap();
+    params.params.write(&mut legacy).unwrap();
+    let legacy = Parameters::<Bn256>::read(&mut legacy.as_slice(), false, true).unwrap();
+    assert_eq!((legacy.num_gates, &legacy.gates, &legacy.const_tracker), (params.num_gates, &params.gates, &params.const_tracker));
+    let (inputs, proof) = prover::prove(&legacy, &public, &(a, b), circuit);
+    assert!(verifier::verify(&params.get_vk(), &proof, &inputs));
+
+    let mut buf = vec![];
+    params.write(&mut buf).unwrap();
+    buf[4] += 1;
+    assert!(Parameters::<Bn256>::read(&mut buf.as_slice(), false, true).is_err());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16", feature="serde_support"))]
+#[test]
+fn test_snarkjs_json() {
+    use fawkes_crypto::backend::bellman_groth16::{
+        prover::Proof,
+        snarkjs::{SnarkjsProof, SnarkjsVK},
+        verifier::VK,
+    };
+
+    use crate::common::mul_circuit as circuit;
+    let params = setup::<Bn256, _, _, _>(circuit);
+
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+    let (inputs, proof) = prover::prove(&params, &(a * b), &(a, b), circuit);
+
+    let proof_json = serde_json::to_string(&proof.to_snarkjs()).unwrap();
+    let vk_json = serde_json::to_string(&params.get_vk().to_snarkjs()).unwrap();
+
+    let value: serde_json::Value = serde_json::from_str(&vk_json).unwrap();
+    assert_eq!(value["curve"], "bn128");
+    assert_eq!(value["nPublic"], 1);
+    assert_eq!(value["IC"].as_array().unwrap().len(), 2);
+    assert_eq!(value["vk_beta_2"][2], serde_json::json!(["1", "0"]));
+
+    let proof = Proof::from_snarkjs(&serde_json::from_str::<SnarkjsProof<Bn256>>(&proof_json).unwrap()).unwrap();
+    let vk = VK::from_snarkjs(&serde_json::from_str::<SnarkjsVK<Bn256>>(&vk_json).unwrap()).unwrap();
+    assert!(verifier::verify(&vk, &proof, &inputs), "Verifier result should be true");
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_solidity_verifier() {
+    use fawkes_crypto::backend::bellman_groth16::solidity::*;
+
+    use crate::common::mul_circuit as circuit;
+    let params = setup::<Bn256, _, _, _>(circuit);
+    let vk = params.get_vk();
+
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+    let (mut inputs, proof) = prover::prove(&params, &(a * b), &(a, b), circuit);
+
+    let contract = generate_verifier(&vk);
+    assert!(contract.contains("function verifyProof("));
+    assert!(contract.contains(&format!("uint256 constant IC1_X = {};", vk.ic[1].0)));
+
+    let calldata = encode_calldata(&proof, &inputs);
+    assert_eq!(&calldata[..4], &VERIFY_PROOF_SELECTOR);
+    assert_eq!(calldata.len(), 4 + 32 * 11);
+
+    let pairing = pairing_input(&vk, &proof, &inputs).unwrap();
+    assert_eq!(pairing.len(), 4 * 192);
+    // A.x and B are passed to the precompile as they are encoded in calldata
+    assert_eq!(&pairing[..32], &calldata[4..36]);
+    assert_eq!(&pairing[64..192], &calldata[68..196]);
+    assert_eq!(check_pairing_input(&pairing).unwrap(), verifier::verify(&vk, &proof, &inputs));
+    assert!(check_pairing_input(&pairing).unwrap());
+
+    inputs[0] += Num::ONE;
+    let pairing = pairing_input(&vk, &proof, &inputs).unwrap();
+    assert_eq!(check_pairing_input(&pairing).unwrap(), verifier::verify(&vk, &proof, &inputs));
+    assert!(!check_pairing_input(&pairing).unwrap());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_solidity_verifier_evm() {
+    use fawkes_crypto::backend::bellman_groth16::solidity::*;
+
+    if !common::solc_available() {
+        eprintln!("skipping test_solidity_verifier_evm: solc is not in PATH");
+        return;
+    }
+
+    use crate::common::mul_circuit as circuit;
+    let params = setup::<Bn256, _, _, _>(circuit);
+    let vk = params.get_vk();
+
+    let mut rng = thread_rng();
+    let a: Num<Fr> = rng.gen();
+    let b: Num<Fr> = rng.gen();
+    let (inputs, proof) = prover::prove(&params, &(a * b), &(a, b), circuit);
+
+    // `verifyProof` returns an ABI encoded bool
+    let word = |b: bool| {
+        let mut res = vec![0u8; 32];
+        res[31] = b as u8;
+        res
+    };
+
+    let deployment_code = common::compile_solidity(&generate_verifier(&vk));
+    let res = common::evm_call(deployment_code.clone(), encode_calldata(&proof, &inputs));
+    assert_eq!(res, Ok(word(true)));
+
+    let tampered = common::evm_call(deployment_code.clone(), encode_calldata(&proof, &[inputs[0] + Num::ONE]));
+    assert_eq!(tampered, Ok(word(false)));
+
+    assert!(common::evm_call(deployment_code, encode_calldata(&proof, &[])).is_err());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16", feature="borsh_support"))]
+#[test]
+fn test_seeded_setup_and_prove() {
+    use fawkes_crypto::{
+        backend::bellman_groth16::setup::{setup_optimized_with_rng, setup_with_rng},
+        bellman::rand::{SeedableRng, XorShiftRng},
+        BorshSerialize,
+    };
+
+    use crate::common::mul_circuit as circuit;
+    let seed = [0x3f1c_2a55, 0x0b6d_9e71, 0x5d2e_c4a3, 0x7a91_0f68];
+
+    let params = setup_with_rng::<Bn256, _, _, _, _>(circuit, &mut XorShiftRng::from_seed(seed));
+    let params2 = setup_with_rng::<Bn256, _, _, _, _>(circuit, &mut XorShiftRng::from_seed(seed));
+    assert_eq!(params.get_vk().try_to_vec().unwrap(), params2.get_vk().try_to_vec().unwrap());
+
+    let optimized = setup_optimized_with_rng::<Bn256, _, _, _, _>(circuit, &mut XorShiftRng::from_seed(seed));
+    let optimized2 = setup_optimized_with_rng::<Bn256, _, _, _, _>(circuit, &mut XorShiftRng::from_seed(seed));
+    assert_eq!(optimized.get_vk().try_to_vec().unwrap(), optimized2.get_vk().try_to_vec().unwrap());
+
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    let (inputs, proof) = prover::prove_with_rng(&params, &(a * b), &(a, b), circuit, &mut XorShiftRng::from_seed(seed));
+    let (_, proof2) = prover::prove_with_rng(&params, &(a * b), &(a, b), circuit, &mut XorShiftRng::from_seed(seed));
+    assert_eq!(proof.try_to_vec().unwrap(), proof2.try_to_vec().unwrap());
+    assert!(verifier::verify(&params.get_vk(), &proof, &inputs), "Verifier result should be true");
+}
+
+#[cfg(all(feature = "heavy_tests", feature="backend_bellman_groth16"))]
+#[test]
+fn test_phase2_ceremony() {
+    use fawkes_crypto::{
+        backend::bellman_groth16::{ceremony::*, Parameters},
+        bellman::rand::{SeedableRng, XorShiftRng},
+        circuit::cs::BuildCS,
+    };
+
+    use crate::common::mul_circuit as circuit;
+    let ref rcs = BuildCS::<Fr>::rc_new();
+    let signal_pub = CNum::alloc(rcs, None);
+    signal_pub.inputize();
+    let signal_sec = <(CNum<_>, CNum<_>)>::alloc(rcs, None);
+    circuit(signal_pub, signal_sec);
+
+    let ref mut rng = XorShiftRng::from_seed([0x1b2c_3d4e, 0x5f60_7182, 0x93a4_b5c6, 0xd7e8_f901]);
+    let m = domain_size(&rcs.borrow());
+    let phase1 = Phase1::<Bn256>::insecure_new(m, rng).unwrap();
+    let mut buf = vec![];
+    phase1.write(&mut buf).unwrap();
+    let phase1 = Phase1::<Bn256>::read(&mut buf.as_slice(), m).unwrap();
+    assert!(Phase1::<Bn256>::read(&mut buf.as_slice(), 2 * m).is_err());
+    assert!(Phase1::<Bn256>::read(&mut buf.as_slice(), 0).is_err());
+
+    let snapshot = |mpc: &MPCParameters<Bn256>| {
+        let mut buf = vec![];
+        mpc.write(&mut buf).unwrap();
+        MPCParameters::<Bn256>::read(&mut buf.as_slice(), true).unwrap()
+    };
+
+    let mut mpc = MPCParameters::<Bn256>::new_cs(rcs, &phase1).unwrap();
+    let initial = snapshot(&mpc);
+
+    let hash = mpc.contribute(rng);
+    assert_eq!(verify_contribution(&initial, &mpc).unwrap()[..], hash[..]);
+    let first = snapshot(&mpc);
+
+    mpc.contribute(rng);
+    assert!(verify_contribution(&first, &mpc).is_ok());
+    assert!(verify_contribution(&initial, &mpc).is_err());
+    let second = snapshot(&mpc);
+
+    mpc.beacon(b"block 123456");
+    assert!(verify_beacon(&second, &mpc, b"block 123456").is_ok());
+    assert!(verify_beacon(&second, &mpc, b"block 123457").is_err());
+
+    let mut buf = vec![];
+    mpc.into_params().write(&mut buf).unwrap();
+    let params = Parameters::<Bn256>::read(&mut buf.as_slice(), false, true).unwrap();
+
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    let (inputs, proof) = prover::prove(&params, &(a * b), &(a, b), circuit);
+    assert!(verifier::verify(&params.get_vk(), &proof, &inputs), "Verifier result should be true");
+}
+
+
+// small enough to run without `heavy_tests`
+#[cfg(feature="backend_bellman_groth16")]
+#[test]
+fn test_prove_on_threads() {
+    use fawkes_crypto::{
+        backend::bellman_groth16::{engines::Bn256, prover, setup::setup, verifier},
+        circuit::{cs::ArcHandle, num::CNum},
+        core::signal::Signal,
+        engines::bn256::Fr,
+        ff_uint::Num,
+    };
+
+    use crate::common::mul_circuit as circuit;
+    let params = setup::<Bn256, _, _, _>(circuit);
+
+    std::thread::scope(|s| {
+        let workers = (1..5u64).map(|i| {
+            let params = &params;
+            s.spawn(move || {
+                let (a, b) = (Num::<Fr>::from(i), Num::<Fr>::from(i + 1));
+                let ref rcs = params.get_witness_rcs::<ArcHandle>();
+                let signal_pub = CNum::alloc(rcs, Some(&(a * b)));
+                signal_pub.inputize();
+                let signal_sec = <(CNum<_>, CNum<_>)>::alloc(rcs, Some(&(a, b)));
+                circuit(signal_pub, signal_sec);
+                prover::prove_cs(params, rcs)
+            })
+        }).collect::<Vec<_>>();
+
+        for w in workers {
+            let (inputs, proof) = w.join().unwrap();
+            assert!(verifier::verify(&params.get_vk(), &proof, &inputs), "Verifier result should be true");
+        }
+    });
+}
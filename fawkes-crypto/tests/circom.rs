@@ -0,0 +1,204 @@
+#[cfg(feature = "r1cs")]
+pub mod tests {
+    use fawkes_crypto::{
+        circuit::{check::check_satisfied, circom::*, cs::CS, lc::Index},
+        engines::bn256::Fr,
+        ff_uint::{Num, Uint},
+    };
+
+    fn section(out: &mut Vec<u8>, section_type: u32, data: &[u8]) {
+        out.extend_from_slice(&section_type.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+
+    fn field(out: &mut Vec<u8>) {
+        out.extend_from_slice(&32u32.to_le_bytes());
+        out.extend_from_slice(&Num::<Fr>::MODULUS.0.to_little_endian());
+    }
+
+    fn num(out: &mut Vec<u8>, n: Num<Fr>) {
+        out.extend_from_slice(&n.to_uint().0.to_little_endian());
+    }
+
+    // out = a * b, wires: [one, out, a, b]
+    fn mul_r1cs() -> Vec<u8> {
+        let mut header = vec![];
+        field(&mut header);
+        for n in [4u32, 1, 0, 2] {
+            header.extend_from_slice(&n.to_le_bytes());
+        }
+        header.extend_from_slice(&4u64.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+
+        let mut constraints = vec![];
+        for wire in [2u32, 3, 1] {
+            constraints.extend_from_slice(&1u32.to_le_bytes());
+            constraints.extend_from_slice(&wire.to_le_bytes());
+            num(&mut constraints, Num::ONE);
+        }
+
+        let mut res = b"r1cs".to_vec();
+        res.extend_from_slice(&1u32.to_le_bytes());
+        res.extend_from_slice(&2u32.to_le_bytes());
+        section(&mut res, 1, &header);
+        section(&mut res, 2, &constraints);
+        res
+    }
+
+    fn wtns(witness: &[Num<Fr>]) -> Vec<u8> {
+        let mut header = vec![];
+        field(&mut header);
+        header.extend_from_slice(&(witness.len() as u32).to_le_bytes());
+
+        let mut values = vec![];
+        witness.iter().for_each(|w| num(&mut values, *w));
+
+        let mut res = b"wtns".to_vec();
+        res.extend_from_slice(&2u32.to_le_bytes());
+        res.extend_from_slice(&2u32.to_le_bytes());
+        section(&mut res, 1, &header);
+        section(&mut res, 2, &values);
+        res
+    }
+
+    #[test]
+    fn test_read_r1cs_and_wtns() {
+        let mut cs = read_r1cs::<Fr, _>(&mut mul_r1cs().as_slice()).unwrap();
+        assert_eq!(cs.num_input(), 2);
+        assert_eq!(cs.num_aux(), 2);
+        assert_eq!(cs.gates.len(), 1);
+        assert_eq!(cs.gates[0].0[0].1, Index::Aux(0));
+        assert_eq!(cs.gates[0].2[0].1, Index::Input(1));
+
+        let a = Num::from(3);
+        let b = Num::from(5);
+        let witness = read_wtns::<Fr, _>(&mut wtns(&[Num::ONE, a * b, a, b]).as_slice()).unwrap();
+
+        cs.tracking = true;
+        cs.values_input = witness[..2].iter().cloned().map(Some).collect();
+        cs.values_aux = witness[2..].iter().cloned().map(Some).collect();
+        assert!(check_satisfied(&cs).is_ok());
+
+        cs.values_aux[1] = Some(a);
+        assert!(check_satisfied(&cs).is_err());
+    }
+
+    #[test]
+    fn test_read_r1cs_wrong_magic() {
+        let mut data = mul_r1cs();
+        data[0] = b'x';
+        assert!(read_r1cs::<Fr, _>(&mut data.as_slice()).is_err());
+        assert!(read_wtns::<Fr, _>(&mut mul_r1cs().as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_read_r1cs_oversized_section() {
+        // section claims more data than the file holds
+        let mut data = b"r1cs".to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&[0u8; 16]);
+        assert!(read_r1cs::<Fr, _>(&mut data.as_slice()).is_err());
+
+        let mut data = mul_r1cs();
+        data.truncate(data.len() - 1);
+        assert!(read_r1cs::<Fr, _>(&mut data.as_slice()).is_err());
+    }
+
+    #[cfg(feature = "backend_bellman_groth16")]
+    #[test]
+    fn test_prove_circom_circuit() {
+        use fawkes_crypto::backend::bellman_groth16::{engines::Bn256, prover, setup, verifier};
+        use std::{cell::RefCell, rc::Rc};
+
+        let cs = read_r1cs::<Fr, _>(&mut mul_r1cs().as_slice()).unwrap();
+        let params = setup::setup_cs::<Bn256>(&Rc::new(RefCell::new(cs)));
+
+        let a = Num::from(3);
+        let b = Num::from(5);
+        let witness = read_wtns::<Fr, _>(&mut wtns(&[Num::ONE, a * b, a, b]).as_slice()).unwrap();
+        let (inputs, proof) = prover::prove_witness(&params, &witness);
+        assert_eq!(inputs, vec![a * b]);
+        assert!(verifier::verify(&params.get_vk(), &proof, &inputs), "Verifier result should be true");
+        assert!(!verifier::verify(&params.get_vk(), &proof, &[a * b + Num::ONE]));
+
+        // the witness does not satisfy the constraint, the proof is created but does not verify
+        let (inputs, proof) = prover::prove_witness(&params, &[Num::ONE, a * b, a, a]);
+        assert!(!verifier::verify(&params.get_vk(), &proof, &inputs));
+    }
+
+    #[cfg(feature = "backend_bellman_groth16")]
+    #[test]
+    fn test_prove_exported_gadget() {
+        use fawkes_crypto::{
+            backend::bellman_groth16::{engines::Bn256, prover, setup, verifier},
+            circuit::{num::CNum, poseidon::c_poseidon, r1cs::cs::BuildCS},
+            core::signal::Signal,
+            native::poseidon::{poseidon, PoseidonParams},
+        };
+        use std::{cell::RefCell, rc::Rc};
+
+        let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+        let a = Num::from(3);
+        let b = Num::from(5);
+        let hash = poseidon(&[a, b], &poseidon_params);
+
+        let ref rcs = BuildCS::<Fr>::rc_new_tracking();
+        let signal_pub = CNum::alloc(rcs, Some(&hash));
+        signal_pub.inputize();
+        let signal_sec = <(CNum<_>, CNum<_>)>::alloc(rcs, Some(&(a, b)));
+        c_poseidon(&[signal_sec.0, signal_sec.1], &poseidon_params).assert_eq(&signal_pub);
+
+        let mut r1cs = vec![];
+        let mut wtns = vec![];
+        rcs.borrow().write_r1cs(&mut r1cs).unwrap();
+        write_wtns(&get_witness(&*rcs.borrow()).unwrap(), &mut wtns).unwrap();
+
+        let cs = read_r1cs::<Fr, _>(&mut r1cs.as_slice()).unwrap();
+        assert_eq!(cs.gates.len(), rcs.borrow().gates.len());
+        let params = setup::setup_cs::<Bn256>(&Rc::new(RefCell::new(cs)));
+
+        let witness = read_wtns::<Fr, _>(&mut wtns.as_slice()).unwrap();
+        let (inputs, proof) = prover::prove_witness(&params, &witness);
+        assert_eq!(inputs, vec![hash]);
+        assert!(verifier::verify(&params.get_vk(), &proof, &inputs), "Verifier result should be true");
+    }
+
+    #[test]
+    fn test_write_r1cs_and_wtns_roundtrip() {
+        let data = mul_r1cs();
+        let cs = read_r1cs::<Fr, _>(&mut data.as_slice()).unwrap();
+
+        let mut out = vec![];
+        cs.write_r1cs(&mut out).unwrap();
+        let cs2 = read_r1cs::<Fr, _>(&mut out.as_slice()).unwrap();
+        assert_eq!(cs2.num_input(), cs.num_input());
+        assert_eq!(cs2.num_aux(), cs.num_aux());
+        assert_eq!(cs2.gates.len(), cs.gates.len());
+        for (g, g2) in cs.gates.iter().zip(cs2.gates.iter()) {
+            assert_eq!(g.0, g2.0);
+            assert_eq!(g.1, g2.1);
+            assert_eq!(g.2, g2.2);
+        }
+
+        let a = Num::from(3);
+        let b = Num::from(5);
+        let mut cs = cs;
+        cs.tracking = true;
+        cs.values_input = vec![Some(Num::ONE), Some(a * b)];
+        cs.values_aux = vec![Some(a), Some(b)];
+        let witness = get_witness(&cs).unwrap();
+        assert_eq!(witness, vec![Num::ONE, a * b, a, b]);
+
+        let mut out = vec![];
+        write_wtns(&witness, &mut out).unwrap();
+        assert_eq!(out, wtns(&witness));
+        assert_eq!(read_wtns::<Fr, _>(&mut out.as_slice()).unwrap(), witness);
+
+        cs.values_aux[1] = None;
+        assert!(get_witness(&cs).is_none());
+    }
+}
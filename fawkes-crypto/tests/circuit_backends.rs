@@ -0,0 +1,41 @@
+#[cfg(all(feature = "r1cs", feature = "plonk"))]
+use fawkes_crypto::{
+    circuit::{
+        bitify::{c_from_bits_le, c_into_bits_le_strict},
+        cs::CS,
+        num::CNum,
+        plonk,
+        poseidon::c_poseidon,
+        r1cs,
+    },
+    core::signal::Signal,
+    engines::bn256::Fr,
+    ff_uint::Num,
+    native::poseidon::{poseidon, PoseidonParams},
+    rand::{thread_rng, Rng},
+};
+
+#[cfg(all(feature = "r1cs", feature = "plonk"))]
+fn circuit<C: CS>(inputs: &[CNum<C>], params: &PoseidonParams<C::Fr>) -> CNum<C> {
+    let hash = c_poseidon(inputs, params);
+    c_from_bits_le(&c_into_bits_le_strict(&hash))
+}
+
+#[cfg(all(feature = "r1cs", feature = "plonk"))]
+#[test]
+fn test_circuit_on_both_arithmetizations() {
+    let mut rng = thread_rng();
+    let params = PoseidonParams::<Fr>::new(3, 8, 53);
+    let inputs = (0..2).map(|_| rng.gen()).collect::<Vec<Num<Fr>>>();
+    let expected = poseidon(&inputs, &params);
+
+    let ref rcs = r1cs::cs::BuildCS::<Fr>::rc_new_tracking();
+    let signals = inputs.iter().map(|x| CNum::alloc(rcs, Some(x))).collect::<Vec<_>>();
+    assert_eq!(circuit(&signals, &params).get_value(), Some(expected));
+    assert!(r1cs::check::check_satisfied(&*rcs.borrow()).is_ok());
+
+    let ref rcs = plonk::cs::BuildCS::<Fr>::rc_new(true);
+    let signals = inputs.iter().map(|x| CNum::alloc(rcs, Some(x))).collect::<Vec<_>>();
+    assert_eq!(circuit(&signals, &params).get_value(), Some(expected));
+    assert!(plonk::check::check_satisfied(&*rcs.borrow()).is_ok());
+}
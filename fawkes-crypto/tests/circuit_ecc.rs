@@ -23,6 +23,7 @@ pub mod tests {
         let p = EdwardsPoint::from_scalar(t, &jubjub_params);
 
         signal_p.assert_const(&p);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -41,6 +42,7 @@ pub mod tests {
         n_constraints = cs.borrow().num_gates() - n_constraints;
 
         res.y.assert_const(&p.y);
+        cs.borrow().assert_satisfied();
 
         println!("subgroup_decompress constraints = {}", n_constraints);
 
@@ -64,6 +66,7 @@ pub mod tests {
         let signal_p3 = signal_p1.add(&signal_p2, &jubjub_params);
 
         signal_p3.assert_const(&p3);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -81,6 +84,7 @@ pub mod tests {
         let signal_p3 = signal_p.double(&jubjub_params);
 
         signal_p3.assert_const(&p3);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -93,6 +97,7 @@ pub mod tests {
         let signal_p = CEdwardsPoint::alloc(cs, Some(&p));
         let signal_mp = signal_p.into_montgomery();
         signal_mp.assert_const(&mp);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -107,6 +112,7 @@ pub mod tests {
         let signal_p = signal_mp.into_edwards();
 
         signal_p.assert_const(&p);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -130,6 +136,7 @@ pub mod tests {
         let signal_p3 = signal_mp3.into_edwards();
 
         signal_p3.assert_const(&p3);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -147,6 +154,7 @@ pub mod tests {
         let signal_p3 = signal_mp3.into_edwards();
 
         signal_p3.assert_const(&p3);
+        cs.borrow().assert_satisfied();
     }
 
     #[test]
@@ -171,6 +179,7 @@ pub mod tests {
         n_constraints = cs.borrow().num_gates() - n_constraints;
 
         signal_p3.assert_const(&p3);
+        cs.borrow().assert_satisfied();
         println!("edwards_mul constraints = {}", n_constraints);
     }
 
@@ -196,6 +205,7 @@ pub mod tests {
         n_constraints = cs.borrow().num_gates() - n_constraints;
 
         signal_p3.assert_const(&p3);
+        cs.borrow().assert_satisfied();
 
         println!("edwards_mul_const constraints = {}", n_constraints);
     }
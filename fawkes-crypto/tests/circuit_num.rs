@@ -1,9 +1,18 @@
 #[cfg(feature = "r1cs")]
 pub mod tests {
     use fawkes_crypto::{
-        circuit::{cs::{DebugCS, CS}, num::CNum},
+        circuit::{
+            check::check_satisfied,
+            cs::{scope, BuildCS, DebugCS, CS},
+            lc::{AbstractLC, VecLC, LC},
+            num::CNum,
+            optimize::{optimize, OptimizeStats},
+            poseidon::c_poseidon,
+        },
         core::{signal::Signal},
         engines::bn256::Fr,
+        ff_uint::Num,
+        native::poseidon::PoseidonParams,
         rand::{thread_rng, Rng},
     };
 
@@ -27,5 +36,105 @@ pub mod tests {
 
         println!("a * b^2 == c constraints = {}", n_constraints);
         assert!(c.get_value().unwrap() == _c);
+        cs.borrow().assert_satisfied();
+    }
+
+    #[test]
+    fn test_unsatisfied_constraint_locator() {
+        let ref mut cs = DebugCS::<Fr>::rc_collecting();
+        let mut rng = thread_rng();
+
+        let _a: Num<Fr> = rng.gen();
+        let a = CNum::alloc(cs, Some(&_a));
+        let b = CNum::alloc(cs, Some(&(_a + Num::ONE)));
+
+        {
+            let _s = scope(cs, "outer");
+            let _s = scope(cs, "inner");
+            a.assert_eq(&b);
+        }
+        a.assert_eq(&a);
+
+        let cs = cs.borrow();
+        assert!(!cs.is_satisfied());
+        assert!(cs.scope.is_empty());
+        let e = cs.first_unsatisfied().unwrap();
+        assert_eq!(e.gate, 0);
+        assert_eq!(e.scope, "outer/inner");
+        assert!(e.a == _a && e.c == _a + Num::ONE);
+    }
+
+    #[test]
+    #[should_panic(expected = "constraint 0 at `outer` is not satisfied")]
+    fn test_unsatisfied_constraint_panics() {
+        let ref mut cs = DebugCS::<Fr>::rc_new();
+
+        let a = CNum::alloc(cs, Some(&Num::ONE));
+        let b = CNum::alloc(cs, Some(&Num::ZERO));
+
+        let _s = scope(cs, "outer");
+        a.assert_eq(&b);
+    }
+
+    #[test]
+    fn test_check_satisfied() {
+        let ref mut cs = BuildCS::<Fr>::rc_new_tracking();
+        let mut rng = thread_rng();
+
+        let _a: Num<Fr> = rng.gen();
+        let _b: Num<Fr> = rng.gen();
+
+        let a = CNum::alloc(cs, Some(&_a));
+        let b = CNum::alloc(cs, Some(&_b));
+        let c = &a * &b;
+        c.inputize();
+        assert!(c.get_value().unwrap() == _a * _b);
+        assert!(check_satisfied(&*cs.borrow()).is_ok());
+
+        cs.borrow_mut().values_aux[2] = Some(_a + _b);
+        let e = check_satisfied(&*cs.borrow()).unwrap_err();
+        assert_eq!(e.index, 0);
+    }
+
+    #[test]
+    fn test_optimize() {
+        let ref mut cs = BuildCS::<Fr>::rc_new_tracking();
+        let mut rng = thread_rng();
+
+        let _a: Num<Fr> = rng.gen();
+        let _b: Num<Fr> = rng.gen();
+
+        let a = CNum::alloc(cs, Some(&_a));
+        let b = CNum::alloc(cs, Some(&_b));
+        let c = &a * &b;
+        let d = &a * &b;
+        c.assert_eq(&d);
+        let _unused = CNum::alloc(cs, Some(&_a));
+        (&c + &d).inputize();
+        assert_eq!(cs.borrow().num_gates(), 4);
+
+        let stats = optimize(&mut cs.borrow_mut());
+        assert_eq!(stats, OptimizeStats { substituted: 2, duplicates: 1, trivial: 0, removed_aux: 3 });
+
+        let cs = cs.borrow();
+        assert_eq!((cs.num_gates(), cs.num_input(), cs.num_aux()), (1, 2, 2));
+        assert_eq!(cs.aux_mask.iter().collect::<Vec<_>>(), vec![true, true, false, false, false]);
+        assert!(check_satisfied(&*cs).is_ok());
+    }
+
+    #[test]
+    fn test_vec_lc_gates() {
+        fn gates<L: AbstractLC<Fr>>() -> Vec<String> {
+            let ref cs = BuildCS::<Fr, L>::rc_new();
+            let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+            let a = CNum::alloc(cs, None);
+            let b = CNum::alloc(cs, None);
+            let c = &a * Num::from(3) - &b + Num::ONE;
+            (&c - &c * Num::from(2) + &a).assert_zero();
+            c_poseidon(&[a, b, c], &poseidon_params).inputize();
+            let res = cs.borrow().gates.iter().map(|g| format!("{:?}", g)).collect();
+            res
+        }
+        assert_eq!(gates::<LC<Fr>>(), gates::<VecLC<Fr>>());
     }
 }
@@ -1,7 +1,7 @@
 #[cfg(feature = "r1cs")]
 pub mod tests {
     use fawkes_crypto::{
-        circuit::{cs::{DebugCS, CS}, poseidon::*, num::CNum},
+        circuit::{check::check_satisfied, cs::{ArcHandle, BuildCS, DebugCS, CS}, lc::VecLC, poseidon::*, num::CNum},
         core::{signal::Signal, sizedvec::SizedVec},
         engines::bn256::Fr,
         native::poseidon::{poseidon, poseidon_merkle_proof_root, MerkleProof, PoseidonParams},
@@ -29,6 +29,7 @@ pub mod tests {
 
         let res2 = poseidon(data.as_slice(), &poseidon_params);
         res.assert_const(&res2);
+        cs.borrow().assert_satisfied();
 
         println!("poseidon(4,8,54) constraints = {}", n_constraints);
         assert!(res.get_value().unwrap() == res2);
@@ -69,6 +70,7 @@ pub mod tests {
         let proof = MerkleProof { sibling, path };
         let res2 = poseidon_merkle_proof_root(leaf, &proof, &poseidon_params);
         res.assert_const(&res2);
+        cs.borrow().assert_satisfied();
 
         println!(
             "merkle root poseidon(3,8,53)x32 constraints = {}",
@@ -77,4 +79,21 @@ pub mod tests {
         println!("circuit constructing time = {} ms", elapsed.as_millis());
         assert!(res.get_value().unwrap() == res2);
 }
+
+    #[test]
+    fn test_circuit_poseidon_arc_handle() {
+        let mut rng = thread_rng();
+        let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+        let data = (0..2).map(|_| rng.gen()).collect::<SizedVec<_, 2>>();
+
+        let ref cs = BuildCS::<Fr, VecLC<Fr>, ArcHandle>::rc_new_tracking();
+        let inputs = SizedVec::<CNum<_>, 2>::alloc(cs, Some(&data));
+
+        // signals are Send, so the circuit can be continued on another thread
+        let params = poseidon_params.clone();
+        let res = std::thread::spawn(move || c_poseidon(inputs.as_slice(), &params)).join().unwrap();
+
+        assert!(res.get_value().unwrap() == poseidon(data.as_slice(), &poseidon_params));
+        assert!(check_satisfied(&*cs.borrow()).is_ok());
+    }
 }
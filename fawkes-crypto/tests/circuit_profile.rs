@@ -0,0 +1,92 @@
+#[cfg(feature = "r1cs")]
+pub mod tests {
+    use fawkes_crypto::{
+        circuit::{cs::{ProfilingCS, CS}, poseidon::*, num::CNum},
+        core::{signal::Signal, sizedvec::SizedVec},
+        engines::bn256::Fr,
+        native::poseidon::{MerkleProof, PoseidonParams},
+        rand::{thread_rng, Rng},
+    };
+
+    #[test]
+    fn test_profile_poseidon_merkle_root() {
+        const PROOF_LENGTH: usize = 4;
+
+        let mut rng = thread_rng();
+        let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+
+        let ref mut cs = ProfilingCS::rc_new();
+
+        let leaf = rng.gen();
+        let proof = MerkleProof {
+            sibling: (0..PROOF_LENGTH).map(|_| rng.gen()).collect::<SizedVec<_, PROOF_LENGTH>>(),
+            path: (0..PROOF_LENGTH).map(|_| rng.gen()).collect::<SizedVec<bool, PROOF_LENGTH>>(),
+        };
+
+        let signal_leaf = CNum::alloc(cs, Some(&leaf));
+        let signal_proof = CMerkleProof::alloc(cs, Some(&proof));
+        c_poseidon_merkle_proof_root(&signal_leaf, &signal_proof, &poseidon_params);
+
+        let cs = cs.borrow();
+        let root = &cs.profiler.root;
+        assert_eq!(root.total_gates(), cs.num_gates());
+        assert_eq!(root.total_aux(), cs.num_aux());
+
+        let merkle = &root.children[0];
+        assert_eq!(merkle.name, "c_poseidon_merkle_proof_root");
+        let poseidon = merkle.children.iter().find(|c| c.name == "c_poseidon").unwrap();
+        assert_eq!(poseidon.calls, PROOF_LENGTH);
+
+        let folded = cs.profiler.to_folded();
+        assert!(folded.contains("circuit;c_poseidon_merkle_proof_root;c_poseidon "));
+        println!("{}", cs.profiler.to_tree());
+    }
+}
+
+#[cfg(feature = "plonk")]
+#[test]
+fn test_profile_plonk() {
+    use fawkes_crypto::{
+        circuit::{
+            bitify::c_into_bits_le_lookup,
+            cs::CS,
+            num::CNum,
+            plonk::cs::{BuildCS, ProfilingCS},
+            poseidon::c_poseidon,
+        },
+        core::signal::Signal,
+        engines::bn256::Fr,
+        ff_uint::Num,
+        native::poseidon::PoseidonParams,
+        rand::{thread_rng, Rng},
+    };
+
+    // poseidon, a sum spanning several rows and lookups
+    fn circuit<C: CS<Fr = Fr>>(inputs: &[CNum<C>]) {
+        let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+        let h = c_poseidon(&inputs[..2], &poseidon_params);
+        let s = CNum::sum(inputs[0].get_cs(), inputs);
+        (h + &s).inputize();
+    }
+
+    let mut rng = thread_rng();
+    let values = (0..7).map(|_| rng.gen()).collect::<Vec<Num<Fr>>>();
+    let small = Num::<Fr>::from(rng.gen::<u32>() as u64);
+
+    let ref pcs = ProfilingCS::<Fr>::rc_new();
+    let ref bcs = BuildCS::<Fr>::rc_new(false);
+    let signals = values.iter().map(|v| CNum::alloc(pcs, Some(v))).collect::<Vec<_>>();
+    circuit(&signals);
+    c_into_bits_le_lookup(&CNum::alloc(pcs, Some(&small)), 32);
+    let signals = values.iter().map(|v| CNum::alloc(bcs, Some(v))).collect::<Vec<_>>();
+    circuit(&signals);
+    c_into_bits_le_lookup(&CNum::alloc(bcs, Some(&small)), 32);
+
+    let (pcs, bcs) = (pcs.borrow(), bcs.borrow());
+    assert!(bcs.gates.iter().any(|g| !g.g.is_zero()), "sum should be chained by rotation");
+    assert!(bcs.gates.iter().any(|g| g.table != 0), "lookup gates should be used");
+    assert_eq!(pcs.num_gates(), bcs.num_gates());
+    assert_eq!(pcs.profiler.root.total_gates(), pcs.num_gates());
+    assert_eq!(pcs.profiler.root.total_aux(), bcs.values.len());
+    assert_eq!(pcs.num_input(), 1);
+}
@@ -0,0 +1,79 @@
+// Helpers shared by the integration tests, each test crate uses a part of them
+#![allow(dead_code)]
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use fawkes_crypto::circuit::{cs::CS, num::CNum};
+use revm::{
+    primitives::{CreateScheme, ExecutionResult, Output, TransactTo, TxEnv},
+    InMemoryDB, EVM,
+};
+
+// `secret.0 * secret.1 == public`, the smallest circuit with a multiplication gate
+pub fn mul_circuit<C: CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+    (secret.0 * secret.1).assert_eq(&public);
+}
+
+// the EVM verifier tests are skipped without `solc`
+pub fn solc_available() -> bool {
+    Command::new("solc").arg("--version").output().is_ok()
+}
+
+// Compile a Solidity source with one contract, returns its deployment code
+pub fn compile_solidity(source: &str) -> Vec<u8> {
+    let mut solc = Command::new("solc")
+        .args(["--bin", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("solc is not in PATH");
+    solc.stdin.take().unwrap().write_all(source.as_bytes()).unwrap();
+    let output = solc.wait_with_output().unwrap();
+    assert!(output.status.success(), "solc failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let bin = stdout
+        .lines()
+        .skip_while(|l| !l.starts_with("Binary"))
+        .nth(1)
+        .expect("no binary in solc output")
+        .trim();
+    (0..bin.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&bin[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// Deploy the contract to an in-memory EVM and call it, returns the output of the call or the failure reason
+pub fn evm_call(deployment_code: Vec<u8>, calldata: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut evm = EVM {
+        env: Default::default(),
+        db: Some(InMemoryDB::default()),
+    };
+
+    evm.env.tx = TxEnv {
+        gas_limit: u64::MAX,
+        transact_to: TransactTo::Create(CreateScheme::Create),
+        data: deployment_code.into(),
+        ..Default::default()
+    };
+    let contract = match evm.transact_commit().map_err(|e| format!("{:?}", e))? {
+        ExecutionResult::Success { output: Output::Create(_, Some(contract)), .. } => contract,
+        res => return Err(format!("deployment failed: {:?}", res)),
+    };
+
+    evm.env.tx = TxEnv {
+        gas_limit: u64::MAX,
+        transact_to: TransactTo::Call(contract),
+        data: calldata.into(),
+        ..Default::default()
+    };
+    match evm.transact_commit().map_err(|e| format!("{:?}", e))? {
+        ExecutionResult::Success { output: Output::Call(output), .. } => Ok(output.to_vec()),
+        res => Err(format!("call failed: {:?}", res)),
+    }
+}
@@ -6,6 +6,7 @@ use fawkes_crypto::{
         setup::setup
     },
     circuit::cs::CS,
+    circuit::plonk::cs::PlonkCS,
     circuit::num::CNum,
     circuit::poseidon::{c_poseidon_merkle_proof_root, CMerkleProof},
     core::signal::Signal,
@@ -15,6 +16,9 @@ use fawkes_crypto::{
     rand::{thread_rng, Rng}
 };
 
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+mod common;
+
 
 #[cfg(all(feature = "heavy_tests", feature="plonk"))]
 #[test]
@@ -25,7 +29,7 @@ fn test_circuit_poseidon_merkle_root() {
         res.assert_eq(&public);
     }
     let parameters = Parameters::<Bn256>::setup(20);
-    let keys = setup::<_, _, _>(&parameters, circuit);
+    let keys = setup::<_, _, _, _>(&parameters, circuit);
 
     const PROOF_LENGTH: usize = 32;
     let mut rng = thread_rng();
@@ -48,3 +52,430 @@ fn test_circuit_poseidon_merkle_root() {
     assert!(res, "Verifier result should be true");
 }
 
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_seeded_setup_and_prove() {
+    use fawkes_crypto::{
+        ff_uint::Num,
+        halo2_rand::{rngs::StdRng, SeedableRng},
+    };
+
+    use crate::common::mul_circuit as circuit;
+    let parameters = Parameters::<Bn256>::setup_with_rng(10, StdRng::seed_from_u64(42));
+    let parameters2 = Parameters::<Bn256>::setup_with_rng(10, StdRng::seed_from_u64(42));
+    let (mut buf, mut buf2) = (vec![], vec![]);
+    parameters.write(&mut buf).unwrap();
+    parameters2.write(&mut buf2).unwrap();
+    assert_eq!(buf, buf2);
+
+    let keys = setup::<_, _, _, _>(&parameters, circuit);
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    let (inputs, proof) = prover::prove_with_rng(&parameters, &keys.1, &(a * b), &(a, b), circuit, StdRng::seed_from_u64(7));
+    let (_, proof2) = prover::prove_with_rng(&parameters, &keys.1, &(a * b), &(a, b), circuit, StdRng::seed_from_u64(7));
+    assert_eq!(proof.0, proof2.0);
+    assert!(verifier::verify(&parameters, &keys.0, &proof, &inputs), "Verifier result should be true");
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_keys_serialization() {
+    use fawkes_crypto::{
+        backend::plonk::setup::{ProvingKey, VerifyingKey},
+        ff_uint::Num,
+        halo2_proofs::SerdeFormat,
+    };
+
+    use crate::common::mul_circuit as circuit;
+    fn other_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        (secret.0 + secret.1).assert_eq(&public);
+    }
+    let parameters = Parameters::<Bn256>::setup(10);
+    let (vk, pk) = setup::<_, _, _, _>(&parameters, circuit);
+
+    let (mut vk_buf, mut pk_buf) = (vec![], vec![]);
+    vk.write(&mut vk_buf, SerdeFormat::RawBytes).unwrap();
+    pk.write(&mut pk_buf, SerdeFormat::RawBytes).unwrap();
+
+    let vk = VerifyingKey::read(&mut vk_buf.as_slice(), SerdeFormat::RawBytes, &parameters, circuit).unwrap();
+    let pk = ProvingKey::read(&mut pk_buf.as_slice(), SerdeFormat::RawBytes, &parameters, circuit).unwrap();
+    assert_eq!(pk.hash, vk.1);
+    // no lookup gates, so no lookup argument
+    assert!(!uses_lookup(&vk.0));
+
+    assert!(VerifyingKey::read(&mut vk_buf.as_slice(), SerdeFormat::RawBytes, &parameters, other_circuit).is_err());
+    assert!(VerifyingKey::read(&mut vk_buf.as_slice(), SerdeFormat::Processed, &parameters, circuit).is_err());
+    assert!(ProvingKey::read(&mut pk_buf.as_slice(), SerdeFormat::RawBytes, &parameters, other_circuit).is_err());
+    assert!(ProvingKey::read(&mut pk_buf.as_slice(), SerdeFormat::RawBytes, &Parameters::<Bn256>::setup(11), circuit).is_err());
+
+    // corrupted layout is detected by the hash check, the number of variables follows the 46 bytes header
+    let mut corrupted = pk_buf.clone();
+    corrupted[46] ^= 1;
+    assert!(ProvingKey::read(&mut corrupted.as_slice(), SerdeFormat::RawBytes, &parameters, circuit).is_err());
+
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    let (inputs, proof) = prover::prove(&parameters, &pk, &(a * b), &(a, b), circuit);
+    assert!(verifier::verify(&parameters, &vk, &proof, &inputs), "Verifier result should be true");
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_witness_cs_gate_layout() {
+    use fawkes_crypto::{
+        circuit::plonk::cs::BuildCS,
+        ff_uint::Num,
+    };
+
+    fn circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let poseidon_params = PoseidonParams::<C::Fr>::new(3, 8, 53);
+        fawkes_crypto::circuit::poseidon::c_poseidon(&[secret.0, secret.1], &poseidon_params).assert_eq(&public);
+    }
+    fn longer_circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        (secret.0.clone() * &secret.1).assert_nonzero();
+        circuit(public, secret);
+    }
+    let parameters = Parameters::<Bn256>::setup(10);
+    let (vk, pk) = setup::<_, _, _, _>(&parameters, circuit);
+
+    let ref rcs = BuildCS::<Fr>::rc_new(false);
+    let signal_pub = CNum::alloc(rcs, None);
+    signal_pub.inputize();
+    let signal_sec = <(CNum<_>, CNum<_>)>::alloc(rcs, None);
+    circuit(signal_pub, signal_sec);
+
+    let built = rcs.borrow().get_gate_iterator().map(|g| format!("{:?}", g)).collect::<Vec<_>>();
+    let replayed = pk.get_witness_rcs::<fawkes_crypto::circuit::cs::RcHandle>().borrow().get_gate_iterator().map(|g| format!("{:?}", g)).collect::<Vec<_>>();
+    assert_eq!(built.len(), pk.num_gates as usize);
+    assert_eq!(built, replayed);
+
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+    let hash = fawkes_crypto::native::poseidon::poseidon(&[a, b], &poseidon_params);
+    let (inputs, proof) = prover::prove(&parameters, &pk, &hash, &(a, b), circuit);
+    assert!(verifier::verify(&parameters, &vk, &proof, &inputs), "Verifier result should be true");
+
+    assert!(prover::try_prove(&parameters, &pk, &hash, &(a, b), longer_circuit).is_err());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_prove_unassigned_value() {
+    use fawkes_crypto::{ff_uint::Num, Error};
+
+    // the value of `extra` is never assigned, even at proving time
+    fn circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+        let extra = CNum::alloc(public.get_cs(), None);
+        (secret.0 * secret.1 + &extra).assert_eq(&public);
+    }
+    let parameters = Parameters::<Bn256>::setup(10);
+    let (_, pk) = setup::<_, _, _, _>(&parameters, circuit);
+
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    match prover::try_prove(&parameters, &pk, &(a * b), &(a, b), circuit) {
+        Err(Error::Synthesis(msg)) => assert!(msg.contains("is not assigned"), "{}", msg),
+        _ => panic!("missing value should be reported"),
+    }
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_evm_verifier() {
+    use fawkes_crypto::{
+        backend::plonk::solidity::*,
+        ff_uint::{Num, Uint},
+    };
+
+    // `compile_yul` runs `solc`
+    if !common::solc_available() {
+        eprintln!("skipping test_evm_verifier: solc is not in PATH");
+        return;
+    }
+
+    use crate::common::mul_circuit as circuit;
+    let parameters = Parameters::<Bn256>::setup(10);
+    let (vk, pk) = setup::<_, _, _, _>(&parameters, circuit);
+
+    let code = generate_verifier(&parameters, &vk, 1).unwrap();
+    assert!(code.contains("object"));
+
+    let a = Num::<Fr>::from(3);
+    let b = Num::<Fr>::from(5);
+    let (inputs, proof) = prover::prove(&parameters, &pk, &(a * b), &(a, b), circuit);
+    assert!(verifier::verify(&parameters, &vk, &proof, &inputs), "Verifier result should be true");
+
+    let calldata = encode_calldata(&proof, &inputs);
+    assert_eq!(calldata.len(), 32 + proof.0.len());
+    assert_eq!(&calldata[..32], &inputs[0].to_uint().0.to_big_endian()[..]);
+    assert_eq!(&calldata[32..], &proof.0[..]);
+
+    // the verifier reverts on an invalid proof
+    let deployment_code = fawkes_crypto::plonk_verifier::loader::evm::compile_yul(&code);
+    assert!(common::evm_call(deployment_code.clone(), calldata).is_ok());
+    let tampered = encode_calldata(&proof, &[inputs[0] + Num::ONE]);
+    assert!(common::evm_call(deployment_code, tampered).is_err());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_custom_gates() {
+    use fawkes_crypto::{
+        circuit::plonk::{check::check_satisfied, cs::BuildCS},
+        ff_uint::Num,
+        native::poseidon::poseidon,
+    };
+
+    let ref rcs = BuildCS::<Fr>::rc_new(true);
+    let mut rng = thread_rng();
+
+    let x: Num<Fr> = rng.gen();
+    let signal_x = CNum::alloc(rcs, Some(&x));
+    let k: Num<Fr> = rng.gen();
+    let c: Num<Fr> = rng.gen();
+    let p = (signal_x.clone() * k + c).pow5();
+    let base = x * k + c;
+    assert_eq!(p.get_value(), Some(base * base * base * base * base));
+
+    let items = (0..7).map(|_| CNum::alloc(rcs, Some(&rng.gen()))).collect::<Vec<_>>();
+    let s = CNum::sum(rcs, &items);
+    assert_eq!(s.get_value(), Some(items.iter().fold(Num::ZERO, |s, x| s + x.get_value().unwrap())));
+    assert_eq!(CNum::sum(rcs, &[]).as_const(), Some(Num::ZERO));
+
+    let inputs = (0..2).map(|_| rng.gen()).collect::<Vec<Num<Fr>>>();
+    let signal_inputs = inputs.iter().map(|x| CNum::alloc(rcs, Some(x))).collect::<Vec<_>>();
+    let poseidon_params = PoseidonParams::<Fr>::new(3, 8, 53);
+    let h = fawkes_crypto::circuit::poseidon::c_poseidon(&signal_inputs, &poseidon_params);
+    assert_eq!(h.get_value(), Some(poseidon(&inputs, &poseidon_params)));
+
+    // the sum of 7 items takes three rows chained by rotation
+    assert!(rcs.borrow().gates.iter().any(|g| g.pow5));
+    assert!(rcs.borrow().gates.iter().any(|g| !g.g.is_zero()));
+    assert!(check_satisfied(&*rcs.borrow()).is_ok());
+
+    let var = s.lc.1;
+    rcs.borrow_mut().values[var] = Some(Num::ONE);
+    assert!(check_satisfied(&*rcs.borrow()).is_err());
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_lookup() {
+    use fawkes_crypto::{
+        circuit::{
+            bitify::{c_assert_range_lookup, c_into_bits_le, c_into_bits_le_lookup},
+            plonk::{check::check_satisfied, cs::{BuildCS, LookupTable}},
+        },
+        backend::plonk::setup::{ProvingKey, VerifyingKey},
+        ff_uint::{BitIterLE, Num},
+        halo2_proofs::SerdeFormat,
+    };
+
+    let ref rcs = BuildCS::<Fr>::rc_new(true);
+    let mut rng = thread_rng();
+
+    let x = Num::<Fr>::from(rng.gen::<u64>() >> 1);
+    let signal_x = CNum::alloc(rcs, Some(&x));
+    let bits = c_into_bits_le_lookup(&signal_x, 63);
+    let expected = x.bit_iter_le().take(63).collect::<Vec<_>>();
+    assert_eq!(bits.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), expected);
+    c_assert_range_lookup(&signal_x, 63);
+
+    let (a, b) = (CNum::alloc(rcs, Some(&Num::from(9))), CNum::alloc(rcs, Some(&Num::from(12))));
+    let c = CNum::alloc(rcs, Some(&Num::from(9 ^ 12)));
+    PlonkCS::enforce_lookup(LookupTable::Xor4, &[a, b, c]);
+    assert!(check_satisfied(&*rcs.borrow()).is_ok());
+
+    // one lookup per bit instead of a bit check and an addition
+    let lookup_gates = rcs.borrow().num_gates();
+    let ref rcs2 = BuildCS::<Fr>::rc_new(true);
+    c_into_bits_le(&CNum::alloc(rcs2, Some(&x)), 63);
+    assert!(lookup_gates < rcs2.borrow().num_gates());
+
+    let wrong = CNum::alloc(rcs, Some(&Num::from(256)));
+    rcs.borrow_mut().tracking = false;
+    c_assert_range_lookup(&wrong, 8);
+    assert!(check_satisfied(&*rcs.borrow()).is_err());
+
+    fn circuit<C:PlonkCS>(public: CNum<C>, secret: CNum<C>) {
+        let bits = c_into_bits_le_lookup(&secret, 10);
+        fawkes_crypto::circuit::bitify::c_from_bits_le(&bits).assert_eq(&public);
+        c_assert_range_lookup(&secret, 10);
+    }
+    let parameters = Parameters::<Bn256>::setup(11);
+    let (vk, pk) = setup::<_, _, _, _>(&parameters, circuit);
+    assert!(uses_lookup(&vk.0));
+
+    // the lookup configuration is restored from the key header
+    let (mut vk_buf, mut pk_buf) = (vec![], vec![]);
+    vk.write(&mut vk_buf, SerdeFormat::RawBytes).unwrap();
+    pk.write(&mut pk_buf, SerdeFormat::RawBytes).unwrap();
+    let vk = VerifyingKey::read(&mut vk_buf.as_slice(), SerdeFormat::RawBytes, &parameters, circuit).unwrap();
+    let pk = ProvingKey::read(&mut pk_buf.as_slice(), SerdeFormat::RawBytes, &parameters, circuit).unwrap();
+
+    let v = Num::<Fr>::from(777);
+    let (inputs, proof) = prover::prove(&parameters, &pk, &v, &v, circuit);
+    assert!(verifier::verify(&parameters, &vk, &proof, &inputs), "Verifier result should be true");
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_prove_on_threads() {
+    use fawkes_crypto::{circuit::cs::ArcHandle, ff_uint::Num};
+
+    use crate::common::mul_circuit as circuit;
+    let parameters = Parameters::<Bn256>::setup(10);
+    let (vk, pk) = setup::<_, _, _, _>(&parameters, circuit);
+
+    std::thread::scope(|s| {
+        let workers = (1..5u64).map(|i| {
+            let (parameters, pk) = (&parameters, &pk);
+            s.spawn(move || {
+                let (a, b) = (Num::<Fr>::from(i), Num::<Fr>::from(i + 1));
+                let ref rcs = pk.get_witness_rcs::<ArcHandle>();
+                let signal_pub = CNum::alloc(rcs, Some(&(a * b)));
+                signal_pub.inputize();
+                let signal_sec = <(CNum<_>, CNum<_>)>::alloc(rcs, Some(&(a, b)));
+                circuit(signal_pub, signal_sec);
+                prover::prove_cs(parameters, pk, rcs)
+            })
+        }).collect::<Vec<_>>();
+
+        for w in workers {
+            let (inputs, proof) = w.join().unwrap();
+            assert!(verifier::verify(&parameters, &vk, &proof, &inputs), "Verifier result should be true");
+        }
+    });
+}
+
+#[cfg(all(feature = "heavy_tests", feature="plonk"))]
+#[test]
+fn test_aggregation() {
+    use fawkes_crypto::{
+        backend::plonk::aggregation::{self, Snark},
+        ff_uint::Num,
+    };
+
+    use crate::common::mul_circuit as circuit;
+    // the same SRS is used for the inner proofs and the aggregation circuit
+    let parameters = Parameters::<Bn256>::setup(22);
+    let (vk, pk) = setup::<_, _, _, _>(&parameters, circuit);
+
+    let mut rng = thread_rng();
+    let mut snark_inputs = vec![];
+    let snarks = (0..2).map(|_| {
+        let a: Num<Fr> = rng.gen();
+        let b: Num<Fr> = rng.gen();
+        let (inputs, proof) = aggregation::prove_snark(&parameters, &pk, &(a * b), &(a, b), circuit).unwrap();
+        snark_inputs.extend(inputs.iter().cloned());
+        Snark::new(&parameters, &vk, &inputs, &proof)
+    }).collect::<Vec<_>>();
+
+    let keys = aggregation::setup(&parameters, &snarks).unwrap();
+    let (inputs, proof) = aggregation::aggregate_snarks(&parameters, &keys, &snarks).unwrap();
+    // the inputs of the snarks follow the accumulator limbs
+    assert_eq!(inputs.len(), 4 * aggregation::LIMBS + snark_inputs.len());
+    assert_eq!(&inputs[4 * aggregation::LIMBS..], snark_inputs.as_slice());
+    assert!(aggregation::verify(&parameters, &keys, &proof, &inputs).unwrap());
+
+    let mut wrong_accumulator = inputs.clone();
+    wrong_accumulator[0] += Num::ONE;
+    assert!(!aggregation::verify(&parameters, &keys, &proof, &wrong_accumulator).unwrap());
+
+    // the proof does not hold for another statement of an inner snark
+    let mut wrong_snark_input = inputs.clone();
+    wrong_snark_input[4 * aggregation::LIMBS + 1] += Num::ONE;
+    assert!(!aggregation::verify(&parameters, &keys, &proof, &wrong_snark_input).unwrap());
+
+    assert!(aggregation::verify(&parameters, &keys, &proof, &inputs[..4 * aggregation::LIMBS]).is_err());
+}
+
+#[cfg(feature="plonk")]
+#[test]
+fn test_check_unsatisfied_gate() {
+    use fawkes_crypto::{
+        circuit::{cs::CS, num::CNum, plonk::{check::{check_satisfied, check_satisfied_all}, cs::{BuildCS, PlonkCS}}},
+        core::signal::Signal,
+        engines::bn256::Fr,
+        ff_uint::Num,
+    };
+
+    let ref rcs = BuildCS::<Fr>::rc_new(false);
+    let x = CNum::alloc(rcs, Some(&Num::from(3)));
+    let y = CNum::alloc(rcs, Some(&Num::from(4)));
+    let s = CNum::alloc(rcs, Some(&Num::from(7)));
+    let p = CNum::alloc(rcs, Some(&Num::from(13)));
+    PlonkCS::enforce_add(&x, &y, &s);
+    PlonkCS::enforce_mul(&x, &y, &p);
+    PlonkCS::enforce_add(&x, &s, &p);
+
+    let cs = rcs.borrow();
+    assert_eq!(cs.num_gates(), 3);
+    let e = check_satisfied(&*cs).unwrap_err();
+    assert_eq!(e.index, 1);
+    assert_eq!((e.gate.x, e.gate.y, e.gate.z), (x.lc.1, y.lc.1, p.lc.1));
+    assert_eq!((e.x, e.y, e.z), (Some(Num::from(3)), Some(Num::from(4)), Some(Num::from(13))));
+    assert_eq!(e.w, None);
+    assert!(e.to_string().starts_with("gate 1 is not satisfied"));
+
+    // 3 + 7 == 13 is not satisfied either
+    assert_eq!(check_satisfied_all(&*cs).iter().map(|e| e.index).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[cfg(feature="plonk")]
+#[test]
+fn test_debug_cs() {
+    use fawkes_crypto::{
+        circuit::{cs::{CS, RCS}, num::CNum, poseidon::c_poseidon, plonk::{check::check_satisfied_all, cs::{BuildCS, DebugCS, PlonkCS}}},
+        core::signal::Signal,
+        engines::bn256::Fr,
+        ff_uint::Num,
+        native::poseidon::{poseidon, PoseidonParams},
+    };
+
+    // poseidon uses sums over several rows and S-boxes, the last constraint is not satisfied
+    fn circuit<C: PlonkCS<Fr = Fr>>(rcs: &RCS<C>) {
+        let params = PoseidonParams::<Fr>::new(3, 8, 53);
+        let inputs = [Num::from(1), Num::from(2)];
+        let hash = poseidon(&inputs, &params);
+        let inputs = inputs.iter().map(|v| CNum::alloc(rcs, Some(v))).collect::<Vec<_>>();
+        let public = CNum::alloc(rcs, Some(&hash));
+        public.inputize();
+        let res = c_poseidon(&inputs, &params);
+        res.assert_eq(&public);
+        (res + &Num::ONE).assert_eq(&public);
+    }
+
+    let ref build = BuildCS::<Fr>::rc_new(false);
+    circuit(build);
+    let ref debug = DebugCS::<Fr>::rc_collecting();
+    circuit(debug);
+
+    let (build, debug) = (build.borrow(), debug.borrow());
+    assert_eq!(debug.num_gates(), build.num_gates());
+    assert_eq!(debug.as_public(), build.as_public());
+    assert!(debug.unassigned.is_none());
+    let expected = check_satisfied_all(&*build).iter().map(|e| e.index).collect::<Vec<_>>();
+    assert_eq!(expected.len(), 1);
+    assert_eq!(debug.unsatisfied.iter().map(|e| e.index).collect::<Vec<_>>(), expected);
+    assert_eq!(debug.first_unsatisfied().unwrap().to_string(), check_satisfied_all(&*build)[0].to_string());
+}
+
+#[cfg(feature="plonk")]
+#[test]
+#[should_panic(expected = "is not satisfied")]
+fn test_debug_cs_panics() {
+    use fawkes_crypto::{
+        circuit::{num::CNum, plonk::cs::DebugCS},
+        core::signal::Signal,
+        engines::bn256::Fr,
+        ff_uint::Num,
+    };
+
+    let ref rcs = DebugCS::<Fr>::rc_new();
+    let a = CNum::alloc(rcs, Some(&Num::from(2)));
+    let b = CNum::alloc(rcs, Some(&Num::from(3)));
+    (a * b).assert_eq(&CNum::from_const(rcs, &Num::from(7)));
+}
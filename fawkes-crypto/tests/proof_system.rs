@@ -0,0 +1,70 @@
+#[cfg(all(feature = "heavy_tests", any(feature = "backend_bellman_groth16", feature = "plonk")))]
+use fawkes_crypto::{
+    backend::ProofSystem,
+    circuit::cs::CS,
+    circuit::num::CNum,
+    circuit::poseidon::c_poseidon,
+    core::signal::Signal,
+    engines::bn256::Fr,
+    ff_uint::Num,
+    native::poseidon::{poseidon, PoseidonParams},
+    rand::{thread_rng, Rng}
+};
+
+#[cfg(all(feature = "heavy_tests", any(feature = "backend_bellman_groth16", feature = "plonk")))]
+fn circuit<C:CS>(public: CNum<C>, secret: (CNum<C>, CNum<C>)) {
+    let poseidon_params = PoseidonParams::<C::Fr>::new(3, 8, 53);
+    // public input which is not a variable in PLONK
+    (secret.0.clone() * &Num::from(2) + &Num::ONE).inputize();
+    c_poseidon(&[secret.0, secret.1], &poseidon_params).assert_eq(&public);
+}
+
+// setup, prove and verify through the serialized keys and proof
+#[cfg(all(feature = "heavy_tests", any(feature = "backend_bellman_groth16", feature = "plonk")))]
+fn check_proof_system<P: ProofSystem<Fr = Fr>>(params: &P::Params) {
+    let (vk, pk) = P::setup(params, circuit::<P::SetupCS>);
+
+    let mut buf = vec![];
+    P::write_params(params, &mut buf).unwrap();
+    let params = P::read_params(&mut buf.as_slice()).unwrap();
+
+    let mut vk_buf = vec![];
+    let mut pk_buf = vec![];
+    P::write_verifying_key(&vk, &mut vk_buf).unwrap();
+    P::write_proving_key(&pk, &mut pk_buf).unwrap();
+    let vk = P::read_verifying_key(&mut vk_buf.as_slice(), &params, circuit::<P::SetupCS>).unwrap();
+    let pk = P::read_proving_key(&mut pk_buf.as_slice(), &params, circuit::<P::SetupCS>).unwrap();
+
+    let mut rng = thread_rng();
+    let secret: (Num<Fr>, Num<Fr>) = (rng.gen(), rng.gen());
+    let hash = poseidon(&[secret.0, secret.1], &PoseidonParams::<Fr>::new(3, 8, 53));
+
+    let (inputs, proof) = P::prove(&params, &pk, &hash, &secret, circuit::<P::WitnessCS<'_>>);
+    assert_eq!(inputs, vec![hash, secret.0 * Num::from(2) + Num::ONE]);
+    assert_eq!(P::compute_public_inputs(circuit::<P::DebugCS>, &hash, &secret).unwrap(), inputs);
+    assert!(P::compute_public_inputs(circuit::<P::DebugCS>, &(hash + Num::ONE), &secret).is_err());
+
+    let mut proof_buf = vec![];
+    P::write_proof(&proof, &mut proof_buf).unwrap();
+    let proof = P::read_proof(&mut proof_buf.as_slice()).unwrap();
+
+    assert!(P::verify(&params, &vk, &proof, &inputs), "Verifier result should be true");
+    assert!(!P::verify(&params, &vk, &proof, &[hash + Num::ONE, inputs[1]]));
+    assert!(!P::verify(&params, &vk, &proof, &inputs[..1]));
+}
+
+#[cfg(all(feature = "heavy_tests", feature = "backend_bellman_groth16"))]
+#[test]
+fn test_groth16_proof_system() {
+    use fawkes_crypto::backend::bellman_groth16::{engines::Bn256, Groth16};
+
+    check_proof_system::<Groth16<Bn256>>(&());
+}
+
+#[cfg(all(feature = "heavy_tests", feature = "plonk"))]
+#[test]
+fn test_plonk_proof_system() {
+    use fawkes_crypto::backend::plonk::{engines::Bn256, Parameters, Plonk};
+
+    check_proof_system::<Plonk<Bn256>>(&Parameters::<Bn256>::setup(10));
+}